@@ -0,0 +1,75 @@
+/* C interface to diskhound's directory scanner, built by the diskhound-ffi
+ * crate as libdiskhound_ffi (shared and static).
+ *
+ * Paths, in both directions, are the file system's bytes on Unix, so names
+ * that aren't valid UTF-8 still open; on Windows they are UTF-8. */
+
+#ifndef DISKHOUND_H
+#define DISKHOUND_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A finished scan, freed with diskhound_scan_free. */
+typedef struct DiskhoundScan DiskhoundScan;
+
+/* One directory of a scan. path is relative to the scanned root and stays
+ * valid until the scan is freed. */
+typedef struct DiskhoundEntry {
+    const char *path;
+    uint64_t size;
+    uint64_t file_count;
+} DiskhoundEntry;
+
+/* Scans root, keeping every directory from 1 to depth levels below it
+ * (0 = every directory), largest first. walk_threads is as for
+ * --walk-threads (0 = one per CPU, 1 = serial). Returns NULL on failure;
+ * see diskhound_last_error. */
+DiskhoundScan *diskhound_scan_new(const char *root, uint32_t depth, uint32_t walk_threads);
+
+/* Called with the files and bytes counted so far. */
+typedef void (*DiskhoundProgressFn)(uint64_t files, uint64_t bytes, void *context);
+
+/* diskhound_scan_new, calling progress (if not NULL) with context every
+ * thousand files and once at the end, on the calling thread. */
+DiskhoundScan *diskhound_scan_new_with_progress(const char *root, uint32_t depth,
+                                                uint32_t walk_threads,
+                                                DiskhoundProgressFn progress, void *context);
+
+/* The number of directories in scan. */
+size_t diskhound_scan_len(const DiskhoundScan *scan);
+
+/* Total size in bytes and number of files below the scanned root. */
+uint64_t diskhound_scan_total_size(const DiskhoundScan *scan);
+uint64_t diskhound_scan_total_files(const DiskhoundScan *scan);
+
+/* Fills out with the directory at index (0 = largest). Returns 0, or -1 if
+ * index is out of range; see diskhound_last_error. */
+int diskhound_scan_entry(const DiskhoundScan *scan, size_t index, DiskhoundEntry *out);
+
+/* Called for every regular file, with its path relative to the root (valid
+ * during the call) and size. */
+typedef void (*DiskhoundFileFn)(const char *path, uint64_t size, void *context);
+
+/* Walks root and calls on_file with context for every regular file, on the
+ * calling thread, without keeping anything. Returns 0, or -1 on failure; see
+ * diskhound_last_error. */
+int diskhound_walk_files(const char *root, uint32_t walk_threads, DiskhoundFileFn on_file,
+                         void *context);
+
+/* Frees a scan and the entry paths read from it. NULL is ignored. */
+void diskhound_scan_free(DiskhoundScan *scan);
+
+/* Why the last failing call on this thread failed, or NULL. Valid
+ * until the next failing call on the same thread. */
+const char *diskhound_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* DISKHOUND_H */
@@ -0,0 +1,451 @@
+//! C ABI over [`diskhound::scan_with`]: a scan is an opaque handle holding
+//! the directories down to a chosen depth with their cumulative sizes, read
+//! back one entry at a time. See `include/diskhound.h` for the C side.
+
+use diskhound::{scan_with, Event, ScanOptions};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// A finished scan. Owned by the caller until `diskhound_scan_free`.
+pub struct DiskhoundScan {
+    entries: Vec<Entry>,
+    total_size: u64,
+    total_files: u64,
+}
+
+struct Entry {
+    path: CString,
+    size: u64,
+    file_count: u64,
+}
+
+/// One directory of a scan. `path` is relative to the scanned root and
+/// stays valid until the scan is freed.
+#[repr(C)]
+pub struct DiskhoundEntry {
+    pub path: *const c_char,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// The path `root` names, or `None` (with the error set). On Unix that's
+/// its bytes as they are; elsewhere it must be UTF-8.
+///
+/// # Safety
+///
+/// `root` must be NULL or a valid NUL-terminated string.
+unsafe fn root_path(root: *const c_char) -> Option<PathBuf> {
+    if root.is_null() {
+        set_error("root is NULL".to_string());
+        return None;
+    }
+    // SAFETY: the caller passes a valid C string.
+    let root = unsafe { CStr::from_ptr(root) };
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(root.to_bytes())))
+    }
+    #[cfg(not(unix))]
+    match root.to_str() {
+        Ok(root) => Some(PathBuf::from(root)),
+        Err(_) => {
+            set_error("root is not valid UTF-8".to_string());
+            None
+        }
+    }
+}
+
+/// `path` for C: its bytes on Unix (where it can't contain NUL), lossily
+/// converted to UTF-8 elsewhere.
+fn c_path(path: &Path) -> CString {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().replace('\0', " ").into_bytes();
+    CString::new(bytes).unwrap_or_default()
+}
+
+/// Called with the files and bytes counted so far.
+pub type DiskhoundProgressFn = extern "C" fn(files: u64, bytes: u64, context: *mut c_void);
+
+/// Called for every regular file, with its path (valid during the call) and
+/// size.
+pub type DiskhoundFileFn = extern "C" fn(path: *const c_char, size: u64, context: *mut c_void);
+
+/// Files between two progress calls.
+const PROGRESS_INTERVAL: u64 = 1000;
+
+/// Scans `root`, keeping every directory from 1 to `depth` levels below it
+/// (0 = every directory), largest first. `walk_threads` is as for
+/// `--walk-threads`. Returns NULL on failure; see `diskhound_last_error`.
+///
+/// # Safety
+///
+/// `root` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_scan_new(
+    root: *const c_char,
+    depth: u32,
+    walk_threads: u32,
+) -> *mut DiskhoundScan {
+    // SAFETY: passed through from the caller.
+    unsafe {
+        diskhound_scan_new_with_progress(root, depth, walk_threads, None, std::ptr::null_mut())
+    }
+}
+
+/// `diskhound_scan_new`, calling `progress` (if not NULL) with `context`
+/// every thousand files and once at the end, on the calling thread.
+///
+/// # Safety
+///
+/// `root` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_scan_new_with_progress(
+    root: *const c_char,
+    depth: u32,
+    walk_threads: u32,
+    progress: Option<DiskhoundProgressFn>,
+    context: *mut c_void,
+) -> *mut DiskhoundScan {
+    // SAFETY: passed through from the caller.
+    let Some(root) = (unsafe { root_path(root) }) else {
+        return std::ptr::null_mut();
+    };
+    let max_depth = match depth {
+        0 => usize::MAX,
+        n => n as usize,
+    };
+    let options = ScanOptions {
+        walk_threads: walk_threads as usize,
+        ..ScanOptions::default()
+    };
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut scan = DiskhoundScan {
+            entries: Vec::new(),
+            total_size: 0,
+            total_files: 0,
+        };
+        let (mut files, mut bytes) = (0u64, 0u64);
+        scan_with(&root, &options, |event| {
+            if let (Event::File { size, .. }, Some(progress)) = (&event, progress) {
+                files += 1;
+                bytes += size;
+                if files % PROGRESS_INTERVAL == 0 {
+                    progress(files, bytes, context);
+                }
+            }
+            let Event::DirComplete {
+                path,
+                depth,
+                size,
+                file_count,
+            } = event
+            else {
+                return;
+            };
+            if depth == 0 {
+                scan.total_size = size;
+                scan.total_files = file_count;
+            } else if depth <= max_depth {
+                let relative = path.strip_prefix(&root).unwrap_or(path);
+                scan.entries.push(Entry {
+                    path: c_path(relative),
+                    size,
+                    file_count,
+                });
+            }
+        })?;
+        if let Some(progress) = progress {
+            progress(files, bytes, context);
+        }
+        scan.entries.sort_by_key(|e| std::cmp::Reverse(e.size));
+        anyhow::Ok(scan)
+    }));
+    match result {
+        Ok(Ok(scan)) => Box::into_raw(Box::new(scan)),
+        Ok(Err(err)) => {
+            set_error(format!("{err:#}"));
+            std::ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("scan panicked".to_string());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Walks `root` and calls `on_file` with `context` for every regular file,
+/// on the calling thread, without keeping anything. Returns 0, or -1 on
+/// failure; see `diskhound_last_error`.
+///
+/// # Safety
+///
+/// `root` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_walk_files(
+    root: *const c_char,
+    walk_threads: u32,
+    on_file: DiskhoundFileFn,
+    context: *mut c_void,
+) -> c_int {
+    // SAFETY: passed through from the caller.
+    let Some(root) = (unsafe { root_path(root) }) else {
+        return -1;
+    };
+    let options = ScanOptions {
+        walk_threads: walk_threads as usize,
+        ..ScanOptions::default()
+    };
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        scan_with(&root, &options, |event| {
+            if let Event::File { path, size, .. } = event {
+                let relative = path.strip_prefix(&root).unwrap_or(path);
+                on_file(c_path(relative).as_ptr(), size, context);
+            }
+        })
+    }));
+    match result {
+        Ok(Ok(())) => 0,
+        Ok(Err(err)) => {
+            set_error(format!("{err:#}"));
+            -1
+        }
+        Err(_) => {
+            set_error("walk panicked".to_string());
+            -1
+        }
+    }
+}
+
+/// The number of directories in `scan`.
+///
+/// # Safety
+///
+/// `scan` must come from `diskhound_scan_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_scan_len(scan: *const DiskhoundScan) -> usize {
+    // SAFETY: the caller passes a live handle.
+    unsafe { scan.as_ref() }.map_or(0, |scan| scan.entries.len())
+}
+
+/// Total size in bytes of the files below the scanned root.
+///
+/// # Safety
+///
+/// `scan` must come from `diskhound_scan_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_scan_total_size(scan: *const DiskhoundScan) -> u64 {
+    // SAFETY: the caller passes a live handle.
+    unsafe { scan.as_ref() }.map_or(0, |scan| scan.total_size)
+}
+
+/// Number of files below the scanned root.
+///
+/// # Safety
+///
+/// `scan` must come from `diskhound_scan_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_scan_total_files(scan: *const DiskhoundScan) -> u64 {
+    // SAFETY: the caller passes a live handle.
+    unsafe { scan.as_ref() }.map_or(0, |scan| scan.total_files)
+}
+
+/// Fills `out` with the directory at `index` (0 = largest). Returns 0, or
+/// -1 if `index` is out of range; see `diskhound_last_error`.
+///
+/// # Safety
+///
+/// `scan` must come from `diskhound_scan_new` and not be freed yet, and
+/// `out` must point to writable memory for one `DiskhoundEntry`.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_scan_entry(
+    scan: *const DiskhoundScan,
+    index: usize,
+    out: *mut DiskhoundEntry,
+) -> c_int {
+    // SAFETY: the caller passes a live handle.
+    let Some(entry) = unsafe { scan.as_ref() }.and_then(|scan| scan.entries.get(index)) else {
+        set_error(format!("no entry at index {index}"));
+        return -1;
+    };
+    if out.is_null() {
+        set_error("out is NULL".to_string());
+        return -1;
+    }
+    // SAFETY: the caller passes writable memory for one entry.
+    unsafe {
+        out.write(DiskhoundEntry {
+            path: entry.path.as_ptr(),
+            size: entry.size,
+            file_count: entry.file_count,
+        })
+    };
+    0
+}
+
+/// Frees a scan and the entry paths read from it. NULL is ignored.
+///
+/// # Safety
+///
+/// `scan` must come from `diskhound_scan_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn diskhound_scan_free(scan: *mut DiskhoundScan) {
+    if !scan.is_null() {
+        // SAFETY: the handle came from Box::into_raw in diskhound_scan_new.
+        drop(unsafe { Box::from_raw(scan) });
+    }
+}
+
+/// Why the last failing call on this thread failed, or NULL. Valid
+/// until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn diskhound_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(std::ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_tree(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("diskhound-ffi-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("big/inner")).unwrap();
+        std::fs::create_dir_all(root.join("small")).unwrap();
+        std::fs::write(root.join("big/inner/a"), [0u8; 300]).unwrap();
+        std::fs::write(root.join("big/b"), [0u8; 100]).unwrap();
+        std::fs::write(root.join("small/c"), [0u8; 10]).unwrap();
+        root
+    }
+
+    fn last_error() -> String {
+        let error = diskhound_last_error();
+        assert!(!error.is_null());
+        // SAFETY: non-null errors are valid C strings.
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn scan_entries_and_free() {
+        let root = temp_tree("scan");
+        let c = c_path(&root);
+        extern "C" fn count(files: u64, _bytes: u64, context: *mut c_void) {
+            // SAFETY: `context` is the `u64` below.
+            unsafe { *context.cast::<u64>() = files };
+        }
+        let mut reported = 0u64;
+        // SAFETY: a valid root, and `reported` outlives the call.
+        let scan = unsafe {
+            diskhound_scan_new_with_progress(
+                c.as_ptr(),
+                1,
+                1,
+                Some(count),
+                (&raw mut reported).cast(),
+            )
+        };
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(!scan.is_null());
+        // Small scans still get the final progress call.
+        assert_eq!(reported, 3);
+
+        // SAFETY: `scan` is live until freed at the end.
+        unsafe {
+            assert_eq!(diskhound_scan_len(scan), 2);
+            assert_eq!(diskhound_scan_total_size(scan), 410);
+            assert_eq!(diskhound_scan_total_files(scan), 3);
+            let mut entry = DiskhoundEntry {
+                path: std::ptr::null(),
+                size: 0,
+                file_count: 0,
+            };
+            assert_eq!(diskhound_scan_entry(scan, 0, &mut entry), 0);
+            assert_eq!(CStr::from_ptr(entry.path).to_str(), Ok("big"));
+            assert_eq!((entry.size, entry.file_count), (400, 2));
+            assert_eq!(diskhound_scan_entry(scan, 1, &mut entry), 0);
+            assert_eq!(CStr::from_ptr(entry.path).to_str(), Ok("small"));
+
+            assert_eq!(diskhound_scan_entry(scan, 2, &mut entry), -1);
+            assert_eq!(last_error(), "no entry at index 2");
+            assert_eq!(diskhound_scan_entry(scan, 0, std::ptr::null_mut()), -1);
+            assert_eq!(last_error(), "out is NULL");
+            diskhound_scan_free(scan);
+            diskhound_scan_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn failures_set_the_last_error() {
+        let missing = CString::new("/nonexistent/diskhound-ffi").unwrap();
+        // SAFETY: valid or NULL C strings.
+        unsafe {
+            assert!(diskhound_scan_new(missing.as_ptr(), 0, 1).is_null());
+            assert!(last_error().starts_with("failed to read /nonexistent/diskhound-ffi"));
+            assert!(diskhound_scan_new(std::ptr::null(), 0, 1).is_null());
+            assert_eq!(last_error(), "root is NULL");
+            extern "C" fn ignore(_: *const c_char, _: u64, _: *mut c_void) {}
+            assert_eq!(
+                diskhound_walk_files(missing.as_ptr(), 1, ignore, std::ptr::null_mut()),
+                -1
+            );
+            assert!(last_error().starts_with("failed to read"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_keep_their_bytes() {
+        use std::ffi::{OsStr, OsString};
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+        let name = [
+            b"diskhound-ffi-\xfd-",
+            std::process::id().to_string().as_bytes(),
+        ]
+        .concat();
+        let root = std::env::temp_dir().join(OsString::from_vec(name));
+        std::fs::create_dir_all(root.join(OsStr::from_bytes(b"\xff"))).unwrap();
+        std::fs::write(root.join(OsStr::from_bytes(b"\xff/f\xfe")), b"x").unwrap();
+        let c = c_path(&root);
+
+        extern "C" fn collect(path: *const c_char, _size: u64, context: *mut c_void) {
+            // SAFETY: `context` is the Vec below, and `path` a C string.
+            let path = unsafe { CStr::from_ptr(path) }.to_bytes().to_vec();
+            unsafe { (*context.cast::<Vec<Vec<u8>>>()).push(path) };
+        }
+        let mut files: Vec<Vec<u8>> = Vec::new();
+        // SAFETY: a valid C string, and `files` outlives the call.
+        let walked =
+            unsafe { diskhound_walk_files(c.as_ptr(), 1, collect, (&raw mut files).cast()) };
+        assert_eq!(walked, 0);
+        assert_eq!(files, [b"\xff/f\xfe".to_vec()]);
+
+        // SAFETY: a valid C string; the scan is live until freed.
+        unsafe {
+            let scan = diskhound_scan_new(c.as_ptr(), 0, 1);
+            assert!(!scan.is_null());
+            let mut entry: DiskhoundEntry = std::mem::zeroed();
+            assert_eq!(diskhound_scan_entry(scan, 0, &mut entry), 0);
+            let path = CStr::from_ptr(entry.path).to_bytes();
+            assert_eq!(path, b"\xff");
+            // What a caller gets back opens the same directory.
+            assert!(root.join(OsStr::from_bytes(path)).is_dir());
+            diskhound_scan_free(scan);
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
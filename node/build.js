@@ -0,0 +1,16 @@
+'use strict';
+
+// Builds the addon crate in release mode and copies the library next to
+// index.js as diskhound.node.
+
+const { execFileSync } = require('child_process');
+const fs = require('fs');
+const path = require('path');
+
+execFileSync('cargo', ['build', '--release', '-p', 'diskhound-node'], {
+  cwd: __dirname,
+  stdio: 'inherit',
+});
+const target = path.resolve(__dirname, process.env.CARGO_TARGET_DIR || path.join('..', 'target'));
+const library = process.platform === 'darwin' ? 'libdiskhound_node.dylib' : 'libdiskhound_node.so';
+fs.copyFileSync(path.join(target, 'release', library), path.join(__dirname, 'diskhound.node'));
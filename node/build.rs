@@ -0,0 +1,8 @@
+// Node-API functions are resolved from the node (or Electron) process that
+// loads the addon, so the macOS linker has to leave them undefined.
+fn main() {
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("macos") {
+        println!("cargo:rustc-cdylib-link-arg=-undefined");
+        println!("cargo:rustc-cdylib-link-arg=dynamic_lookup");
+    }
+}
@@ -0,0 +1,66 @@
+export interface Progress {
+  /** Files and bytes counted so far, every thousand files and once at the end. */
+  files: number;
+  bytes: number;
+}
+
+export interface Options {
+  /** Directory levels to keep below the root (default 1, 0 = all). */
+  depth?: number;
+  /** Worker threads for the walk (default 0 = one per CPU, 1 = serial). */
+  walkThreads?: number;
+  /** Directory names skipped with everything below them. */
+  exclude?: string[];
+  skipHidden?: boolean;
+}
+
+export interface ScanOptions extends Options {
+  /** Called on the main thread while the walk runs. */
+  onProgress?: (progress: Progress) => void;
+}
+
+export interface CompareOptions extends Options {
+  /** Called for either tree while both walks run. */
+  onProgress?: (progress: Progress & { side: 'left' | 'right' }) => void;
+  /** Keep only this many of the largest differences. */
+  top?: number;
+}
+
+export interface Entry {
+  /** Relative to the scanned root. */
+  path: string;
+  size: number;
+  fileCount: number;
+}
+
+export interface Scan {
+  root: string;
+  totalSize: number;
+  totalFiles: number;
+  /** Largest first. */
+  entries: Entry[];
+}
+
+export interface Row {
+  path: string;
+  /** `null` when the entry only exists on the other side. */
+  leftSize: number | null;
+  rightSize: number | null;
+  delta: number;
+}
+
+export interface Comparison {
+  left: { root: string; totalSize: number };
+  right: { root: string; totalSize: number };
+  /** The largest differences first. */
+  entries: Row[];
+  onlyLeft: number;
+  onlyRight: number;
+  identical: number;
+}
+
+/** Scans `path` on a worker thread. */
+export function scan(path: string, options?: ScanOptions): Promise<Scan>;
+
+/** Scans both trees and aligns their entries by path. */
+export function compare(left: string, right: string, options?: CompareOptions): Promise<Comparison>;
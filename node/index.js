@@ -0,0 +1,55 @@
+'use strict';
+
+// Loads the diskhound addon (diskhound.node, built by build.js from this
+// directory's crate) and fills in option defaults. compare is two scans
+// aligned by path, as `diskhound compare` does.
+
+const native = require('./diskhound.node');
+
+// Scans `path` on a worker thread and resolves with the directories down to
+// `depth` (default 1, 0 = all), largest first.
+function scan(path, options = {}) {
+  return native.scan(
+    String(path),
+    options.depth === undefined ? 1 : options.depth,
+    options.walkThreads || 0,
+    options.exclude || [],
+    Boolean(options.skipHidden),
+    options.onProgress,
+  );
+}
+
+// Scans both trees at once and resolves with their entries side by side,
+// the largest differences first.
+async function compare(left, right, options = {}) {
+  const progress = (side) => options.onProgress && ((p) => options.onProgress({ side, ...p }));
+  const [a, b] = await Promise.all([
+    scan(left, { ...options, onProgress: progress('left') }),
+    scan(right, { ...options, onProgress: progress('right') }),
+  ]);
+
+  const rows = new Map();
+  for (const entry of a.entries) {
+    rows.set(entry.path, { path: entry.path, leftSize: entry.size, rightSize: null });
+  }
+  for (const entry of b.entries) {
+    const row = rows.get(entry.path) || { path: entry.path, leftSize: null };
+    row.rightSize = entry.size;
+    rows.set(entry.path, row);
+  }
+  const entries = [...rows.values()].sort((x, y) => (x.path < y.path ? -1 : 1));
+  for (const row of entries) row.delta = (row.rightSize || 0) - (row.leftSize || 0);
+  // Stable sort keeps equal differences in path order.
+  entries.sort((x, y) => Math.abs(y.delta) - Math.abs(x.delta));
+
+  return {
+    left: { root: a.root, totalSize: a.totalSize },
+    right: { root: b.root, totalSize: b.totalSize },
+    entries: options.top === undefined ? entries : entries.slice(0, options.top),
+    onlyLeft: entries.filter((row) => row.rightSize === null).length,
+    onlyRight: entries.filter((row) => row.leftSize === null).length,
+    identical: entries.filter((row) => row.leftSize === row.rightSize).length,
+  };
+}
+
+module.exports = { scan, compare };
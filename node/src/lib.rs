@@ -0,0 +1,489 @@
+//! Node-API addon over [`diskhound::scan_with`]: `scan` walks a tree on
+//! libuv's thread pool and resolves a promise with the directories down to
+//! a chosen depth, largest first, calling back with progress on the main
+//! thread. `index.js` wraps it with option defaults and `compare`.
+
+mod sys;
+
+use diskhound::{scan_with, Event, ScanOptions};
+use std::ffi::{c_void, CStr, CString, OsString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr::null_mut;
+use sys::*;
+
+/// Files between two progress calls.
+const PROGRESS_INTERVAL: u64 = 1000;
+
+type Result<T> = std::result::Result<T, String>;
+
+fn check(status: napi_status) -> Result<()> {
+    match status {
+        NAPI_OK => Ok(()),
+        status => Err(format!("Node-API call failed with status {status}")),
+    }
+}
+
+struct Scan {
+    entries: Vec<Entry>,
+    total_size: u64,
+    total_files: u64,
+    /// Files and bytes the progress calls counted, for the last one.
+    counted: (u64, u64),
+}
+
+struct Entry {
+    path: String,
+    size: u64,
+    file_count: u64,
+}
+
+/// What goes through the progress function's queue. The promise is
+/// settled through it too, so it never resolves ahead of a progress call.
+enum Message {
+    Progress {
+        files: u64,
+        bytes: u64,
+    },
+    Done {
+        deferred: napi_deferred,
+        root: PathBuf,
+        result: Option<Result<Scan>>,
+    },
+}
+
+/// A queued scan, owned by its async work from `scan` until `complete`.
+struct Task {
+    root: PathBuf,
+    max_depth: usize,
+    options: ScanOptions,
+    /// Null without an `onProgress` callback.
+    progress: napi_threadsafe_function,
+    deferred: napi_deferred,
+    work: napi_async_work,
+    result: Option<Result<Scan>>,
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if !self.progress.is_null() {
+            // SAFETY: the task holds the one reference `scan` created, and
+            // releasing is allowed from any thread.
+            unsafe { napi_release_threadsafe_function(self.progress, NAPI_TSFN_RELEASE) };
+        }
+    }
+}
+
+impl Task {
+    /// Runs on a worker thread, so it must not touch JavaScript values.
+    fn run(&self) -> Result<Scan> {
+        let mut scan = Scan {
+            entries: Vec::new(),
+            total_size: 0,
+            total_files: 0,
+            counted: (0, 0),
+        };
+        let (mut files, mut bytes) = (0u64, 0u64);
+        scan_with(&self.root, &self.options, |event| {
+            if let Event::File { size, .. } = &event {
+                files += 1;
+                bytes += size;
+                if files % PROGRESS_INTERVAL == 0 && !self.progress.is_null() {
+                    // SAFETY: the function stays alive until the task drops.
+                    let _ = unsafe { queue(self.progress, Message::Progress { files, bytes }) };
+                }
+            }
+            let Event::DirComplete {
+                path,
+                depth,
+                size,
+                file_count,
+            } = event
+            else {
+                return;
+            };
+            if depth == 0 {
+                scan.total_size = size;
+                scan.total_files = file_count;
+            } else if depth <= self.max_depth {
+                let relative = path.strip_prefix(&self.root).unwrap_or(path);
+                scan.entries.push(Entry {
+                    path: relative.to_string_lossy().to_string(),
+                    size,
+                    file_count,
+                });
+            }
+        })
+        .map_err(|err| format!("{err:#}"))?;
+        scan.entries.sort_by_key(|e| std::cmp::Reverse(e.size));
+        scan.counted = (files, bytes);
+        Ok(scan)
+    }
+}
+
+/// Queues `message` for `deliver`, or hands it back if `function` is
+/// closing.
+///
+/// # Safety
+///
+/// `function` must be a live threadsafe function with `deliver` as its
+/// callback.
+unsafe fn queue(
+    function: napi_threadsafe_function,
+    message: Message,
+) -> std::result::Result<(), Message> {
+    let data = Box::into_raw(Box::new(message));
+    // SAFETY: `deliver` takes the box back.
+    let status =
+        unsafe { napi_call_threadsafe_function(function, data.cast(), NAPI_TSFN_NONBLOCKING) };
+    if status == NAPI_OK {
+        Ok(())
+    } else {
+        // SAFETY: not queued, so still ours.
+        Err(*unsafe { Box::from_raw(data) })
+    }
+}
+
+unsafe fn string(env: napi_env, value: &str) -> Result<napi_value> {
+    let mut result = null_mut();
+    check(unsafe {
+        napi_create_string_utf8(env, value.as_ptr().cast(), value.len(), &mut result)
+    })?;
+    Ok(result)
+}
+
+unsafe fn number(env: napi_env, value: u64) -> Result<napi_value> {
+    let mut result = null_mut();
+    check(unsafe { napi_create_double(env, value as f64, &mut result) })?;
+    Ok(result)
+}
+
+unsafe fn object(env: napi_env, properties: &[(&CStr, napi_value)]) -> Result<napi_value> {
+    let mut result = null_mut();
+    check(unsafe { napi_create_object(env, &mut result) })?;
+    for (name, value) in properties {
+        check(unsafe { napi_set_named_property(env, result, name.as_ptr(), *value) })?;
+    }
+    Ok(result)
+}
+
+unsafe fn error(env: napi_env, message: &str) -> Result<napi_value> {
+    let mut result = null_mut();
+    check(unsafe { napi_create_error(env, null_mut(), string(env, message)?, &mut result) })?;
+    Ok(result)
+}
+
+unsafe fn get_string(env: napi_env, value: napi_value) -> Result<String> {
+    let mut len = 0;
+    check(unsafe { napi_get_value_string_utf8(env, value, null_mut(), 0, &mut len) })
+        .map_err(|_| "expected a string".to_string())?;
+    let mut buf = vec![0u8; len + 1];
+    check(unsafe {
+        napi_get_value_string_utf8(env, value, buf.as_mut_ptr().cast(), buf.len(), &mut len)
+    })?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|err| err.to_string())
+}
+
+unsafe fn get_u32(env: napi_env, value: napi_value) -> Result<u32> {
+    let mut result = 0;
+    check(unsafe { napi_get_value_uint32(env, value, &mut result) })
+        .map_err(|_| "expected a number".to_string())?;
+    Ok(result)
+}
+
+unsafe fn get_bool(env: napi_env, value: napi_value) -> Result<bool> {
+    let mut result = false;
+    check(unsafe { napi_get_value_bool(env, value, &mut result) })
+        .map_err(|_| "expected a boolean".to_string())?;
+    Ok(result)
+}
+
+unsafe fn get_strings(env: napi_env, value: napi_value) -> Result<Vec<String>> {
+    let mut len = 0;
+    check(unsafe { napi_get_array_length(env, value, &mut len) })
+        .map_err(|_| "expected an array".to_string())?;
+    (0..len)
+        .map(|i| {
+            let mut element = null_mut();
+            check(unsafe { napi_get_element(env, value, i, &mut element) })?;
+            unsafe { get_string(env, element) }
+        })
+        .collect()
+}
+
+fn throw(env: napi_env, message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    // SAFETY: called from a Node-API callback with its `env`.
+    unsafe { napi_throw_error(env, std::ptr::null(), message.as_ptr()) };
+}
+
+/// `scan(root, depth, walkThreads, exclude, skipHidden, onProgress)`:
+/// `depth` 0 keeps every directory, and `onProgress` may be undefined.
+unsafe extern "C" fn scan(env: napi_env, info: napi_callback_info) -> napi_value {
+    match unsafe { queue_scan(env, info) } {
+        Ok(promise) => promise,
+        Err(message) => {
+            throw(env, &format!("scan: {message}"));
+            null_mut()
+        }
+    }
+}
+
+unsafe fn queue_scan(env: napi_env, info: napi_callback_info) -> Result<napi_value> {
+    let mut argc = 6;
+    let mut argv = [null_mut(); 6];
+    check(unsafe {
+        napi_get_cb_info(
+            env,
+            info,
+            &mut argc,
+            argv.as_mut_ptr(),
+            null_mut(),
+            null_mut(),
+        )
+    })?;
+    if argc < 5 {
+        return Err(format!("expected at least 5 arguments, got {argc}"));
+    }
+    let root = PathBuf::from(unsafe { get_string(env, argv[0]) }?);
+    let max_depth = match unsafe { get_u32(env, argv[1]) }? {
+        0 => usize::MAX,
+        n => n as usize,
+    };
+    let options = ScanOptions {
+        walk_threads: unsafe { get_u32(env, argv[2]) }? as usize,
+        exclude: unsafe { get_strings(env, argv[3]) }?
+            .into_iter()
+            .map(OsString::from)
+            .collect(),
+        skip_hidden: unsafe { get_bool(env, argv[4]) }?,
+    };
+    let name = unsafe { string(env, "diskhound scan") }?;
+
+    let mut task = Box::new(Task {
+        root,
+        max_depth,
+        options,
+        progress: null_mut(),
+        deferred: null_mut(),
+        work: null_mut(),
+        result: None,
+    });
+    let mut kind = 0;
+    check(unsafe { napi_typeof(env, argv[5], &mut kind) })?;
+    if kind == NAPI_FUNCTION {
+        check(unsafe {
+            napi_create_threadsafe_function(
+                env,
+                argv[5],
+                null_mut(),
+                name,
+                0,
+                1,
+                null_mut(),
+                None,
+                null_mut(),
+                Some(deliver),
+                &mut task.progress,
+            )
+        })?;
+    }
+    let mut promise = null_mut();
+    check(unsafe { napi_create_promise(env, &mut task.deferred, &mut promise) })?;
+    let task = Box::into_raw(task);
+    // SAFETY: `task` stays valid until `complete` takes it back, and on
+    // failure here it was never handed to Node.
+    unsafe {
+        let work = &raw mut (*task).work;
+        let queued = check(napi_create_async_work(
+            env,
+            null_mut(),
+            name,
+            Some(execute),
+            Some(complete),
+            task.cast(),
+            work,
+        ))
+        .and_then(|()| check(napi_queue_async_work(env, *work)));
+        if let Err(err) = queued {
+            if !(*work).is_null() {
+                napi_delete_async_work(env, *work);
+            }
+            drop(Box::from_raw(task));
+            return Err(err);
+        }
+    }
+    Ok(promise)
+}
+
+unsafe extern "C" fn execute(_env: napi_env, data: *mut c_void) {
+    // SAFETY: `data` is the task `queue_scan` leaked; only this thread
+    // touches it until `complete`.
+    let task = unsafe { &mut *data.cast::<Task>() };
+    task.result = Some(
+        catch_unwind(AssertUnwindSafe(|| task.run()))
+            .unwrap_or_else(|_| Err("scan panicked".to_string())),
+    );
+}
+
+unsafe extern "C" fn complete(env: napi_env, _status: napi_status, data: *mut c_void) {
+    // SAFETY: `data` is the task `queue_scan` leaked, and this is the last
+    // callback for it.
+    let mut task = unsafe { Box::from_raw(data.cast::<Task>()) };
+    unsafe { napi_delete_async_work(env, task.work) };
+    let result = task.result.take();
+    if task.progress.is_null() {
+        unsafe { settle(env, task.deferred, &task.root, result) };
+        return;
+    }
+    // SAFETY: the function is released only when `task` drops below.
+    unsafe {
+        // One last call with the final counts, so small scans report too.
+        if let Some(Ok(scan)) = &result {
+            let (files, bytes) = scan.counted;
+            let _ = queue(task.progress, Message::Progress { files, bytes });
+        }
+        // Behind any progress calls still queued; `deliver` settles it.
+        let done = Message::Done {
+            deferred: task.deferred,
+            root: task.root.clone(),
+            result,
+        };
+        if let Err(Message::Done {
+            deferred,
+            root,
+            result,
+        }) = queue(task.progress, done)
+        {
+            settle(env, deferred, &root, result);
+        }
+    }
+}
+
+unsafe fn settle(
+    env: napi_env,
+    deferred: napi_deferred,
+    root: &std::path::Path,
+    result: Option<Result<Scan>>,
+) {
+    let result = match result {
+        Some(Ok(scan)) => unsafe { scan_object(env, root, &scan) },
+        Some(Err(message)) => Err(message),
+        None => Err("scan was cancelled".to_string()),
+    };
+    unsafe {
+        match result {
+            Ok(value) => napi_resolve_deferred(env, deferred, value),
+            Err(message) => match error(env, &message) {
+                Ok(err) => napi_reject_deferred(env, deferred, err),
+                Err(_) => NAPI_OK,
+            },
+        };
+    }
+}
+
+unsafe fn scan_object(env: napi_env, root: &std::path::Path, scan: &Scan) -> Result<napi_value> {
+    let mut entries = null_mut();
+    check(unsafe { napi_create_array_with_length(env, scan.entries.len(), &mut entries) })?;
+    for (i, entry) in scan.entries.iter().enumerate() {
+        let value = unsafe {
+            object(
+                env,
+                &[
+                    (c"path", string(env, &entry.path)?),
+                    (c"size", number(env, entry.size)?),
+                    (c"fileCount", number(env, entry.file_count)?),
+                ],
+            )
+        }?;
+        check(unsafe { napi_set_element(env, entries, i as u32, value) })?;
+    }
+    unsafe {
+        object(
+            env,
+            &[
+                (c"root", string(env, &root.to_string_lossy())?),
+                (c"totalSize", number(env, scan.total_size)?),
+                (c"totalFiles", number(env, scan.total_files)?),
+                (c"entries", entries),
+            ],
+        )
+    }
+}
+
+unsafe extern "C" fn deliver(
+    env: napi_env,
+    callback: napi_value,
+    _context: *mut c_void,
+    data: *mut c_void,
+) {
+    // SAFETY: `data` is a box `Task::run` or `complete` queued.
+    let message = unsafe { Box::from_raw(data.cast::<Message>()) };
+    // A null `env` means Node is shutting down and only wants the data freed.
+    if env.is_null() {
+        return;
+    }
+    let (files, bytes) = match *message {
+        Message::Progress { files, bytes } => (files, bytes),
+        Message::Done {
+            deferred,
+            root,
+            result,
+        } => return unsafe { settle(env, deferred, &root, result) },
+    };
+    let _ = unsafe {
+        (|| -> Result<()> {
+            let argument = object(
+                env,
+                &[
+                    (c"files", number(env, files)?),
+                    (c"bytes", number(env, bytes)?),
+                ],
+            )?;
+            let (mut this, mut result) = (null_mut(), null_mut());
+            check(napi_get_undefined(env, &mut this))?;
+            check(napi_call_function(
+                env,
+                this,
+                callback,
+                1,
+                &argument,
+                &mut result,
+            ))
+        })()
+    };
+}
+
+/// Called by Node when the addon loads.
+///
+/// # Safety
+///
+/// Only Node calls this, with a valid `env` and `exports`.
+#[no_mangle]
+pub unsafe extern "C" fn napi_register_module_v1(env: napi_env, exports: napi_value) -> napi_value {
+    let mut function = null_mut();
+    // SAFETY: `env` and `exports` come from Node.
+    let registered = unsafe {
+        check(napi_create_function(
+            env,
+            c"scan".as_ptr(),
+            4,
+            Some(scan),
+            null_mut(),
+            &mut function,
+        ))
+        .and_then(|()| {
+            check(napi_set_named_property(
+                env,
+                exports,
+                c"scan".as_ptr(),
+                function,
+            ))
+        })
+    };
+    if let Err(message) = registered {
+        throw(env, &message);
+    }
+    exports
+}
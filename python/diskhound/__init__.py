@@ -0,0 +1,148 @@
+"""Python access to diskhound's directory scanner through libdiskhound_ffi.
+
+The library is found through $DISKHOUND_LIB (a path to the shared library),
+next to this package, or on the system library path.
+"""
+
+import ctypes
+import ctypes.util
+import os
+import sys
+from dataclasses import dataclass
+from typing import Callable, List, Optional
+
+__all__ = ["Entry", "File", "Scan", "scan", "files"]
+
+
+@dataclass(frozen=True)
+class Entry:
+    """A directory with the total size and file count below it."""
+
+    path: str
+    size: int
+    file_count: int
+
+
+@dataclass(frozen=True)
+class File:
+    """A regular file and its size."""
+
+    path: str
+    size: int
+
+
+@dataclass(frozen=True)
+class Scan:
+    """The directories down to the scanned depth, largest first."""
+
+    root: str
+    total_size: int
+    total_files: int
+    entries: List[Entry]
+
+
+class _Entry(ctypes.Structure):
+    _fields_ = [
+        ("path", ctypes.c_char_p),
+        ("size", ctypes.c_uint64),
+        ("file_count", ctypes.c_uint64),
+    ]
+
+
+_PROGRESS_FN = ctypes.CFUNCTYPE(None, ctypes.c_uint64, ctypes.c_uint64, ctypes.c_void_p)
+_FILE_FN = ctypes.CFUNCTYPE(None, ctypes.c_char_p, ctypes.c_uint64, ctypes.c_void_p)
+
+_lib = None
+
+
+def _library():
+    global _lib
+    if _lib is not None:
+        return _lib
+    if sys.platform == "win32":
+        name = "diskhound_ffi.dll"
+    elif sys.platform == "darwin":
+        name = "libdiskhound_ffi.dylib"
+    else:
+        name = "libdiskhound_ffi.so"
+    bundled = os.path.join(os.path.dirname(__file__), name)
+    path = (
+        os.environ.get("DISKHOUND_LIB")
+        or (bundled if os.path.exists(bundled) else None)
+        or ctypes.util.find_library("diskhound_ffi")
+    )
+    if path is None:
+        raise OSError("libdiskhound_ffi not found; build ffi/ and set DISKHOUND_LIB")
+    lib = ctypes.CDLL(path)
+    lib.diskhound_scan_new_with_progress.restype = ctypes.c_void_p
+    lib.diskhound_scan_new_with_progress.argtypes = [
+        ctypes.c_char_p,
+        ctypes.c_uint32,
+        ctypes.c_uint32,
+        _PROGRESS_FN,
+        ctypes.c_void_p,
+    ]
+    lib.diskhound_scan_len.restype = ctypes.c_size_t
+    lib.diskhound_scan_len.argtypes = [ctypes.c_void_p]
+    lib.diskhound_scan_total_size.restype = ctypes.c_uint64
+    lib.diskhound_scan_total_size.argtypes = [ctypes.c_void_p]
+    lib.diskhound_scan_total_files.restype = ctypes.c_uint64
+    lib.diskhound_scan_total_files.argtypes = [ctypes.c_void_p]
+    lib.diskhound_scan_entry.restype = ctypes.c_int
+    lib.diskhound_scan_entry.argtypes = [ctypes.c_void_p, ctypes.c_size_t, ctypes.POINTER(_Entry)]
+    lib.diskhound_scan_free.restype = None
+    lib.diskhound_scan_free.argtypes = [ctypes.c_void_p]
+    lib.diskhound_walk_files.restype = ctypes.c_int
+    lib.diskhound_walk_files.argtypes = [ctypes.c_char_p, ctypes.c_uint32, _FILE_FN, ctypes.c_void_p]
+    lib.diskhound_last_error.restype = ctypes.c_char_p
+    lib.diskhound_last_error.argtypes = []
+    _lib = lib
+    return lib
+
+
+def _error(lib) -> OSError:
+    message = lib.diskhound_last_error()
+    return OSError(message.decode("utf-8", "replace") if message else "scan failed")
+
+
+def scan(
+    root: str,
+    depth: int = 1,
+    walk_threads: int = 0,
+    progress: Optional[Callable[[int, int], None]] = None,
+) -> Scan:
+    """Scans `root` and returns every directory from 1 to `depth` levels below
+    it (0 = every directory), largest first. `progress`, if given, is called
+    with the files and bytes counted so far every thousand files."""
+    lib = _library()
+    callback = _PROGRESS_FN(lambda files, size, _: progress(files, size)) if progress else _PROGRESS_FN()
+    handle = lib.diskhound_scan_new_with_progress(
+        os.fsencode(root), depth, walk_threads, callback, None
+    )
+    if not handle:
+        raise _error(lib)
+    try:
+        entries = []
+        entry = _Entry()
+        for i in range(lib.diskhound_scan_len(handle)):
+            if lib.diskhound_scan_entry(handle, i, ctypes.byref(entry)) != 0:
+                raise _error(lib)
+            entries.append(Entry(os.fsdecode(entry.path), entry.size, entry.file_count))
+        return Scan(
+            root=root,
+            total_size=lib.diskhound_scan_total_size(handle),
+            total_files=lib.diskhound_scan_total_files(handle),
+            entries=entries,
+        )
+    finally:
+        lib.diskhound_scan_free(handle)
+
+
+def files(root: str, walk_threads: int = 0) -> List[File]:
+    """Every regular file below `root`, with paths relative to it."""
+    lib = _library()
+    found: List[File] = []
+    callback = _FILE_FN(lambda path, size, _: found.append(File(os.fsdecode(path), size)))
+    if lib.diskhound_walk_files(os.fsencode(root), walk_threads, callback, None) != 0:
+        raise _error(lib)
+    return found
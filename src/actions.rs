@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+use tracing::debug;
+
+/// Opens `dir` in the platform file manager.
+pub fn reveal(dir: &Path) -> Result<()> {
+    let program = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    debug!("revealing {} with {program}", dir.display());
+    // explorer.exe reports a non-zero exit status even on success, so only a
+    // failure to launch is treated as an error.
+    Command::new(program)
+        .arg(dir)
+        .spawn()
+        .with_context(|| format!("failed to run {program}"))?;
+    Ok(())
+}
+
+/// Starts an interactive shell in `dir` and waits for it to exit.
+pub fn shell(dir: &Path) -> Result<()> {
+    let program = if cfg!(windows) {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    } else {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    };
+    debug!("starting {program} in {}", dir.display());
+    Command::new(&program)
+        .current_dir(dir)
+        .status()
+        .with_context(|| format!("failed to start {program}"))?;
+    Ok(())
+}
+
+/// Runs `command` through the platform shell with `input` on stdin, e.g. to
+/// post alerts to a webhook, and fails if it exits unsuccessfully.
+pub fn run_hook(command: &str, input: &str) -> Result<()> {
+    let (program, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    debug!("running hook {command}");
+    let mut child = Command::new(program)
+        .args([flag, command])
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run {command}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        use std::io::Write;
+        stdin.write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("{command} exited with {status}");
+    }
+    Ok(())
+}
+
+/// Places `text` on the system clipboard using the platform's clipboard tool.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let candidates: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+
+    for (program, args) in candidates {
+        let child = Command::new(program)
+            .args(*args)
+            .stdin(std::process::Stdio::piped())
+            .spawn();
+        let Ok(mut child) = child else {
+            debug!("clipboard tool {program} not available");
+            continue;
+        };
+        // A tool that can't reach a display (wl-copy outside Wayland,
+        // xclip without X) exits early, closing the pipe; try the next.
+        let written = child.stdin.take().map_or(Ok(()), |mut stdin| {
+            use std::io::Write;
+            stdin.write_all(text.as_bytes())
+        });
+        let status = child.wait()?;
+        if written.is_err() || !status.success() {
+            debug!("clipboard tool {program} failed ({status})");
+            continue;
+        }
+        debug!("copied to clipboard with {program}");
+        return Ok(());
+    }
+    let names: Vec<&str> = candidates.iter().map(|(p, _)| *p).collect();
+    anyhow::bail!("no clipboard tool worked (tried {})", names.join(", "))
+}
@@ -0,0 +1,281 @@
+use crate::locale;
+use crate::output::SCHEMA_VERSION;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Backup tools whose repositories `diskhound backup` reads.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Tool {
+    Restic,
+    Borg,
+}
+
+impl Tool {
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Restic => "restic",
+            Tool::Borg => "borg",
+        }
+    }
+}
+
+/// A backup repository's statistics, from the tool's own JSON output.
+pub struct Repository {
+    pub tool: Tool,
+    pub location: String,
+    /// Bytes the repository takes up, after deduplication and compression.
+    pub stored: u64,
+    /// Bytes all snapshots would take up restored.
+    pub logical: u64,
+    /// Largest `unique` (or `added`) first.
+    pub snapshots: Vec<Snapshot>,
+}
+
+impl Repository {
+    /// How many times over deduplication and compression fit the snapshots
+    /// into the repository.
+    pub fn ratio(&self) -> f64 {
+        if self.stored == 0 {
+            0.0
+        } else {
+            self.logical as f64 / self.stored as f64
+        }
+    }
+}
+
+pub struct Snapshot {
+    /// restic's short ID, or borg's archive name.
+    pub id: String,
+    /// As the tool writes it (RFC 3339).
+    pub time: String,
+    pub files: Option<u64>,
+    /// Bytes of the backed-up files.
+    pub size: Option<u64>,
+    /// Bytes only this snapshot references, which deleting it would free
+    /// (borg).
+    pub unique: Option<u64>,
+    /// Bytes this snapshot added to the repository when it was taken,
+    /// compressed (restic 0.17 and later).
+    pub added: Option<u64>,
+}
+
+/// Reads `repo` through the `restic` or `borg` command. Passwords come from
+/// the tools' usual environment variables (`RESTIC_PASSWORD_FILE`,
+/// `BORG_PASSPHRASE`, ...) or a prompt on the terminal.
+pub fn read(tool: Tool, repo: &str) -> Result<Repository> {
+    let mut repository = match tool {
+        Tool::Restic => restic(repo)?,
+        Tool::Borg => borg(repo)?,
+    };
+    repository
+        .snapshots
+        .sort_by_key(|s| std::cmp::Reverse(s.unique.or(s.added).unwrap_or(0)));
+    Ok(repository)
+}
+
+fn run(tool: Tool, args: &[&str]) -> Result<Value> {
+    let output = Command::new(tool.name())
+        .args(args)
+        // Lets the tool prompt for a password.
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to run {}", tool.name()))?;
+    if !output.status.success() {
+        bail!("{} {} failed", tool.name(), args.join(" "));
+    }
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("{} wrote unexpected JSON", tool.name()))
+}
+
+fn restic(repo: &str) -> Result<Repository> {
+    #[derive(Deserialize)]
+    struct ResticSnapshot {
+        short_id: String,
+        time: String,
+        #[serde(default)]
+        summary: Option<Summary>,
+    }
+    #[derive(Deserialize)]
+    struct Summary {
+        data_added_packed: Option<u64>,
+        total_files_processed: Option<u64>,
+        total_bytes_processed: Option<u64>,
+    }
+    let snapshots: Vec<ResticSnapshot> =
+        serde_json::from_value(run(Tool::Restic, &["-r", repo, "snapshots", "--json"])?)
+            .context("restic snapshots wrote unexpected JSON")?;
+    let stats = |mode| {
+        run(
+            Tool::Restic,
+            &["-r", repo, "stats", "--mode", mode, "--json"],
+        )
+    };
+    let stored = stats("raw-data")?["total_size"].as_u64().unwrap_or(0);
+    let logical = stats("restore-size")?["total_size"].as_u64().unwrap_or(0);
+    Ok(Repository {
+        tool: Tool::Restic,
+        location: repo.to_string(),
+        stored,
+        logical,
+        snapshots: snapshots
+            .into_iter()
+            .map(|s| Snapshot {
+                id: s.short_id,
+                time: s.time,
+                files: s.summary.as_ref().and_then(|s| s.total_files_processed),
+                size: s.summary.as_ref().and_then(|s| s.total_bytes_processed),
+                unique: None,
+                added: s.summary.as_ref().and_then(|s| s.data_added_packed),
+            })
+            .collect(),
+    })
+}
+
+fn borg(repo: &str) -> Result<Repository> {
+    // An archive filter makes `borg info` describe every archive too.
+    let info = run(Tool::Borg, &["info", "--json", "--last", "1000000", repo])?;
+    let stats = &info["cache"]["stats"];
+    let snapshots = info["archives"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|archive| {
+            let stats = &archive["stats"];
+            Snapshot {
+                id: archive["name"].as_str().unwrap_or_default().to_string(),
+                time: archive["start"].as_str().unwrap_or_default().to_string(),
+                files: stats["nfiles"].as_u64(),
+                size: stats["original_size"].as_u64(),
+                unique: stats["deduplicated_size"].as_u64(),
+                added: None,
+            }
+        })
+        .collect();
+    Ok(Repository {
+        tool: Tool::Borg,
+        location: info["repository"]["location"]
+            .as_str()
+            .unwrap_or(repo)
+            .to_string(),
+        stored: stats["unique_csize"].as_u64().unwrap_or(0),
+        logical: stats["total_size"].as_u64().unwrap_or(0),
+        snapshots,
+    })
+}
+
+/// `diskhound backup`: the repository's totals, then its snapshots by what
+/// each costs, `top` of them.
+pub fn write_table(w: &mut dyn Write, repository: &Repository, top: usize) -> Result<()> {
+    writeln!(
+        w,
+        "{} repository {}: {} snapshots",
+        repository.tool.name(),
+        repository.location,
+        locale::count(repository.snapshots.len() as u64)
+    )?;
+    writeln!(
+        w,
+        "Stored {} for {} of snapshot data ({}x from deduplication and compression)",
+        locale::size(repository.stored),
+        locale::size(repository.logical),
+        locale::fixed(repository.ratio(), 1)
+    )?;
+    if repository.snapshots.is_empty() {
+        return Ok(());
+    }
+    let cost = match repository.tool {
+        Tool::Restic => "added",
+        Tool::Borg => "unique",
+    };
+    writeln!(w)?;
+    writeln!(
+        w,
+        "  {:<24}  {:<16}  {:>10}  {:>10}  {:>10}",
+        "snapshot", "taken", "files", "size", cost
+    )?;
+    let unknown = || "?".to_string();
+    for snapshot in repository.snapshots.iter().take(top) {
+        let time: String = snapshot
+            .time
+            .replacen('T', " ", 1)
+            .chars()
+            .take(16)
+            .collect();
+        writeln!(
+            w,
+            "  {:<24}  {:<16}  {:>10}  {:>10}  {:>10}",
+            snapshot.id,
+            time,
+            snapshot.files.map_or_else(unknown, locale::count),
+            snapshot.size.map_or_else(unknown, locale::size),
+            snapshot
+                .unique
+                .or(snapshot.added)
+                .map_or_else(unknown, locale::size),
+        )?;
+    }
+    if repository.snapshots.len() > top {
+        writeln!(
+            w,
+            "  ... {} more",
+            locale::count((repository.snapshots.len() - top) as u64)
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonRepository<'a> {
+    schema_version: u32,
+    tool: &'static str,
+    location: &'a str,
+    stored: u64,
+    logical: u64,
+    /// `logical` over `stored`.
+    ratio: f64,
+    snapshots: Vec<JsonSnapshot<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonSnapshot<'a> {
+    id: &'a str,
+    time: &'a str,
+    files: Option<u64>,
+    size: Option<u64>,
+    /// borg: bytes only this archive references.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unique: Option<u64>,
+    /// restic: bytes the snapshot added to the repository when taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    added: Option<u64>,
+}
+
+pub fn write_json(w: &mut dyn Write, repository: &Repository, top: usize) -> Result<()> {
+    let output = JsonRepository {
+        schema_version: SCHEMA_VERSION,
+        tool: repository.tool.name(),
+        location: &repository.location,
+        stored: repository.stored,
+        logical: repository.logical,
+        ratio: repository.ratio(),
+        snapshots: repository
+            .snapshots
+            .iter()
+            .take(top)
+            .map(|s| JsonSnapshot {
+                id: &s.id,
+                time: &s.time,
+                files: s.files,
+                size: s.size,
+                unique: s.unique,
+                added: s.added,
+            })
+            .collect(),
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::locale;
+use crate::output::SCHEMA_VERSION;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::time::Duration;
+
+/// How long full scans took with one `--walk-threads` setting.
+pub struct Timing {
+    pub walk_threads: usize,
+    pub runs: Vec<Duration>,
+    pub files: u64,
+}
+
+impl Timing {
+    pub fn best(&self) -> Duration {
+        self.runs.iter().copied().min().unwrap_or_default()
+    }
+
+    pub fn median(&self) -> Duration {
+        let mut runs = self.runs.clone();
+        runs.sort();
+        runs.get(runs.len() / 2).copied().unwrap_or_default()
+    }
+
+    fn files_per_second(&self) -> f64 {
+        match self.best().as_secs_f64() {
+            0.0 => 0.0,
+            secs => self.files as f64 / secs,
+        }
+    }
+}
+
+/// The thread counts `diskhound bench` tries unless told otherwise: serial,
+/// then doubling up to one per CPU.
+pub fn default_threads() -> Vec<usize> {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut threads = vec![1];
+    while threads[threads.len() - 1] * 2 < cpus {
+        threads.push(threads[threads.len() - 1] * 2);
+    }
+    if cpus > 1 {
+        threads.push(cpus);
+    }
+    threads
+}
+
+fn fastest(timings: &[Timing]) -> Option<&Timing> {
+    timings.iter().min_by_key(|t| t.best())
+}
+
+/// `diskhound bench`: best and median wall time per thread count, with
+/// the fastest marked.
+pub fn write_table(w: &mut dyn Write, timings: &[Timing]) -> Result<()> {
+    let best = fastest(timings);
+    writeln!(
+        w,
+        "  {:>12}  {:>10}  {:>10}  {:>12}",
+        "walk threads", "best", "median", "files/s"
+    )?;
+    for timing in timings {
+        writeln!(
+            w,
+            "  {:>12}  {:>9}s  {:>9}s  {:>12}{}",
+            timing.walk_threads,
+            locale::fixed(timing.best().as_secs_f64(), 3),
+            locale::fixed(timing.median().as_secs_f64(), 3),
+            locale::count(timing.files_per_second() as u64),
+            if best.is_some_and(|b| b.walk_threads == timing.walk_threads) {
+                "  fastest"
+            } else {
+                ""
+            },
+        )?;
+    }
+    if let Some(fastest) = best {
+        writeln!(w)?;
+        writeln!(
+            w,
+            "Fastest with --walk-threads {} ({} files, after a warm-up scan)",
+            fastest.walk_threads,
+            locale::count(fastest.files)
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonBench {
+    schema_version: u32,
+    /// The fastest `--walk-threads` setting.
+    fastest: Option<usize>,
+    timings: Vec<JsonTiming>,
+}
+
+#[derive(Serialize)]
+struct JsonTiming {
+    walk_threads: usize,
+    /// Wall time of each run, in seconds.
+    seconds: Vec<f64>,
+    best_seconds: f64,
+    median_seconds: f64,
+    files: u64,
+    files_per_second: f64,
+}
+
+pub fn write_json(w: &mut dyn Write, timings: &[Timing]) -> Result<()> {
+    let output = JsonBench {
+        schema_version: SCHEMA_VERSION,
+        fastest: fastest(timings).map(|t| t.walk_threads),
+        timings: timings
+            .iter()
+            .map(|t| JsonTiming {
+                walk_threads: t.walk_threads,
+                seconds: t.runs.iter().map(Duration::as_secs_f64).collect(),
+                best_seconds: t.best().as_secs_f64(),
+                median_seconds: t.median().as_secs_f64(),
+                files: t.files,
+                files_per_second: t.files_per_second(),
+            })
+            .collect(),
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Secondary aggregations collected alongside the directory totals.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Breakdown {
+    /// Bytes per file extension
+    Ext,
+}
+
+/// How many labels the table lists per entry.
+pub const DOMINANT: usize = 3;
+
+/// Lowercased extension with its leading dot, or `(none)`.
+pub fn extension_label(file: &Path) -> String {
+    match file.extension() {
+        Some(ext) => format!(".{}", ext.to_string_lossy().to_lowercase()),
+        None => "(none)".to_string(),
+    }
+}
+
+/// Bytes per label (an extension, a merged source) within each grouping key.
+#[derive(Default)]
+pub struct Shares {
+    by_key: HashMap<PathBuf, HashMap<String, u64>>,
+}
+
+impl Shares {
+    pub fn add(&mut self, key: &Path, label: &str, size: u64) {
+        let labels = self.by_key.entry(key.to_path_buf()).or_default();
+        match labels.get_mut(label) {
+            Some(total) => *total += size,
+            None => {
+                labels.insert(label.to_string(), size);
+            }
+        }
+    }
+
+    /// Rolls every key's labels up into its ancestors, matching
+    /// `tree::cumulative`.
+    pub fn cumulative(self) -> Self {
+        let mut by_key: HashMap<PathBuf, HashMap<String, u64>> = HashMap::new();
+        for (key, labels) in self.by_key {
+            for ancestor in key.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+                let out = by_key.entry(ancestor.to_path_buf()).or_default();
+                for (label, size) in &labels {
+                    *out.entry(label.clone()).or_insert(0) += size;
+                }
+            }
+        }
+        Self { by_key }
+    }
+
+    /// The `limit` largest labels under `key` with their size and percentage
+    /// of the key's bytes, largest first.
+    pub fn dominant(&self, key: &Path, limit: usize) -> Vec<(&str, u64, f64)> {
+        let Some(labels) = self.by_key.get(key) else {
+            return Vec::new();
+        };
+        let total: u64 = labels.values().sum();
+        let mut sorted: Vec<_> = labels.iter().collect();
+        sorted.sort_by_key(|(label, size)| (std::cmp::Reverse(**size), *label));
+        sorted
+            .into_iter()
+            .take(limit)
+            .map(|(label, &size)| {
+                let percentage = if total > 0 {
+                    size as f64 / total as f64 * 100.0
+                } else {
+                    0.0
+                };
+                (label.as_str(), size, percentage)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A Btrfs subvolume (or snapshot) found inside the scanned tree. Walking
+/// counts every snapshot in full, so the walked `size` overstates what the
+/// subvolume costs; `exclusive` (from quota groups, with `--btrfs-qgroups`)
+/// is what deleting it would free.
+pub struct Subvolume {
+    /// Relative to the scanned root, like entry names.
+    pub path: PathBuf,
+    /// The subvolume's tree ID, as in `btrfs subvolume list`; `None` where
+    /// the kernel wouldn't say.
+    pub id: Option<u64>,
+    /// Whether it's a snapshot of another subvolume; `None` where unknown.
+    pub snapshot: Option<bool>,
+    /// Bytes the walk counted below it, not counting nested subvolumes.
+    pub size: u64,
+    /// Bytes it references, shared with snapshots or not (`--btrfs-qgroups`).
+    pub referenced: Option<u64>,
+    /// Bytes only it references (`--btrfs-qgroups`).
+    pub exclusive: Option<u64>,
+}
+
+/// Whether `root` (canonical) lives on a Btrfs filesystem.
+pub fn contains(root: &Path) -> bool {
+    crate::mounts::list(&crate::mounts::mountinfo())
+        .into_iter()
+        .filter(|m| root.starts_with(&m.point))
+        .max_by_key(|m| m.point.components().count())
+        .is_some_and(|m| m.fstype == "btrfs")
+}
+
+/// The tree ID and snapshot status of the subvolume rooted at directory
+/// `path`, or `None` if `path` is an ordinary directory.
+#[cfg(target_os = "linux")]
+pub fn subvolume(path: &Path, metadata: &std::fs::Metadata) -> Option<(Option<u64>, Option<bool>)> {
+    use std::os::unix::fs::MetadataExt;
+    // Every subvolume's top directory has this inode number.
+    const FIRST_FREE_OBJECTID: u64 = 256;
+    if metadata.ino() != FIRST_FREE_OBJECTID {
+        return None;
+    }
+    Some(match info(path) {
+        Some(info) => (Some(info.treeid), Some(info.parent_uuid != [0; 16])),
+        None => (None, None),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn subvolume(
+    _path: &Path,
+    _metadata: &std::fs::Metadata,
+) -> Option<(Option<u64>, Option<bool>)> {
+    None
+}
+
+/// From <linux/btrfs.h>: struct btrfs_ioctl_get_subvol_info_args.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct SubvolInfo {
+    treeid: u64,
+    name: [u8; 256],
+    parent_id: u64,
+    dirid: u64,
+    generation: u64,
+    flags: u64,
+    uuid: [u8; 16],
+    parent_uuid: [u8; 16],
+    received_uuid: [u8; 16],
+    transids: [u64; 4],
+    times: [[u64; 2]; 4],
+    reserved: [u64; 8],
+}
+
+/// BTRFS_IOC_GET_SUBVOL_INFO, which unlike the tree search ioctls works
+/// without root (Linux 4.18 and later).
+#[cfg(target_os = "linux")]
+fn info(path: &Path) -> Option<SubvolInfo> {
+    use std::os::fd::AsRawFd;
+    const GET_SUBVOL_INFO: u32 =
+        (2 << 30) | ((std::mem::size_of::<SubvolInfo>() as u32) << 16) | (0x94 << 8) | 60;
+    let dir = std::fs::File::open(path).ok()?;
+    let mut info: SubvolInfo = unsafe { std::mem::zeroed() };
+    let result = unsafe {
+        libc::ioctl(
+            dir.as_raw_fd(),
+            GET_SUBVOL_INFO as _,
+            &mut info as *mut SubvolInfo,
+        )
+    };
+    (result == 0).then_some(info)
+}
+
+/// Referenced and exclusive bytes per subvolume ID, from
+/// `btrfs qgroup show --raw`. Empty when quotas aren't enabled, the `btrfs`
+/// tool is missing, or the caller may not read them (it needs root).
+pub fn qgroups(root: &Path) -> HashMap<u64, (u64, u64)> {
+    let output = match std::process::Command::new("btrfs")
+        .args(["qgroup", "show", "--raw"])
+        .arg(root)
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            tracing::info!(
+                "btrfs qgroup show failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return HashMap::new();
+        }
+        Err(err) => {
+            tracing::info!("couldn't run btrfs: {err}");
+            return HashMap::new();
+        }
+    };
+    parse_qgroups(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Rows look like `0/257  16384  16384`, after a header; level-0 qgroups
+/// are the subvolumes.
+fn parse_qgroups(text: &str) -> HashMap<u64, (u64, u64)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let id = fields.next()?.strip_prefix("0/")?.parse().ok()?;
+            let referenced = fields.next()?.parse().ok()?;
+            let exclusive = fields.next()?.parse().ok()?;
+            Some((id, (referenced, exclusive)))
+        })
+        .collect()
+}
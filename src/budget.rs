@@ -0,0 +1,188 @@
+use anyhow::Result;
+use humansize::{format_size, BINARY};
+use std::path::{Component, Path, PathBuf};
+
+/// A size limit for a directory (or the whole scan), from `--budget` or the
+/// config file's `[budgets]` section.
+#[derive(Clone)]
+pub struct Budget {
+    /// Relative to the scanned root (empty for the whole scan), or absolute.
+    pub path: PathBuf,
+    pub limit: u64,
+}
+
+/// Parses `PATH=SIZE`, with the size parsed by `parse_size`.
+pub fn parse(s: &str, parse_size: impl Fn(&str) -> Result<u64>) -> Result<Budget> {
+    let Some((path, size)) = s.rsplit_once('=') else {
+        anyhow::bail!("expected a budget like target=2GB, got {s:?}");
+    };
+    Ok(Budget {
+        path: normalize(Path::new(path.trim())),
+        limit: parse_size(size)?,
+    })
+}
+
+/// Drops `.` components, so `.` names the whole scan and `./dist` is `dist`.
+pub fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+/// The budgets that apply to a scan of `root` (canonical), made relative to
+/// it: the `cli` ones, then the `config` ones. Earlier budgets win over later
+/// ones for the same path. Config budgets for paths outside the scan, or that
+/// don't exist in it, are dropped, since they apply to every scan; for `cli`
+/// budgets that's an error, as it's most likely a typo.
+pub fn resolve(
+    root: &Path,
+    cli: impl IntoIterator<Item = Budget>,
+    config: impl IntoIterator<Item = Budget>,
+) -> Result<Vec<Budget>> {
+    let cli = cli.into_iter().map(|budget| (budget, true));
+    let config = config.into_iter().map(|budget| (budget, false));
+    let mut resolved: Vec<Budget> = Vec::new();
+    for (budget, required) in cli.chain(config) {
+        let path = if budget.path.is_absolute() {
+            let absolute = budget.path.canonicalize().unwrap_or(budget.path);
+            match absolute.strip_prefix(root) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) if required => {
+                    anyhow::bail!(
+                        "--budget path {} is outside {}",
+                        absolute.display(),
+                        root.display()
+                    )
+                }
+                Err(_) => continue,
+            }
+        } else {
+            budget.path
+        };
+        if !root.join(&path).exists() {
+            if required {
+                anyhow::bail!(
+                    "--budget path {} does not exist",
+                    root.join(&path).display()
+                );
+            }
+            continue;
+        }
+        if resolved.iter().any(|b| b.path == path) {
+            continue;
+        }
+        resolved.push(Budget {
+            path,
+            limit: budget.limit,
+        });
+    }
+    Ok(resolved)
+}
+
+/// A budget with what the scan counted against it.
+pub struct Measured {
+    pub budget: Budget,
+    pub size: u64,
+}
+
+impl Measured {
+    pub fn exceeded(&self) -> bool {
+        self.size > self.budget.limit
+    }
+
+    /// The budget's path as shown in reports: `.` for the whole scan.
+    pub fn name(&self) -> String {
+        if self.budget.path.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            crate::output::display_name(&self.budget.path)
+        }
+    }
+
+    /// One line for machine formats, e.g. `target is 2.3 GiB, 307 MiB over
+    /// its 2 GiB budget`.
+    pub fn describe(&self) -> String {
+        let size = format_size(self.size, BINARY);
+        let limit = format_size(self.budget.limit, BINARY);
+        if self.exceeded() {
+            let over = format_size(self.size - self.budget.limit, BINARY);
+            format!("{} is {size}, {over} over its {limit} budget", self.name())
+        } else {
+            format!("{} is {size}, within its {limit} budget", self.name())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(s: &str) -> Budget {
+        parse(s, crate::parse_human_size).unwrap()
+    }
+
+    fn resolved(budgets: &[Budget]) -> Vec<(PathBuf, u64)> {
+        budgets.iter().map(|b| (b.path.clone(), b.limit)).collect()
+    }
+
+    #[test]
+    fn parses_path_and_size() {
+        let b = budget(" ./dist = 2KB");
+        assert_eq!((b.path, b.limit), (PathBuf::from("dist"), 2048));
+        // The size is after the last `=`, so paths may contain one.
+        let b = budget("a=b=1KB");
+        assert_eq!((b.path, b.limit), (PathBuf::from("a=b"), 1024));
+        assert_eq!(budget(".=1GB").path, PathBuf::new());
+        assert!(parse("target", crate::parse_human_size).is_err());
+        assert!(parse("target=big", crate::parse_human_size).is_err());
+    }
+
+    #[test]
+    fn normalizes_current_dir_components() {
+        assert_eq!(normalize(Path::new(".")), PathBuf::new());
+        assert_eq!(normalize(Path::new("./a/./b")), PathBuf::from("a/b"));
+        assert_eq!(normalize(Path::new("/x/./y")), PathBuf::from("/x/y"));
+        assert_eq!(normalize(Path::new("a/../b")), PathBuf::from("a/../b"));
+    }
+
+    #[test]
+    fn resolves_against_the_root() {
+        let root = std::env::temp_dir().join(format!("diskhound-budget-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let root = root.canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        let absolute = format!("{}=3KB", root.join("a/b").display());
+
+        let cli = [budget("a=1KB"), budget(&absolute)];
+        let config = [
+            budget("a=2KB"),
+            budget("missing=1KB"),
+            budget("/elsewhere=1KB"),
+            budget(".=9KB"),
+        ];
+        let budgets = resolve(&root, cli, config);
+        let missing = resolve(&root, [budget("typo=1KB")], []).err();
+        let outside = resolve(
+            &root.join("a"),
+            [budget(&format!("{}=1KB", root.display()))],
+            [],
+        )
+        .err();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // The command line's `a` wins; config budgets outside the tree drop.
+        assert_eq!(
+            resolved(&budgets.unwrap()),
+            [
+                (PathBuf::from("a"), 1024),
+                (PathBuf::from("a/b"), 3072),
+                (PathBuf::new(), 9216),
+            ]
+        );
+        assert!(missing
+            .unwrap()
+            .to_string()
+            .ends_with("typo does not exist"));
+        assert!(outside.unwrap().to_string().contains("is outside"));
+    }
+}
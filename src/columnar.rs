@@ -0,0 +1,66 @@
+use crate::output::{display_name, Report};
+use anyhow::Result;
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use std::io::Write;
+use std::sync::Arc;
+
+/// One row per reported directory, or one row per file when the report was
+/// collected with `--files`.
+fn record_batch(report: &Report) -> Result<RecordBatch> {
+    if let Some(files) = &report.files {
+        let schema = Schema::new(vec![
+            Field::new("path", DataType::Utf8, false),
+            Field::new("size", DataType::UInt64, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(
+                files.iter().map(|f| display_name(&f.path)),
+            )),
+            Arc::new(UInt64Array::from_iter_values(files.iter().map(|f| f.size))),
+        ];
+        return Ok(RecordBatch::try_new(Arc::new(schema), columns)?);
+    }
+
+    let entries = &report.entries;
+    let schema = Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("size", DataType::UInt64, false),
+        Field::new("file_count", DataType::UInt64, false),
+        Field::new("percentage", DataType::Float64, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            entries.iter().map(|(n, _)| display_name(n)),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            entries.iter().map(|(_, s)| s.size),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            entries.iter().map(|(_, s)| s.file_count),
+        )),
+        Arc::new(Float64Array::from_iter_values(report.percentages())),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(feature = "arrow")]
+pub fn write_arrow(w: &mut dyn Write, report: &Report) -> Result<()> {
+    let batch = record_batch(report)?;
+    let mut writer = arrow_ipc::writer::StreamWriter::try_new(w, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+pub fn write_parquet(w: &mut dyn Write, report: &Report) -> Result<()> {
+    let batch = record_batch(report)?;
+    // ArrowWriter needs a Send sink, so encode into memory first.
+    let mut buf = Vec::new();
+    let mut writer = parquet::arrow::ArrowWriter::try_new(&mut buf, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    w.write_all(&buf)?;
+    Ok(())
+}
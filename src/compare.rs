@@ -0,0 +1,190 @@
+use crate::locale;
+use crate::output::{display_name, raw_base64, Report, SCHEMA_VERSION};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// One entry aligned across both sides; `None` where a side doesn't have it.
+pub struct Row {
+    pub name: PathBuf,
+    pub left: Option<u64>,
+    pub right: Option<u64>,
+}
+
+impl Row {
+    fn delta(&self) -> i64 {
+        self.right.unwrap_or(0) as i64 - self.left.unwrap_or(0) as i64
+    }
+}
+
+pub struct Comparison {
+    pub left_root: PathBuf,
+    pub right_root: PathBuf,
+    pub left_total: u64,
+    pub right_total: u64,
+    /// The `top` largest differences, biggest first.
+    pub rows: Vec<Row>,
+    pub only_left: usize,
+    pub only_right: usize,
+    pub identical: usize,
+}
+
+impl Comparison {
+    /// Aligns the entries of two untruncated reports by name.
+    pub fn new(left: &Report, right: &Report, top: usize) -> Self {
+        let mut by_name: BTreeMap<&PathBuf, (Option<u64>, Option<u64>)> = BTreeMap::new();
+        for (name, stats) in &left.entries {
+            by_name.entry(name).or_default().0 = Some(stats.size);
+        }
+        for (name, stats) in &right.entries {
+            by_name.entry(name).or_default().1 = Some(stats.size);
+        }
+
+        let mut rows: Vec<Row> = by_name
+            .into_iter()
+            .map(|(name, (left, right))| Row {
+                name: name.clone(),
+                left,
+                right,
+            })
+            .collect();
+        let only_left = rows.iter().filter(|r| r.right.is_none()).count();
+        let only_right = rows.iter().filter(|r| r.left.is_none()).count();
+        let identical = rows.iter().filter(|r| r.left == r.right).count();
+        // Stable sort keeps equal differences in name order.
+        rows.sort_by_key(|r| std::cmp::Reverse(r.delta().unsigned_abs()));
+        rows.truncate(top);
+
+        Comparison {
+            left_root: left.root.clone(),
+            right_root: right.root.clone(),
+            left_total: left.total_size,
+            right_total: right.total_size,
+            rows,
+            only_left,
+            only_right,
+            identical,
+        }
+    }
+}
+
+fn signed_size(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{sign}{}", locale::size(delta.unsigned_abs()))
+}
+
+pub fn write_table(w: &mut dyn Write, cmp: &Comparison) -> Result<()> {
+    let names: Vec<String> = cmp.rows.iter().map(|r| display_name(&r.name)).collect();
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let side = |size: Option<u64>| size.map_or("-".to_string(), locale::size);
+
+    writeln!(
+        w,
+        "  {:<width$}  {:>12}  {:>12}  delta",
+        "",
+        "left",
+        "right",
+        width = width,
+    )?;
+    for (name, row) in names.iter().zip(&cmp.rows) {
+        let delta = match (row.left, row.right) {
+            (Some(_), None) => "only in left".to_string(),
+            (None, Some(_)) => "only in right".to_string(),
+            _ if row.delta() == 0 => "=".to_string(),
+            _ => signed_size(row.delta()),
+        };
+        writeln!(
+            w,
+            "  {:<width$}  {:>12}  {:>12}  {}",
+            name,
+            side(row.left),
+            side(row.right),
+            delta,
+            width = width,
+        )?;
+    }
+
+    writeln!(w)?;
+    writeln!(
+        w,
+        "left:  {} ({})",
+        cmp.left_root.display(),
+        locale::size(cmp.left_total)
+    )?;
+    writeln!(
+        w,
+        "right: {} ({}, {})",
+        cmp.right_root.display(),
+        locale::size(cmp.right_total),
+        signed_size(cmp.right_total as i64 - cmp.left_total as i64),
+    )?;
+    writeln!(
+        w,
+        "{} only in left, {} only in right, {} identical (showing top {} by difference)",
+        locale::count(cmp.only_left as u64),
+        locale::count(cmp.only_right as u64),
+        locale::count(cmp.identical as u64),
+        cmp.rows.len(),
+    )?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonComparison {
+    schema_version: u32,
+    left: JsonSide,
+    right: JsonSide,
+    entries: Vec<JsonRow>,
+    only_left: usize,
+    only_right: usize,
+    identical: usize,
+}
+
+#[derive(Serialize)]
+struct JsonSide {
+    path: String,
+    total_size: u64,
+}
+
+#[derive(Serialize)]
+struct JsonRow {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_base64: Option<String>,
+    /// `null` when the entry only exists on the other side.
+    left_size: Option<u64>,
+    right_size: Option<u64>,
+    delta: i64,
+}
+
+pub fn write_json(w: &mut dyn Write, cmp: &Comparison) -> Result<()> {
+    let output = JsonComparison {
+        schema_version: SCHEMA_VERSION,
+        left: JsonSide {
+            path: cmp.left_root.to_string_lossy().to_string(),
+            total_size: cmp.left_total,
+        },
+        right: JsonSide {
+            path: cmp.right_root.to_string_lossy().to_string(),
+            total_size: cmp.right_total,
+        },
+        entries: cmp
+            .rows
+            .iter()
+            .map(|r| JsonRow {
+                name: display_name(&r.name),
+                name_base64: raw_base64(r.name.as_os_str()),
+                left_size: r.left,
+                right_size: r.right,
+                delta: r.delta(),
+            })
+            .collect(),
+        only_left: cmp.only_left,
+        only_right: cmp.only_right,
+        identical: cmp.identical,
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
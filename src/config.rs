@@ -0,0 +1,363 @@
+use crate::budget::{self, Budget};
+use anyhow::{bail, Context, Result};
+use clap::{ArgAction, Command};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Settings read from the config file: `key = value` lines grouped under
+/// `[section]` headers, with `#` comments.
+///
+/// ```text
+/// [defaults]
+/// exclude = node_modules, .git
+///
+/// [presets]
+/// media = .thumbnails, Thumbs, cache
+///
+/// [tags]
+/// /srv/ml-data = team:vision
+/// /srv/web = team:web
+///
+/// [budgets]
+/// target = 2GB
+/// ```
+#[derive(Default)]
+pub struct Config {
+    /// Flag values by long name, used where neither the command line nor the
+    /// environment sets them.
+    pub defaults: Vec<(String, String)>,
+    /// User-defined `--preset`s: names and the directory names they exclude.
+    /// One named like a built-in replaces it.
+    pub presets: Vec<(String, Vec<String>)>,
+    /// Absolute paths and the tag their bytes are attributed to with
+    /// `--by-tag`, in file order.
+    pub tags: Vec<(PathBuf, String)>,
+    /// Size limits, relative to the scanned root or absolute.
+    pub budgets: Vec<Budget>,
+}
+
+/// `diskhound/config` in the platform's configuration directory.
+pub fn default_path() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+    };
+    base.map(|b| b.join("diskhound").join("config"))
+}
+
+/// Reads `path` (from `--config`), which must exist, or else the default
+/// config file if there is one. Sizes are parsed with `parse_size`.
+pub fn load(path: Option<&Path>, parse_size: impl Fn(&str) -> Result<u64>) -> Result<Config> {
+    let (path, text) = match path {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            (path.to_path_buf(), text)
+        }
+        None => {
+            let Some(path) = default_path() else {
+                return Ok(Config::default());
+            };
+            match std::fs::read_to_string(&path) {
+                Ok(text) => (path, text),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok(Config::default())
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to read {}", path.display()))
+                }
+            }
+        }
+    };
+    parse(&path, &text, parse_size)
+}
+
+fn parse(path: &Path, text: &str, parse_size: impl Fn(&str) -> Result<u64>) -> Result<Config> {
+    let mut config = Config::default();
+    let mut section = None;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = || format!("{}:{}", path.display(), n + 1);
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            match name.trim() {
+                "defaults" | "presets" | "tags" | "budgets" => {
+                    section = Some(name.trim().to_string())
+                }
+                other => bail!("{}: unknown section [{other}]", at()),
+            }
+            continue;
+        }
+        // Flag and preset names never contain `=`, though their values
+        // might; tags and sizes never do, though paths might.
+        let split = match section.as_deref() {
+            Some("defaults" | "presets") => line.split_once('='),
+            _ => line.rsplit_once('='),
+        };
+        let Some((key, value)) = split else {
+            bail!("{}: expected `key = value`", at());
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match section.as_deref() {
+            Some("defaults") => config
+                .defaults
+                .push((key.trim_start_matches("--").to_string(), value.to_string())),
+            Some("presets") => config.presets.push((
+                key.to_string(),
+                value
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect(),
+            )),
+            Some("tags") => {
+                if value.is_empty() {
+                    bail!("{}: empty tag for {key}", at());
+                }
+                let tagged = PathBuf::from(key);
+                if !tagged.is_absolute() {
+                    bail!("{}: tagged path {key} must be absolute", at());
+                }
+                // Scans compare against canonical roots; paths that don't
+                // exist (yet) are kept as written.
+                let tagged = tagged.canonicalize().unwrap_or(tagged);
+                config.tags.push((tagged, value.to_string()));
+            }
+            Some("budgets") => config.budgets.push(Budget {
+                path: budget::normalize(Path::new(key)),
+                limit: parse_size(value).with_context(at)?,
+            }),
+            _ => bail!("{}: {key} is outside any section", at()),
+        }
+    }
+    Ok(config)
+}
+
+/// The config file named on the command line (`--config`) or by
+/// `DISKHOUND_CONFIG`, found before the full parse because the file
+/// supplies defaults for it.
+pub fn path_from_args(args: impl Iterator<Item = OsString>) -> Option<PathBuf> {
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|a| a.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    std::env::var_os("DISKHOUND_CONFIG").map(PathBuf::from)
+}
+
+/// Gives every flag of `command` and its subcommands a default from its
+/// `DISKHOUND_*` environment variable (`--skip-hidden` reads
+/// `DISKHOUND_SKIP_HIDDEN`), or else from the config file's `[defaults]`.
+/// Flags given on the command line still win. Repeatable flags such as
+/// `--exclude` take comma-separated lists, and switches take `true` or
+/// `false` (or `1`/`0`, `yes`/`no`, `on`/`off`).
+pub fn layer_defaults(command: Command, defaults: &[(String, String)]) -> Result<Command> {
+    let mut known = HashSet::new();
+    long_names(&command, &mut known);
+    if let Some((key, _)) = defaults.iter().find(|(key, _)| !known.contains(key)) {
+        bail!("the config file's [defaults] sets unknown option --{key}");
+    }
+    Ok(layer(command, defaults))
+}
+
+fn long_names(command: &Command, names: &mut HashSet<String>) {
+    names.extend(
+        command
+            .get_arguments()
+            .filter_map(|a| a.get_long().map(String::from)),
+    );
+    for sub in command.get_subcommands() {
+        long_names(sub, names);
+    }
+}
+
+fn layer(command: Command, defaults: &[(String, String)]) -> Command {
+    let mut command = command.mut_args(|arg| {
+        let Some(long) = arg.get_long().filter(|l| !matches!(*l, "help" | "version")) else {
+            return arg;
+        };
+        let var = format!("DISKHOUND_{}", long.to_uppercase().replace('-', "_"));
+        let value = std::env::var(&var).ok().or_else(|| {
+            defaults
+                .iter()
+                .find(|(key, _)| key == long)
+                .map(|(_, value)| value.clone())
+        });
+        let Some(value) = value else {
+            return arg;
+        };
+        match arg.get_action() {
+            ArgAction::SetTrue => arg.default_value(switch(&value)),
+            ArgAction::Append => arg.default_values(value.split(',').map(|v| v.trim().to_string())),
+            _ => arg.default_value(value),
+        }
+    });
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        command = command.mut_subcommand(name, |sub| layer(sub, defaults));
+    }
+    command
+}
+
+/// Anything else is passed on for clap to reject with the flag's name.
+fn switch(value: &str) -> String {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => "true".to_string(),
+        "0" | "false" | "no" | "off" | "" => "false".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn parsed(text: &str) -> Result<Config> {
+        parse(Path::new("config"), text, crate::parse_human_size)
+    }
+
+    #[test]
+    fn parses_every_section() {
+        let config = parsed(
+            "# comment\n\
+             [defaults]\n\
+             --exclude = node_modules, .git\n\
+             budget = d1=1KB\n\
+             \n\
+             [ presets ]\n\
+             media = .thumbnails, ,cache\n\
+             [tags]\n\
+             /srv/a=b = team:web\n\
+             [budgets]\n\
+             ./dist=x = 2KB\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.defaults,
+            [
+                ("exclude".to_string(), "node_modules, .git".to_string()),
+                ("budget".to_string(), "d1=1KB".to_string()),
+            ]
+        );
+        assert_eq!(
+            config.presets,
+            [(
+                "media".to_string(),
+                vec![".thumbnails".to_string(), "cache".to_string()]
+            )]
+        );
+        assert_eq!(
+            config.tags,
+            [(PathBuf::from("/srv/a=b"), "team:web".to_string())]
+        );
+        assert_eq!(config.budgets.len(), 1);
+        assert_eq!(config.budgets[0].path, PathBuf::from("dist=x"));
+        assert_eq!(config.budgets[0].limit, 2048);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = |text: &str| format!("{:#}", parsed(text).err().unwrap());
+        assert_eq!(error("key = value"), "config:1: key is outside any section");
+        assert_eq!(error("\n[other]"), "config:2: unknown section [other]");
+        assert_eq!(
+            error("[defaults]\nno value"),
+            "config:2: expected `key = value`"
+        );
+        assert_eq!(
+            error("[tags]\nrelative = t"),
+            "config:2: tagged path relative must be absolute"
+        );
+        assert_eq!(error("[tags]\n/a ="), "config:2: empty tag for /a");
+        assert!(error("[budgets]\na = lots").starts_with("config:2: "));
+    }
+
+    #[test]
+    fn switch_values() {
+        for on in ["1", "true", "YES", "On"] {
+            assert_eq!(switch(on), "true");
+        }
+        for off in ["0", "false", "No", "off", ""] {
+            assert_eq!(switch(off), "false");
+        }
+        assert_eq!(switch("maybe"), "maybe");
+    }
+
+    #[test]
+    fn command_line_then_environment_then_file() {
+        let command = || {
+            Command::new("t")
+                .arg(Arg::new("layer-test-cli").long("layer-test-cli"))
+                .arg(Arg::new("layer-test-env").long("layer-test-env"))
+                .arg(Arg::new("layer-test-file").long("layer-test-file"))
+                .arg(
+                    Arg::new("layer-test-switch")
+                        .long("layer-test-switch")
+                        .action(ArgAction::SetTrue),
+                )
+                .subcommand(
+                    Command::new("sub").arg(
+                        Arg::new("layer-test-list")
+                            .long("layer-test-list")
+                            .action(ArgAction::Append),
+                    ),
+                )
+        };
+        let defaults: Vec<(String, String)> = [
+            ("layer-test-cli", "file"),
+            ("layer-test-env", "file"),
+            ("layer-test-file", "file"),
+            ("layer-test-switch", "yes"),
+            ("layer-test-list", "a, b"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        // Names no other test or real flag uses, so setting them is safe.
+        std::env::set_var("DISKHOUND_LAYER_TEST_CLI", "env");
+        std::env::set_var("DISKHOUND_LAYER_TEST_ENV", "env");
+        let matches = layer_defaults(command(), &defaults)
+            .unwrap()
+            .get_matches_from(["t", "--layer-test-cli", "cli", "sub"]);
+        let value = |name: &str| matches.get_one::<String>(name).unwrap().as_str();
+        assert_eq!(value("layer-test-cli"), "cli");
+        assert_eq!(value("layer-test-env"), "env");
+        assert_eq!(value("layer-test-file"), "file");
+        assert!(matches.get_flag("layer-test-switch"));
+        let list: Vec<&String> = matches
+            .subcommand_matches("sub")
+            .unwrap()
+            .get_many("layer-test-list")
+            .unwrap()
+            .collect();
+        assert_eq!(list, ["a", "b"]);
+
+        let unknown = [("no-such-flag".to_string(), "1".to_string())];
+        assert_eq!(
+            layer_defaults(command(), &unknown)
+                .err()
+                .unwrap()
+                .to_string(),
+            "the config file's [defaults] sets unknown option --no-such-flag"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use crate::throttle::RateLimiter;
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+const SAMPLE_BLOCK: u64 = 4096;
+const READ_CHUNK: usize = 64 * 1024;
+
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub hash: String,
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes freed by keeping a single copy.
+    pub fn reclaimable(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Finds files with identical content in three stages, each only looking at the
+/// survivors of the previous one: equal sizes, equal hashes of the first and last
+/// 4 KiB, and finally equal full-content blake3 hashes.
+pub fn find_duplicates(
+    files: Vec<(PathBuf, u64)>,
+    threads: usize,
+    limiter: &RateLimiter,
+) -> Result<Vec<DuplicateGroup>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+
+    let by_size = collisions(
+        files
+            .into_iter()
+            .filter(|(_, size)| *size > 0)
+            .map(|(path, size)| (size, path)),
+    );
+
+    debug!(
+        "{} files share a size with another file",
+        by_size.values().map(Vec::len).sum::<usize>()
+    );
+    let sampled = collisions(pool.install(|| {
+        flatten(by_size)
+            .into_par_iter()
+            .filter_map(|(size, path)| {
+                let hash = hash_sample(&path, size, limiter)
+                    .map_err(|err| info!("could not hash {}: {err}", path.display()))
+                    .ok()?;
+                Some(((size, hash), path))
+            })
+            .collect::<Vec<_>>()
+    }));
+
+    // Files no larger than the two sample blocks were hashed in full already.
+    let (small, large): (HashMap<_, _>, HashMap<_, _>) = sampled
+        .into_iter()
+        .partition(|((size, _), _)| *size <= SAMPLE_BLOCK * 2);
+
+    debug!(
+        "{} files match on sampled blocks",
+        small
+            .values()
+            .chain(large.values())
+            .map(Vec::len)
+            .sum::<usize>()
+    );
+    let hashed = collisions(pool.install(|| {
+        flatten(large)
+            .into_par_iter()
+            .filter_map(|((size, _), path)| {
+                let hash = hash_full(&path, limiter)
+                    .map_err(|err| info!("could not hash {}: {err}", path.display()))
+                    .ok()?;
+                Some(((size, hash), path))
+            })
+            .collect::<Vec<_>>()
+    }));
+
+    let mut groups: Vec<DuplicateGroup> = small
+        .into_iter()
+        .chain(hashed)
+        .map(|((size, hash), mut paths)| {
+            paths.sort();
+            DuplicateGroup {
+                size,
+                hash: hash.to_hex().to_string(),
+                paths,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.reclaimable()));
+    Ok(groups)
+}
+
+#[derive(Default)]
+struct TreeNode {
+    /// Name and content hash of every child, directories prefixed with `d`.
+    children: Vec<(OsString, String)>,
+    size: u64,
+    /// Set when a file somewhere below has no duplicate, so the subtree can't
+    /// have one either.
+    unique: bool,
+}
+
+/// Finds directories whose subtrees are identical: the same names, layout,
+/// and file contents. Each directory is hashed from its children's names and
+/// hashes, Merkle-style, and `groups` supplies the content hashes, so only
+/// files that already have a duplicate can be part of a duplicate tree.
+/// `files` is every file under the scanned directories, with `None` where
+/// the size couldn't be read. Copies nested in a reported pair are left out.
+pub fn find_duplicate_trees(
+    dirs: Vec<PathBuf>,
+    files: &[(PathBuf, Option<u64>)],
+    groups: &[DuplicateGroup],
+) -> Vec<DuplicateGroup> {
+    let content: HashMap<&Path, &str> = groups
+        .iter()
+        .flat_map(|g| g.paths.iter().map(|p| (p.as_path(), g.hash.as_str())))
+        .collect();
+    let mut nodes: HashMap<PathBuf, TreeNode> = dirs
+        .into_iter()
+        .map(|dir| (dir, TreeNode::default()))
+        .collect();
+    for (path, size) in files {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let Some(node) = nodes.get_mut(parent) else {
+            continue;
+        };
+        match (size, content.get(path.as_path())) {
+            // Empty files are never hashed but are equal to each other.
+            (Some(0), _) => node.children.push((name.to_os_string(), String::new())),
+            (Some(size), Some(hash)) => {
+                node.children.push((name.to_os_string(), hash.to_string()));
+                node.size += size;
+            }
+            _ => node.unique = true,
+        }
+    }
+
+    // Deepest first, so every directory is complete before its parent.
+    let mut order: Vec<PathBuf> = nodes.keys().cloned().collect();
+    order.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    let mut by_hash: HashMap<blake3::Hash, (u64, Vec<PathBuf>)> = HashMap::new();
+    for dir in order {
+        let Some(mut node) = nodes.remove(&dir) else {
+            continue;
+        };
+        let parent = dir.parent().and_then(|p| nodes.get_mut(p));
+        if node.unique {
+            if let Some(parent) = parent {
+                parent.unique = true;
+            }
+            continue;
+        }
+        node.children.sort();
+        let mut hasher = blake3::Hasher::new();
+        for (name, hash) in &node.children {
+            hasher.update(name.as_encoded_bytes());
+            hasher.update(&[0]);
+            hasher.update(hash.as_bytes());
+            hasher.update(&[0]);
+        }
+        let hash = hasher.finalize();
+        if let (Some(parent), Some(name)) = (parent, dir.file_name()) {
+            parent
+                .children
+                .push((name.to_os_string(), format!("d{}", hash.to_hex())));
+            parent.size += node.size;
+        }
+        if node.size > 0 {
+            let entry = by_hash.entry(hash).or_insert((node.size, Vec::new()));
+            entry.1.push(dir);
+        }
+    }
+    by_hash.retain(|_, (_, paths)| paths.len() > 1);
+
+    let duplicated: HashSet<&Path> = by_hash
+        .values()
+        .flat_map(|(_, paths)| paths.iter().map(PathBuf::as_path))
+        .collect();
+    let nested: HashSet<blake3::Hash> = by_hash
+        .iter()
+        .filter(|(_, (_, paths))| {
+            paths
+                .iter()
+                .all(|p| p.parent().is_some_and(|parent| duplicated.contains(parent)))
+        })
+        .map(|(hash, _)| *hash)
+        .collect();
+    debug!(
+        "{} duplicate trees, {} nested in others",
+        by_hash.len(),
+        nested.len()
+    );
+
+    let mut trees: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(hash, _)| !nested.contains(hash))
+        .map(|(hash, (size, mut paths))| {
+            paths.sort();
+            DuplicateGroup {
+                size,
+                hash: hash.to_hex().to_string(),
+                paths,
+            }
+        })
+        .collect();
+    trees.sort_by_key(|g| std::cmp::Reverse(g.reclaimable()));
+    trees
+}
+
+/// Buckets paths by key, keeping only buckets with more than one member.
+fn collisions<K: std::hash::Hash + Eq>(
+    items: impl IntoIterator<Item = (K, PathBuf)>,
+) -> HashMap<K, Vec<PathBuf>> {
+    let mut buckets: HashMap<K, Vec<PathBuf>> = HashMap::new();
+    for (key, path) in items {
+        buckets.entry(key).or_default().push(path);
+    }
+    buckets.retain(|_, paths| paths.len() > 1);
+    buckets
+}
+
+fn flatten<K: Copy>(buckets: HashMap<K, Vec<PathBuf>>) -> Vec<(K, PathBuf)> {
+    buckets
+        .into_iter()
+        .flat_map(|(key, paths)| paths.into_iter().map(move |p| (key, p)))
+        .collect()
+}
+
+fn hash_sample(path: &Path, size: u64, limiter: &RateLimiter) -> Result<blake3::Hash> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; SAMPLE_BLOCK as usize];
+
+    if size <= SAMPLE_BLOCK * 2 {
+        let mut data = Vec::with_capacity(size as usize);
+        limiter.acquire(size);
+        file.read_to_end(&mut data)?;
+        hasher.update(&data);
+        return Ok(hasher.finalize());
+    }
+
+    limiter.acquire(SAMPLE_BLOCK * 2);
+    file.read_exact(&mut buf)?;
+    hasher.update(&buf);
+    file.seek(SeekFrom::Start(size - SAMPLE_BLOCK))?;
+    file.read_exact(&mut buf)?;
+    hasher.update(&buf);
+    Ok(hasher.finalize())
+}
+
+pub fn hash_full(path: &Path, limiter: &RateLimiter) -> Result<blake3::Hash> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; READ_CHUNK];
+    loop {
+        limiter.acquire(READ_CHUNK as u64);
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Files gathered during the walk for duplicate detection. Hardlinks to an
+/// already-seen inode are skipped since they share storage rather than copy it.
+#[derive(Default)]
+pub struct Candidates {
+    files: Vec<(PathBuf, u64)>,
+    #[cfg(unix)]
+    seen: std::collections::HashSet<(u64, u64)>,
+}
+
+impl Candidates {
+    pub fn add(&mut self, path: PathBuf, metadata: &std::fs::Metadata) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.nlink() > 1 && !self.seen.insert((metadata.dev(), metadata.ino())) {
+                return;
+            }
+        }
+        self.files.push((path, metadata.len()));
+    }
+
+    pub fn into_files(self) -> Vec<(PathBuf, u64)> {
+        self.files
+    }
+}
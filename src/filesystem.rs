@@ -0,0 +1,285 @@
+use crate::breakdown::Shares;
+use crate::locale;
+#[cfg(unix)]
+use crate::mounts;
+use crate::output::SCHEMA_VERSION;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Space on the filesystem holding a path, as `df` reports it.
+#[derive(Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Usage {
+    pub mount_point: PathBuf,
+    pub total: u64,
+    /// Blocks in use, including those reserved for root.
+    pub used: u64,
+    /// Free to unprivileged users.
+    pub available: u64,
+    /// Absent in reports written before inodes were recorded, and on
+    /// filesystems without a fixed inode table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inodes_total: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inodes_used: Option<u64>,
+}
+
+/// `None` where the platform has no `statvfs` or the call fails.
+#[cfg(unix)]
+pub fn usage(path: &Path) -> Option<Usage> {
+    let path = std::fs::canonicalize(path).ok()?;
+    stat(&path, mount_point(&path))
+}
+
+#[cfg(not(unix))]
+pub fn usage(_path: &Path) -> Option<Usage> {
+    None
+}
+
+#[cfg(unix)]
+fn stat(path: &Path, mount_point: PathBuf) -> Option<Usage> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is NUL-terminated and `stat` is a valid out-pointer.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    let inodes = stat.f_files as u64;
+    Some(Usage {
+        mount_point,
+        total: stat.f_blocks as u64 * block,
+        used: (stat.f_blocks as u64).saturating_sub(stat.f_bfree as u64) * block,
+        available: stat.f_bavail as u64 * block,
+        inodes_total: (inodes > 0).then_some(inodes),
+        inodes_used: (inodes > 0).then(|| inodes.saturating_sub(stat.f_ffree as u64)),
+    })
+}
+
+/// A mounted filesystem with its space.
+pub struct Mounted {
+    pub fstype: String,
+    pub source: String,
+    pub usage: Usage,
+}
+
+/// Mounted filesystems that hold data; pseudo filesystems such as proc or
+/// sysfs report no blocks and are left out.
+#[cfg(unix)]
+pub fn mounted() -> Vec<Mounted> {
+    mounts::list(&mounts::mountinfo())
+        .into_iter()
+        .filter_map(|entry| {
+            let usage = stat(&entry.point, entry.point.clone())?;
+            (usage.total > 0).then_some(Mounted {
+                fstype: entry.fstype,
+                source: entry.source,
+                usage,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn mounted() -> Vec<Mounted> {
+    Vec::new()
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64 * 100.0
+    }
+}
+
+/// `diskhound fs`: one numbered row per filesystem, like `df -hT -i` in one.
+pub fn write_table(w: &mut dyn Write, filesystems: &[Mounted]) -> Result<()> {
+    if filesystems.is_empty() {
+        writeln!(w, "No mounted filesystems found")?;
+        return Ok(());
+    }
+    let points: Vec<String> = filesystems
+        .iter()
+        .map(|f| f.usage.mount_point.display().to_string())
+        .collect();
+    let width = points.iter().map(|p| p.chars().count()).max().unwrap_or(0);
+    let type_width = filesystems
+        .iter()
+        .map(|f| f.fstype.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    writeln!(
+        w,
+        "  {:>3}  {:<width$}  {:<type_width$}  {:>10}  {:>10}  {:>10}  {:>6}  {:>6}",
+        "#", "mount", "type", "size", "used", "free", "use%", "inodes",
+    )?;
+    for (i, (point, fs)) in points.iter().zip(filesystems).enumerate() {
+        let usage = &fs.usage;
+        // Like df, the share of space available to users.
+        let used = percent(usage.used, usage.used + usage.available);
+        let inodes = match (usage.inodes_used, usage.inodes_total) {
+            (Some(used), Some(total)) => format!("{}%", locale::fixed(percent(used, total), 0)),
+            _ => "-".to_string(),
+        };
+        writeln!(
+            w,
+            "  {:>3}  {:<width$}  {:<type_width$}  {:>10}  {:>10}  {:>10}  {:>5}%  {:>6}",
+            i + 1,
+            point,
+            fs.fstype,
+            locale::size(usage.total),
+            locale::size(usage.used),
+            locale::size(usage.available),
+            locale::fixed(used, 0),
+            inodes,
+        )?;
+    }
+    writeln!(w)?;
+    writeln!(w, "Scan one with: diskhound fs --scan N")?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonFilesystems {
+    schema_version: u32,
+    filesystems: Vec<JsonFilesystem>,
+}
+
+#[derive(Serialize)]
+struct JsonFilesystem {
+    #[serde(rename = "type")]
+    fstype: String,
+    source: String,
+    #[serde(flatten)]
+    usage: Usage,
+}
+
+pub fn write_json(w: &mut dyn Write, filesystems: &[Mounted]) -> Result<()> {
+    let output = JsonFilesystems {
+        schema_version: SCHEMA_VERSION,
+        filesystems: filesystems
+            .iter()
+            .map(|f| JsonFilesystem {
+                fstype: f.fstype.clone(),
+                source: f.source.clone(),
+                usage: f.usage.clone(),
+            })
+            .collect(),
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
+
+/// Bytes and files one filesystem contributed to a scan.
+pub struct FilesystemTotal {
+    pub mount_point: PathBuf,
+    /// Empty where the mount table couldn't be read.
+    pub fstype: String,
+    /// What is mounted: a device, or a share such as `//nas/media`. Empty
+    /// where the mount table couldn't be read.
+    pub source: String,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+/// Which filesystems a scan's bytes live on, so data behind a network or
+/// removable mount isn't mistaken for local usage.
+#[derive(Default)]
+pub struct Spread {
+    #[cfg(unix)]
+    by_device: HashMap<u64, usize>,
+    #[cfg(unix)]
+    types: Option<HashMap<PathBuf, (String, String)>>,
+    /// In the order the walk first reached them.
+    pub filesystems: Vec<FilesystemTotal>,
+    /// Bytes per mount point within each entry.
+    pub shares: Shares,
+}
+
+impl Spread {
+    #[cfg(unix)]
+    pub fn add(&mut self, key: &Path, file: &Path, metadata: &std::fs::Metadata) {
+        use std::os::unix::fs::MetadataExt;
+        let index = match self.by_device.get(&metadata.dev()) {
+            Some(&index) => index,
+            None => {
+                let point = mount_point(file);
+                let (fstype, source) = self
+                    .types
+                    .get_or_insert_with(|| {
+                        mounts::list(&mounts::mountinfo())
+                            .into_iter()
+                            .map(|m| (m.point, (m.fstype, m.source)))
+                            .collect()
+                    })
+                    .get(&point)
+                    .cloned()
+                    .unwrap_or_default();
+                self.filesystems.push(FilesystemTotal {
+                    mount_point: point,
+                    fstype,
+                    source,
+                    size: 0,
+                    file_count: 0,
+                });
+                self.by_device
+                    .insert(metadata.dev(), self.filesystems.len() - 1);
+                self.filesystems.len() - 1
+            }
+        };
+        let fs = &mut self.filesystems[index];
+        fs.size += metadata.len();
+        fs.file_count += 1;
+        let label = fs.mount_point.to_string_lossy().to_string();
+        self.shares.add(key, &label, metadata.len());
+    }
+
+    /// Device numbers aren't available outside Unix.
+    #[cfg(not(unix))]
+    pub fn add(&mut self, _key: &Path, _file: &Path, _metadata: &std::fs::Metadata) {}
+
+    /// The filesystem mounted at `mount_point`, a label from [`Spread::shares`].
+    pub fn at(&self, mount_point: &str) -> Option<&FilesystemTotal> {
+        self.filesystems
+            .iter()
+            .find(|fs| fs.mount_point.as_os_str() == mount_point)
+    }
+
+    /// `None` unless the scan crossed into a second filesystem.
+    pub fn finish(self, cumulative: bool) -> Option<Spread> {
+        if self.filesystems.len() < 2 {
+            return None;
+        }
+        let mut spread = self;
+        spread
+            .filesystems
+            .sort_by_key(|fs| std::cmp::Reverse(fs.size));
+        if cumulative {
+            spread.shares = spread.shares.cumulative();
+        }
+        Some(spread)
+    }
+}
+
+/// The topmost ancestor of `path` (canonical) on the same device.
+#[cfg(unix)]
+fn mount_point(path: &Path) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+    let Ok(dev) = std::fs::metadata(path).map(|m| m.dev()) else {
+        return path.to_path_buf();
+    };
+    let mut point = path;
+    while let Some(parent) = point.parent() {
+        match std::fs::metadata(parent) {
+            Ok(m) if m.dev() == dev => point = parent,
+            _ => break,
+        }
+    }
+    point.to_path_buf()
+}
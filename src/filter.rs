@@ -0,0 +1,500 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Component, Path};
+
+/// Include and exclude rules read from files, tried in order against each
+/// entry's path below the scanned root; the first match decides, and entries
+/// nothing matches are kept. An excluded directory is skipped with
+/// everything below it, as rsync does.
+#[derive(Clone, Default)]
+pub struct Filter {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone)]
+struct Rule {
+    include: bool,
+    /// The `!` modifier: the rule applies to entries the pattern doesn't
+    /// match.
+    negate: bool,
+    /// A trailing `/`: only directories match.
+    dir_only: bool,
+    /// A leading `/`: matched against the whole path from the scanned root
+    /// instead of its trailing components.
+    anchored: bool,
+    /// Whether the pattern (less the slashes above) contains `/` or `**`,
+    /// and so is matched against paths rather than names.
+    path: bool,
+    /// A trailing `/***`: the directory itself as well as what's in it.
+    subtree: bool,
+    pattern: Vec<u8>,
+}
+
+impl Filter {
+    /// Reads `path` in rsync's `--filter` syntax; see [`Filter::parse_rules`].
+    pub fn load_rules(path: &Path) -> Result<Filter> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Filter::parse_rules(&text, &path.display().to_string())
+    }
+
+    /// rsync filter rules, one per line: `- PATTERN` or `exclude PATTERN`,
+    /// `+ PATTERN` or `include PATTERN`, and `!` to clear the rules above.
+    /// Hide and show rules (`H`, `S`) count as exclude and include; protect
+    /// and risk rules, which only guard deletions, are ignored. A line
+    /// without a rule is an exclude pattern, as in an `--exclude-from` file,
+    /// and blank lines and ones starting with `#` or `;` are skipped. `what`
+    /// names the source in errors.
+    pub fn parse_rules(text: &str, what: &str) -> Result<Filter> {
+        let mut filter = Filter::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.trim() == "!" || line == "clear" {
+                filter.rules.clear();
+                continue;
+            }
+            let rule = parse_rule(line)
+                .with_context(|| format!("{what} line {}: {line:?}", number + 1))?;
+            filter.rules.extend(rule);
+        }
+        Ok(filter)
+    }
+
+    /// Reads an exclude list from `path`; see [`Filter::parse_excludes`].
+    pub fn load_excludes(path: &Path) -> Result<Filter> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(Filter::parse_excludes(&text))
+    }
+
+    /// Exclude patterns one per line, as in a `.gitignore`: `#` starts a
+    /// comment line, `!PATTERN` brings back what an earlier line excluded,
+    /// a trailing `/` matches directories only, and a `/` at the start or in
+    /// the middle ties the pattern to the scanned root (`**/` frees it
+    /// again). The last matching line decides.
+    pub fn parse_excludes(text: &str) -> Filter {
+        let mut rules: Vec<Rule> = text
+            .lines()
+            .map(|line| line.strip_suffix('\r').unwrap_or(line).trim_end())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.strip_prefix('!') {
+                Some(pattern) => Rule::gitignore(true, pattern),
+                None => Rule::gitignore(false, line),
+            })
+            .collect();
+        rules.reverse();
+        Filter { rules }
+    }
+
+    /// Tries `other`'s rules after these.
+    pub fn extend(&mut self, other: Filter) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Whether the entry at `path`, relative to the scanned root, is
+    /// filtered out. The root itself (an empty `path`) never is, so that
+    /// catch-all rules like `- *` only apply below it.
+    pub fn excludes(&self, path: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let mut relative = Vec::new();
+        for component in path.components() {
+            if let Component::Normal(name) = component {
+                if !relative.is_empty() {
+                    relative.push(b'/');
+                }
+                relative.extend_from_slice(name.as_encoded_bytes());
+            }
+        }
+        if relative.is_empty() {
+            return false;
+        }
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(&relative, is_dir))
+            .is_some_and(|rule| !rule.include)
+    }
+}
+
+/// One line's rule, or `None` for rules that don't affect what's scanned.
+fn parse_rule(line: &str) -> Result<Option<Rule>> {
+    let Some((kind, modifiers, pattern)) = split_rule(line) else {
+        return Ok(Some(Rule::new(false, false, line)));
+    };
+    let mut negate = false;
+    for modifier in modifiers.chars() {
+        match modifier {
+            '!' => negate = true,
+            // Sender-side rules are what a scan sees.
+            's' => {}
+            // Receiver-side and perishable rules only matter to deletions.
+            'r' | 'p' => return Ok(None),
+            _ => bail!("the {modifier:?} rule modifier isn't supported"),
+        }
+    }
+    match kind {
+        '+' | 'S' => Ok(Some(Rule::new(true, negate, pattern))),
+        '-' | 'H' => Ok(Some(Rule::new(false, negate, pattern))),
+        'P' | 'R' => Ok(None),
+        '!' => bail!("clear rules take no pattern"),
+        _ => bail!("merge rules aren't supported; list the rules in one file"),
+    }
+}
+
+/// `RULE[,MODIFIERS] PATTERN` as its rule character, modifiers, and
+/// pattern; the comma is optional after a one-character rule. `None` when
+/// the line doesn't start with a rule.
+fn split_rule(line: &str) -> Option<(char, &str, &str)> {
+    const WORDS: &[(&str, char)] = &[
+        ("exclude", '-'),
+        ("include", '+'),
+        ("hide", 'H'),
+        ("show", 'S'),
+        ("protect", 'P'),
+        ("risk", 'R'),
+        ("merge", '.'),
+        ("dir-merge", ':'),
+        ("clear", '!'),
+    ];
+    let (head, pattern) = line.split_once([' ', '_'])?;
+    let (name, modifiers) = match head.split_once(',') {
+        Some(split) => split,
+        None if WORDS.iter().any(|(word, _)| *word == head) => (head, ""),
+        None => head.split_at(head.chars().next()?.len_utf8()),
+    };
+    let kind = match WORDS.iter().find(|(word, _)| *word == name) {
+        Some((_, kind)) => *kind,
+        None => {
+            let mut chars = name.chars();
+            let kind = chars.next().filter(|c| "+-HSPR.:!".contains(*c))?;
+            chars.next().is_none().then_some(kind)?
+        }
+    };
+    modifiers
+        .chars()
+        .all(|c| "!/Cenpwxsr".contains(c))
+        .then_some((kind, modifiers, pattern))
+}
+
+impl Rule {
+    fn new(include: bool, negate: bool, pattern: &str) -> Rule {
+        let mut pattern = pattern.as_bytes();
+        let dir_only = pattern.len() > 1 && pattern.ends_with(b"/");
+        if dir_only {
+            pattern = &pattern[..pattern.len() - 1];
+        }
+        let subtree = pattern.ends_with(b"/***");
+        if subtree {
+            pattern = &pattern[..pattern.len() - 4];
+        }
+        let anchored = pattern.starts_with(b"/");
+        if anchored {
+            pattern = &pattern[1..];
+        }
+        Rule {
+            include,
+            negate,
+            dir_only,
+            anchored,
+            path: pattern.contains(&b'/') || pattern.windows(2).any(|w| w == b"**"),
+            subtree,
+            pattern: pattern.to_vec(),
+        }
+    }
+
+    fn gitignore(include: bool, pattern: &str) -> Rule {
+        let (pattern, floating) = match pattern.strip_prefix("**/") {
+            Some(pattern) => (pattern, true),
+            None => (pattern, false),
+        };
+        let mut rule = Rule::new(include, false, pattern);
+        rule.anchored |= !floating && rule.pattern.contains(&b'/');
+        rule
+    }
+
+    fn matches(&self, path: &[u8], is_dir: bool) -> bool {
+        let matched = (is_dir || !self.dir_only) && self.matches_path(path, is_dir);
+        matched != self.negate
+    }
+
+    fn matches_path(&self, path: &[u8], is_dir: bool) -> bool {
+        if self.subtree {
+            // `dir/***` is `dir` (a directory) and `dir/**`.
+            let mut inside = self.pattern.clone();
+            inside.extend_from_slice(b"/**");
+            return (is_dir && self.matches_tail(&self.pattern, path))
+                || self.matches_tail(&inside, path);
+        }
+        self.matches_tail(&self.pattern, path)
+    }
+
+    /// Anchored patterns match the whole path, ones with a slash any run of
+    /// trailing components, and plain names the last component.
+    fn matches_tail(&self, pattern: &[u8], path: &[u8]) -> bool {
+        if self.anchored {
+            return wildmatch(pattern, path);
+        }
+        if !self.path && !self.subtree {
+            let name = match path.iter().rposition(|&b| b == b'/') {
+                Some(slash) => &path[slash + 1..],
+                None => path,
+            };
+            return wildmatch(pattern, name);
+        }
+        std::iter::once(0)
+            .chain(
+                path.iter()
+                    .enumerate()
+                    .filter(|(_, &b)| b == b'/')
+                    .map(|(i, _)| i + 1),
+            )
+            .any(|start| wildmatch(pattern, &path[start..]))
+    }
+}
+
+/// rsync's wildcards: `*` matches within one path component, `**` across
+/// them (and `/**/` no directories at all, as in git), `?` one character
+/// other than `/`, `[...]` a class (`[!...]` or `[^...]` negated), and `\`
+/// escapes the next character.
+fn wildmatch(pattern: &[u8], text: &[u8]) -> bool {
+    wild(pattern, text)
+        || pattern
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == b"/**/")
+            .any(|(i, _)| {
+                let mut fewer = pattern[..i + 1].to_vec();
+                fewer.extend_from_slice(&pattern[i + 4..]);
+                wildmatch(&fewer, text)
+            })
+}
+
+fn wild(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => {
+            if let Some(rest) = rest.strip_prefix(b"*") {
+                let rest = rest.strip_prefix(b"*").unwrap_or(rest);
+                (0..=text.len()).any(|skip| wild(rest, &text[skip..]))
+            } else {
+                let within = text.iter().position(|&b| b == b'/').unwrap_or(text.len());
+                (0..=within).any(|skip| wild(rest, &text[skip..]))
+            }
+        }
+        Some((b'?', rest)) => {
+            matches!(text.first(), Some(&b) if b != b'/') && wild(rest, &text[1..])
+        }
+        Some((b'[', rest)) => match (text.first(), class(rest)) {
+            (Some(&b), Some((matches, rest))) if b != b'/' => matches(b) && wild(rest, &text[1..]),
+            // An unclosed `[` is literal.
+            (Some(&b'['), None) => wild(rest, &text[1..]),
+            _ => false,
+        },
+        Some((b'\\', rest)) if !rest.is_empty() => {
+            text.first() == rest.first() && wild(&rest[1..], &text[1..])
+        }
+        Some((&c, rest)) => text.first() == Some(&c) && wild(rest, &text[1..]),
+    }
+}
+
+/// The character class after a `[`, as a matcher and the pattern after its
+/// `]`; `None` when it isn't closed.
+fn class(pattern: &[u8]) -> Option<(impl Fn(u8) -> bool + '_, &[u8])> {
+    let (negated, body) = match pattern.first() {
+        Some(b'!' | b'^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+    // A `]` right after the opening bracket is part of the class.
+    let end = body
+        .iter()
+        .skip(1)
+        .position(|&b| b == b']')
+        .map(|i| i + 1)?;
+    let members = &body[..end];
+    let matches = move |b: u8| {
+        let mut found = false;
+        let mut i = 0;
+        while i < members.len() {
+            if i + 2 < members.len() && members[i + 1] == b'-' {
+                found |= (members[i]..=members[i + 2]).contains(&b);
+                i += 3;
+            } else {
+                found |= members[i] == b;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matches, &body[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(text: &str) -> Filter {
+        Filter::parse_rules(text, "rules").unwrap()
+    }
+
+    /// `(path, is_dir, excluded)` cases against `filter`.
+    fn check(filter: &Filter, cases: &[(&str, bool, bool)]) {
+        for &(path, is_dir, excluded) in cases {
+            assert_eq!(
+                filter.excludes(Path::new(path), is_dir),
+                excluded,
+                "{path:?} (dir: {is_dir})"
+            );
+        }
+    }
+
+    #[test]
+    fn root_is_never_filtered() {
+        check(&rules("- *\n"), &[("", true, false), ("a", true, true)]);
+    }
+
+    #[test]
+    fn include_subtree_then_exclude_rest() {
+        check(
+            &rules("+ src/***\n- *\n"),
+            &[
+                ("src", true, false),
+                ("src/lib.rs", false, false),
+                ("src/a/b.rs", false, false),
+                ("docs", true, true),
+                ("README.md", false, true),
+            ],
+        );
+    }
+
+    #[test]
+    fn anchoring_and_directories() {
+        check(
+            &rules("- /cache/\n- tmp/\n- foo/bar\n"),
+            &[
+                ("cache", true, true),
+                ("src/cache", true, false),
+                ("cache", false, false),
+                ("tmp", true, true),
+                ("a/b/tmp", true, true),
+                ("a/tmp", false, false),
+                ("foo/bar", false, true),
+                ("x/foo/bar", false, true),
+                ("xfoo/bar", false, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn wildcards() {
+        check(
+            &rules("- /src/*.rs\n- /lib/**.o\n- ?.tmp\n- [a-c]x\n- [!y]z\n- lit\\*\n"),
+            &[
+                ("src/a.rs", false, true),
+                ("src/x/a.rs", false, false),
+                ("lib/a.o", false, true),
+                ("lib/x/y/a.o", false, true),
+                ("d/a.tmp", false, true),
+                ("d/ab.tmp", false, false),
+                ("bx", false, true),
+                ("dx", false, false),
+                ("az", false, true),
+                ("yz", false, false),
+                ("lit*", false, true),
+                ("litx", false, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn first_match_wins() {
+        check(
+            &rules("+ keep.log\n- *.log\n"),
+            &[("keep.log", false, false), ("a/other.log", false, true)],
+        );
+    }
+
+    #[test]
+    fn negated_rules() {
+        for text in ["-! */\n", "exclude,! */\n"] {
+            check(&rules(text), &[("dir", true, false), ("file", false, true)]);
+        }
+    }
+
+    #[test]
+    fn rule_forms() {
+        check(
+            &rules(concat!(
+                "# comment\n; comment\n\n",
+                "exclude a\n- b\n!\n",
+                "exclude c\ninclude d\nH e\nS f\n- *f\nP g\n",
+                "foo bar\n",
+            )),
+            &[
+                // Cleared by the `!` line.
+                ("a", false, false),
+                ("b", false, false),
+                ("c", false, true),
+                ("d", false, false),
+                ("e", false, true),
+                ("f", false, false),
+                ("xf", false, true),
+                // Protect rules only guard deletions.
+                ("g", false, false),
+                // A line without a rule is an exclude pattern.
+                ("foo bar", false, true),
+            ],
+        );
+    }
+
+    #[test]
+    fn unsupported_rules() {
+        assert!(Filter::parse_rules("merge other.rules\n", "rules").is_err());
+        assert!(Filter::parse_rules("-C foo\n", "rules").is_err());
+        // Receiver-side rules don't apply to a scan.
+        check(&rules("-r foo\n"), &[("foo", false, false)]);
+    }
+
+    #[test]
+    fn exclude_lists() {
+        let filter = Filter::parse_excludes(concat!(
+            "# build output\n",
+            "*.log\n",
+            "!keep.log\n",
+            "build/  \n",
+            "doc/tmp\n",
+            "**/cache\n",
+            "a/**/b\r\n",
+        ));
+        check(
+            &filter,
+            &[
+                ("x/err.log", false, true),
+                // The later `!` line wins.
+                ("x/keep.log", false, false),
+                ("build", true, true),
+                ("src/build", true, true),
+                ("build", false, false),
+                // A slash in the middle anchors at the root.
+                ("doc/tmp", true, true),
+                ("x/doc/tmp", true, false),
+                ("cache", true, true),
+                ("x/y/cache", false, true),
+                // `/**/` matches no directories too.
+                ("a/b", false, true),
+                ("a/x/y/b", false, true),
+                ("x/a/b", false, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn exclude_everything_keeps_the_root() {
+        check(
+            &Filter::parse_excludes("*\n"),
+            &[("", true, false), ("a", false, true)],
+        );
+    }
+}
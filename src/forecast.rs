@@ -0,0 +1,230 @@
+use crate::filesystem::{self, Usage};
+use crate::locale;
+use crate::output::SCHEMA_VERSION;
+use crate::snapshot::Snapshot;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY: f64 = 86_400.0;
+
+/// Usage of one filesystem over time, oldest first, with the current
+/// reading last.
+pub struct FilesystemHistory {
+    pub mount_point: PathBuf,
+    pub total: u64,
+    /// (seconds since the Unix epoch, bytes used)
+    pub samples: Vec<(f64, u64)>,
+}
+
+/// A fill rate and when it reaches 90% and 100% of the filesystem.
+pub struct Trend {
+    pub bytes_per_day: f64,
+    /// `None` when usage isn't growing.
+    pub days_to_90: Option<f64>,
+    pub days_to_100: Option<f64>,
+}
+
+pub struct Forecast {
+    pub history: FilesystemHistory,
+    /// Least-squares fit over every sample.
+    pub linear: Trend,
+    /// Rate between the last two samples.
+    pub recent: Trend,
+}
+
+/// Groups the filesystem readings of saved reports by mount point and adds a
+/// reading taken now. Reports without one (older, merged, or imported) are
+/// skipped.
+pub fn history(reports: &[Snapshot]) -> Result<Vec<FilesystemHistory>> {
+    let mut by_mount: BTreeMap<PathBuf, FilesystemHistory> = BTreeMap::new();
+    for report in reports {
+        let (Some(usage), Some(at)) = (&report.summary.filesystem, report.summary.scanned_at)
+        else {
+            continue;
+        };
+        let history = by_mount
+            .entry(usage.mount_point.clone())
+            .or_insert_with(|| FilesystemHistory {
+                mount_point: usage.mount_point.clone(),
+                total: usage.total,
+                samples: Vec::new(),
+            });
+        history.samples.push((at as f64, usage.used));
+    }
+    if by_mount.is_empty() {
+        bail!(
+            "none of the reports record filesystem usage; save them with --json from a fresh scan"
+        );
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    for history in by_mount.values_mut() {
+        if let Some(Usage { total, used, .. }) = filesystem::usage(&history.mount_point) {
+            history.total = total;
+            history.samples.push((now, used));
+        }
+        history.samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    Ok(by_mount.into_values().collect())
+}
+
+impl Forecast {
+    /// `None` with fewer than two samples at different times.
+    pub fn new(history: FilesystemHistory) -> Option<Forecast> {
+        let samples = &history.samples;
+        let (first, last) = (samples.first()?, samples.last()?);
+        if last.0 <= first.0 {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let mean_t = samples.iter().map(|s| s.0).sum::<f64>() / n;
+        let mean_u = samples.iter().map(|s| s.1 as f64).sum::<f64>() / n;
+        let covariance: f64 = samples
+            .iter()
+            .map(|s| (s.0 - mean_t) * (s.1 as f64 - mean_u))
+            .sum();
+        let variance: f64 = samples.iter().map(|s| (s.0 - mean_t).powi(2)).sum();
+        let linear = covariance / variance * DAY;
+
+        let previous = samples[samples.len() - 2];
+        let recent = if last.0 > previous.0 {
+            (last.1 as f64 - previous.1 as f64) / (last.0 - previous.0) * DAY
+        } else {
+            linear
+        };
+
+        let used = last.1;
+        let total = history.total;
+        Some(Forecast {
+            linear: Trend::new(linear, used, total),
+            recent: Trend::new(recent, used, total),
+            history,
+        })
+    }
+}
+
+impl Trend {
+    fn new(bytes_per_day: f64, used: u64, total: u64) -> Trend {
+        let days_to = |share: f64| {
+            let remaining = (share * total as f64 - used as f64).max(0.0);
+            (bytes_per_day > 0.0).then(|| remaining / bytes_per_day)
+        };
+        Trend {
+            bytes_per_day,
+            days_to_90: days_to(0.9),
+            days_to_100: days_to(1.0),
+        }
+    }
+}
+
+fn rate(bytes_per_day: f64) -> String {
+    let sign = if bytes_per_day < 0.0 { "-" } else { "+" };
+    format!("{sign}{}/day", locale::size(bytes_per_day.abs() as u64))
+}
+
+fn days(days: Option<f64>) -> String {
+    match days {
+        Some(d) if d < 1.0 => "within a day".to_string(),
+        Some(d) => format!("in {} days", locale::count(d.round() as u64)),
+        None => "never".to_string(),
+    }
+}
+
+pub fn write_table(w: &mut dyn Write, forecasts: &[Forecast]) -> Result<()> {
+    for (i, forecast) in forecasts.iter().enumerate() {
+        if i > 0 {
+            writeln!(w)?;
+        }
+        let history = &forecast.history;
+        let (first, last) = (
+            history.samples[0],
+            history.samples[history.samples.len() - 1],
+        );
+        let share = if history.total > 0 {
+            last.1 as f64 / history.total as f64 * 100.0
+        } else {
+            0.0
+        };
+        writeln!(
+            w,
+            "{}  {} of {} used ({}%), {} samples over {} days",
+            history.mount_point.display(),
+            locale::size(last.1),
+            locale::size(history.total),
+            locale::fixed(share, 1),
+            history.samples.len(),
+            locale::fixed((last.0 - first.0) / DAY, 1),
+        )?;
+        for (label, trend) in [("linear", &forecast.linear), ("recent", &forecast.recent)] {
+            writeln!(
+                w,
+                "  {label}  {:>14}  90% {}, 100% {}",
+                rate(trend.bytes_per_day),
+                days(trend.days_to_90),
+                days(trend.days_to_100),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonForecasts {
+    schema_version: u32,
+    filesystems: Vec<JsonForecast>,
+}
+
+#[derive(Serialize)]
+struct JsonForecast {
+    mount_point: String,
+    total: u64,
+    used: u64,
+    samples: usize,
+    /// Least-squares fit over every sample.
+    linear: JsonTrend,
+    /// Rate between the last two samples.
+    recent: JsonTrend,
+}
+
+#[derive(Serialize)]
+struct JsonTrend {
+    bytes_per_day: f64,
+    /// `null` when usage isn't growing.
+    days_to_90_percent: Option<f64>,
+    days_to_100_percent: Option<f64>,
+}
+
+impl From<&Trend> for JsonTrend {
+    fn from(trend: &Trend) -> Self {
+        JsonTrend {
+            bytes_per_day: trend.bytes_per_day,
+            days_to_90_percent: trend.days_to_90,
+            days_to_100_percent: trend.days_to_100,
+        }
+    }
+}
+
+pub fn write_json(w: &mut dyn Write, forecasts: &[Forecast]) -> Result<()> {
+    let output = JsonForecasts {
+        schema_version: SCHEMA_VERSION,
+        filesystems: forecasts
+            .iter()
+            .map(|f| JsonForecast {
+                mount_point: f.history.mount_point.to_string_lossy().to_string(),
+                total: f.history.total,
+                used: f.history.samples.last().map_or(0, |s| s.1),
+                samples: f.history.samples.len(),
+                linear: (&f.linear).into(),
+                recent: (&f.recent).into(),
+            })
+            .collect(),
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use crate::budget::Measured;
+use crate::output::{display_name, Report};
+use anyhow::{Context, Result};
+use humansize::{format_size, BINARY};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// `--format github`: a `::warning` workflow command per exceeded budget,
+/// which GitHub Actions shows as an annotation on the run and the pull
+/// request, and a Markdown section appended to the job summary when
+/// `$GITHUB_STEP_SUMMARY` is set.
+pub fn write(w: &mut dyn Write, report: &Report) -> Result<()> {
+    let budgets = report.budgets.as_deref().unwrap_or_default();
+    for measured in budgets.iter().filter(|m| m.exceeded()) {
+        let message = measured.describe();
+        let mut properties = String::new();
+        if !measured.budget.path.as_os_str().is_empty() {
+            properties.push_str(&format!(
+                "file={},",
+                property(&annotated_path(report, measured))
+            ));
+        }
+        properties.push_str("title=Disk budget exceeded");
+        writeln!(w, "::warning {properties}::{}", data(&message))?;
+    }
+
+    if let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY") {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", PathBuf::from(&path).display()))?;
+        file.write_all(summary(report).as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Annotations name files relative to the workspace, which is where CI runs
+/// diskhound from; paths elsewhere stay absolute.
+fn annotated_path(report: &Report, measured: &Measured) -> String {
+    let absolute = report.root.join(&measured.budget.path);
+    let absolute = absolute.canonicalize().unwrap_or(absolute);
+    let relative = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .ok()
+        .and_then(|dir| absolute.strip_prefix(dir).ok().map(|p| p.to_path_buf()));
+    match relative {
+        Some(relative) => display_name(&relative),
+        None => absolute.to_string_lossy().to_string(),
+    }
+}
+
+fn summary(report: &Report) -> String {
+    let mut md = format!(
+        "### Disk usage of `{}`\n\n{} in {} files\n\n",
+        report.root.display(),
+        format_size(report.total_size, BINARY),
+        report.total_files
+    );
+    if let Some(budgets) = report.budgets.as_deref() {
+        md.push_str("| Budget | Size | Limit | Status |\n|---|---:|---:|---|\n");
+        for measured in budgets {
+            let status = if measured.exceeded() {
+                format!(
+                    ":x: {} over",
+                    format_size(measured.size - measured.budget.limit, BINARY)
+                )
+            } else {
+                ":white_check_mark: within".to_string()
+            };
+            md.push_str(&format!(
+                "| `{}` | {} | {} | {status} |\n",
+                cell(&measured.name()),
+                format_size(measured.size, BINARY),
+                format_size(measured.budget.limit, BINARY),
+            ));
+        }
+        md.push('\n');
+    }
+    md.push_str("| Entry | Size | Files |\n|---|---:|---:|\n");
+    for (name, stats) in &report.entries {
+        md.push_str(&format!(
+            "| `{}` | {} | {} |\n",
+            cell(&display_name(name)),
+            format_size(stats.size, BINARY),
+            stats.file_count
+        ));
+    }
+    md.push('\n');
+    md
+}
+
+/// Escapes a workflow command's message.
+fn data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a workflow command property value.
+fn property(s: &str) -> String {
+    data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Keeps a name from ending its Markdown table cell or code span.
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('`', "'")
+}
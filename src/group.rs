@@ -0,0 +1,385 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What file bytes are grouped by.
+#[derive(Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    /// Containing directory, down to `--depth`
+    Dir,
+    /// Lowercased file extension
+    Ext,
+    /// Owning user
+    Owner,
+    /// Time since the last modification
+    Age,
+    /// Number of directories between the root and the file
+    Depth,
+    /// Calendar period of the last modification, in UTC
+    Mtime(Period),
+    /// Outermost enclosing project root, found by marker files
+    Project,
+    /// Tag of the innermost tagged directory, from the config file
+    Tag,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Period {
+    Year,
+    Month,
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupBy::Dir => write!(f, "dir"),
+            GroupBy::Ext => write!(f, "ext"),
+            GroupBy::Owner => write!(f, "owner"),
+            GroupBy::Age => write!(f, "age"),
+            GroupBy::Depth => write!(f, "depth"),
+            GroupBy::Mtime(Period::Year) => write!(f, "mtime:year"),
+            GroupBy::Mtime(Period::Month) => write!(f, "mtime:month"),
+            GroupBy::Project => write!(f, "project"),
+            GroupBy::Tag => write!(f, "tag"),
+        }
+    }
+}
+
+pub fn parse(s: &str) -> Result<GroupBy, String> {
+    match s {
+        "dir" => Ok(GroupBy::Dir),
+        "ext" => Ok(GroupBy::Ext),
+        "owner" => Ok(GroupBy::Owner),
+        "age" => Ok(GroupBy::Age),
+        "depth" => Ok(GroupBy::Depth),
+        "mtime" | "mtime:year" => Ok(GroupBy::Mtime(Period::Year)),
+        "mtime:month" => Ok(GroupBy::Mtime(Period::Month)),
+        "project" => Ok(GroupBy::Project),
+        "tag" => Ok(GroupBy::Tag),
+        _ => Err(format!(
+            "expected dir, ext, owner, age, depth, mtime:year, mtime:month, project, or tag, got {s:?}"
+        )),
+    }
+}
+
+/// A counted file or directory, as seen by a [`Keyer`].
+pub struct Item<'a> {
+    /// Path components relative to the scanned root.
+    pub components: &'a [&'a OsStr],
+    pub metadata: Option<&'a Metadata>,
+    pub is_dir: bool,
+}
+
+/// Maps each counted entry to the key its bytes are aggregated under. Every
+/// `--group-by` mode is one implementation; the walk, the aggregation, and the
+/// renderers don't know which one is in use.
+pub trait Keyer {
+    /// `None` sends the entry to the `<files in .>` pseudo-entry.
+    fn key(&self, item: &Item) -> Option<PathBuf>;
+}
+
+impl GroupBy {
+    /// The keyer for this mode. `max_depth` only applies to `dir`, `root`
+    /// (the scanned directory) to `project` and `tag`, and `tags` (absolute
+    /// paths with their tag) to `tag`.
+    pub fn keyer(
+        self,
+        max_depth: usize,
+        root: &Path,
+        tags: &[(PathBuf, String)],
+    ) -> Box<dyn Keyer> {
+        match self {
+            GroupBy::Dir => Box::new(DirKeyer { max_depth }),
+            GroupBy::Ext => Box::new(ExtKeyer),
+            GroupBy::Owner => Box::new(OwnerKeyer::new()),
+            GroupBy::Age => Box::new(AgeKeyer {
+                now: SystemTime::now(),
+            }),
+            GroupBy::Depth => Box::new(DepthKeyer),
+            GroupBy::Mtime(period) => Box::new(MtimeKeyer { period }),
+            GroupBy::Project => Box::new(ProjectKeyer {
+                root: root.to_path_buf(),
+                projects: RefCell::default(),
+            }),
+            GroupBy::Tag => Box::new(TagKeyer::new(root, tags)),
+        }
+    }
+}
+
+struct DirKeyer {
+    max_depth: usize,
+}
+
+impl Keyer for DirKeyer {
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        let parts = item.components;
+        if item.is_dir {
+            // A directory's own size belongs to the directory itself
+            return Some(parts[..self.max_depth.min(parts.len())].iter().collect());
+        }
+        // Root-level files don't belong to any subdirectory
+        if parts.len() == 1 {
+            return None;
+        }
+        let key_depth = self.max_depth.min(parts.len() - 1);
+        Some(parts[..key_depth].iter().collect())
+    }
+}
+
+struct ExtKeyer;
+
+impl Keyer for ExtKeyer {
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        if item.is_dir {
+            return Some(PathBuf::from("(directories)"));
+        }
+        let name = std::path::Path::new(item.components.last()?);
+        Some(PathBuf::from(crate::breakdown::extension_label(name)))
+    }
+}
+
+/// uid to user name, from /etc/passwd.
+#[cfg(unix)]
+pub fn user_names() -> std::collections::HashMap<u32, String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    passwd
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+struct OwnerKeyer {
+    /// uid to user name, from /etc/passwd. Users it doesn't list show up by
+    /// number.
+    #[cfg(unix)]
+    names: std::collections::HashMap<u32, String>,
+}
+
+impl OwnerKeyer {
+    #[cfg(unix)]
+    fn new() -> Self {
+        Self {
+            names: user_names(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Keyer for OwnerKeyer {
+    #[cfg(unix)]
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        use std::os::unix::fs::MetadataExt;
+        let Some(uid) = item.metadata.map(|m| m.uid()) else {
+            return Some(PathBuf::from("unknown"));
+        };
+        Some(PathBuf::from(match self.names.get(&uid) {
+            Some(name) => name.clone(),
+            None => uid.to_string(),
+        }))
+    }
+
+    /// Owners come from security descriptors on Windows, which aren't read.
+    #[cfg(not(unix))]
+    fn key(&self, _item: &Item) -> Option<PathBuf> {
+        Some(PathBuf::from("unknown"))
+    }
+}
+
+struct AgeKeyer {
+    now: SystemTime,
+}
+
+impl Keyer for AgeKeyer {
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        const DAY: u64 = 86_400;
+        const BUCKETS: &[(u64, &str)] = &[
+            (DAY, "< 1 day"),
+            (7 * DAY, "< 1 week"),
+            (30 * DAY, "< 1 month"),
+            (182 * DAY, "< 6 months"),
+            (365 * DAY, "< 1 year"),
+            (2 * 365 * DAY, "< 2 years"),
+            (5 * 365 * DAY, "< 5 years"),
+        ];
+        let Some(modified) = item.metadata.and_then(|m| m.modified().ok()) else {
+            return Some(PathBuf::from("unknown"));
+        };
+        // Modification times in the future count as brand new.
+        let age = self
+            .now
+            .duration_since(modified)
+            .unwrap_or_default()
+            .as_secs();
+        let label = BUCKETS
+            .iter()
+            .find(|(limit, _)| age < *limit)
+            .map_or("5+ years", |(_, label)| label);
+        Some(PathBuf::from(label))
+    }
+}
+
+struct DepthKeyer;
+
+impl Keyer for DepthKeyer {
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        let depth = item.components.len() - usize::from(!item.is_dir);
+        Some(PathBuf::from(format!("depth {depth}")))
+    }
+}
+
+struct MtimeKeyer {
+    period: Period,
+}
+
+impl Keyer for MtimeKeyer {
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        let modified = item.metadata.and_then(|m| m.modified().ok());
+        Some(PathBuf::from(mtime_bucket(modified, self.period)))
+    }
+}
+
+/// Files or directories whose presence makes a directory a project root.
+const PROJECT_MARKERS: &[&str] = &[
+    ".git",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    ".diskhound-project",
+];
+
+struct ProjectKeyer {
+    root: PathBuf,
+    /// Directory (relative to the root) to the project it belongs to, so
+    /// each directory is checked for markers once.
+    projects: RefCell<HashMap<PathBuf, Option<PathBuf>>>,
+}
+
+impl ProjectKeyer {
+    /// The outermost project root at or above `dir`. Outermost, so a
+    /// repository's vendored packages and `node_modules` (which carry their
+    /// own `package.json`) count toward the repository.
+    fn project(&self, dir: &[&OsStr]) -> Option<PathBuf> {
+        let path: PathBuf = dir.iter().collect();
+        if let Some(project) = self.projects.borrow().get(&path) {
+            return project.clone();
+        }
+        let project = match dir.split_last() {
+            Some((_, parent)) => self.project(parent),
+            None => None,
+        }
+        .or_else(|| {
+            let absolute = self.root.join(&path);
+            PROJECT_MARKERS
+                .iter()
+                .any(|marker| absolute.join(marker).symlink_metadata().is_ok())
+                .then(|| path.clone())
+        });
+        self.projects.borrow_mut().insert(path, project.clone());
+        project
+    }
+}
+
+impl Keyer for ProjectKeyer {
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        let dir = if item.is_dir {
+            item.components
+        } else {
+            &item.components[..item.components.len() - 1]
+        };
+        Some(match self.project(dir) {
+            Some(project) if project.as_os_str().is_empty() => PathBuf::from("."),
+            Some(project) => project,
+            None => PathBuf::from("(no project)"),
+        })
+    }
+}
+
+struct TagKeyer {
+    /// Tag of the innermost tagged directory containing the scanned root.
+    inherited: Option<String>,
+    /// Tagged directories inside the scan, relative to its root.
+    inside: HashMap<PathBuf, String>,
+}
+
+impl TagKeyer {
+    fn new(root: &Path, tags: &[(PathBuf, String)]) -> Self {
+        let mut inherited: Option<(usize, String)> = None;
+        let mut inside = HashMap::new();
+        for (path, tag) in tags {
+            if let Ok(relative) = path.strip_prefix(root) {
+                if relative.as_os_str().is_empty() {
+                    inherited = Some((usize::MAX, tag.clone()));
+                } else {
+                    inside.insert(relative.to_path_buf(), tag.clone());
+                }
+            } else if root.starts_with(path) {
+                let depth = path.components().count();
+                if inherited.as_ref().is_none_or(|(d, _)| depth > *d) {
+                    inherited = Some((depth, tag.clone()));
+                }
+            }
+        }
+        Self {
+            inherited: inherited.map(|(_, tag)| tag),
+            inside,
+        }
+    }
+}
+
+impl Keyer for TagKeyer {
+    fn key(&self, item: &Item) -> Option<PathBuf> {
+        let dirs = item.components.len() - usize::from(!item.is_dir);
+        let tag = (1..=dirs)
+            .rev()
+            .find_map(|n| {
+                let dir: PathBuf = item.components[..n].iter().collect();
+                self.inside.get(&dir)
+            })
+            .or(self.inherited.as_ref());
+        Some(PathBuf::from(tag.map_or("(untagged)", |t| t.as_str())))
+    }
+}
+
+/// Bucket name for a modification time: `2019` or `2019-03`. Files whose
+/// time couldn't be read land in `unknown`.
+pub fn mtime_bucket(modified: Option<SystemTime>, period: Period) -> String {
+    let Some(modified) = modified else {
+        return "unknown".to_string();
+    };
+    let secs = match modified.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let (year, month) = civil_from_days(secs.div_euclid(86_400));
+    match period {
+        Period::Year => format!("{year}"),
+        Period::Month => format!("{year}-{month:02}"),
+    }
+}
+
+/// Year and month of a day count since 1970-01-01 in the proleptic Gregorian
+/// calendar (Howard Hinnant's `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month as u32)
+}
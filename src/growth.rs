@@ -0,0 +1,259 @@
+use crate::locale;
+use crate::output::{display_name, raw_base64, Report, SCHEMA_VERSION};
+use crate::snapshot::Snapshot;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// `--alert-growth`: bytes per period, e.g. `5GB/day`.
+#[derive(Clone, Copy)]
+pub struct GrowthLimit {
+    pub bytes: u64,
+    pub per: Duration,
+}
+
+impl GrowthLimit {
+    fn bytes_per_day(self) -> f64 {
+        self.bytes as f64 / self.per.as_secs_f64() * 86_400.0
+    }
+}
+
+/// Parses `AMOUNT/PERIOD`, with the amount parsed by `parse_size` and the
+/// period one of `hour`, `day`, or `week` (or `h`, `d`, `w`).
+pub fn parse_limit(s: &str, parse_size: impl Fn(&str) -> Result<u64>) -> Result<GrowthLimit> {
+    let Some((amount, period)) = s.split_once('/') else {
+        anyhow::bail!("expected a growth rate like 5GB/day, got {s:?}");
+    };
+    let hours = match period.trim() {
+        "h" | "hour" => 1,
+        "d" | "day" => 24,
+        "w" | "week" => 24 * 7,
+        other => anyhow::bail!("unknown growth period {other:?} (use hour, day, or week)"),
+    };
+    Ok(GrowthLimit {
+        bytes: parse_size(amount)?,
+        per: Duration::from_secs(hours * 3600),
+    })
+}
+
+/// An entry that grew faster than the limit since the baseline.
+pub struct Alert {
+    pub name: PathBuf,
+    pub previous: u64,
+    pub current: u64,
+    pub bytes_per_day: f64,
+}
+
+/// Compares the entries of `report` with the same entries of `baseline`,
+/// taken `elapsed` earlier. Entries the baseline doesn't have are skipped:
+/// it only kept its `--top` largest, so a missing entry may well have been
+/// there at much the same size.
+pub fn check(
+    report: &Report,
+    baseline: &Snapshot,
+    elapsed: Duration,
+    limit: GrowthLimit,
+) -> Result<Vec<Alert>> {
+    let mut previous: HashMap<PathBuf, u64> = HashMap::new();
+    for entry in &baseline.directories {
+        previous.insert(entry.key()?, entry.size);
+    }
+    // Guard against baselines taken moments ago turning tiny growth into a
+    // huge rate.
+    let days = elapsed.as_secs_f64().max(60.0) / 86_400.0;
+    let mut alerts: Vec<Alert> = report
+        .entries
+        .iter()
+        .filter_map(|(name, stats)| {
+            let before = *previous.get(name)?;
+            let grown = stats.size.checked_sub(before)?;
+            let bytes_per_day = grown as f64 / days;
+            (bytes_per_day > limit.bytes_per_day()).then(|| Alert {
+                name: name.clone(),
+                previous: before,
+                current: stats.size,
+                bytes_per_day,
+            })
+        })
+        .collect();
+    alerts.sort_by(|a, b| b.bytes_per_day.total_cmp(&a.bytes_per_day));
+    Ok(alerts)
+}
+
+/// One line per alert, for the log.
+pub fn describe(alert: &Alert, elapsed: Duration) -> String {
+    format!(
+        "{} grew from {} to {} in {:.1} days ({}/day)",
+        display_name(&alert.name),
+        locale::size(alert.previous),
+        locale::size(alert.current),
+        elapsed.as_secs_f64() / 86_400.0,
+        locale::size(alert.bytes_per_day as u64),
+    )
+}
+
+#[derive(Serialize)]
+struct JsonAlerts {
+    schema_version: u32,
+    root: String,
+    /// Seconds between the baseline and this scan.
+    elapsed_seconds: u64,
+    limit_bytes_per_day: f64,
+    alerts: Vec<JsonAlert>,
+}
+
+#[derive(Serialize)]
+struct JsonAlert {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_base64: Option<String>,
+    previous_size: u64,
+    size: u64,
+    bytes_per_day: f64,
+}
+
+/// The alerts as JSON, passed to `--alert-command` on stdin.
+pub fn to_json(
+    report: &Report,
+    alerts: &[Alert],
+    elapsed: Duration,
+    limit: GrowthLimit,
+) -> Result<String> {
+    let output = JsonAlerts {
+        schema_version: SCHEMA_VERSION,
+        root: report.root.to_string_lossy().to_string(),
+        elapsed_seconds: elapsed.as_secs(),
+        limit_bytes_per_day: limit.bytes_per_day(),
+        alerts: alerts
+            .iter()
+            .map(|a| JsonAlert {
+                name: display_name(&a.name),
+                name_base64: raw_base64(a.name.as_os_str()),
+                previous_size: a.previous,
+                size: a.current,
+                bytes_per_day: a.bytes_per_day,
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&output)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::GroupBy;
+    use crate::output::{DirStats, PercentOf};
+    use crate::snapshot::{SnapshotEntry, SnapshotSummary};
+
+    const DAY: Duration = Duration::from_secs(86_400);
+    const MB: u64 = 1 << 20;
+    const GB: u64 = 1 << 30;
+
+    fn parse_size(s: &str) -> Result<u64> {
+        crate::parse_human_size(s)
+    }
+
+    fn report(entries: &[(&str, u64)]) -> Report {
+        let entries = entries
+            .iter()
+            .map(|&(name, size)| {
+                let stats = DirStats {
+                    size,
+                    file_count: 1,
+                };
+                (PathBuf::from(name), stats)
+            })
+            .collect();
+        Report::new(
+            PathBuf::from("/r"),
+            entries,
+            PercentOf::Total,
+            GroupBy::Dir,
+            10,
+        )
+    }
+
+    fn baseline(entries: &[(&str, u64)]) -> Snapshot {
+        Snapshot {
+            schema_version: SCHEMA_VERSION,
+            directories: entries
+                .iter()
+                .map(|&(name, size)| SnapshotEntry {
+                    name: name.to_string(),
+                    name_base64: None,
+                    size,
+                    file_count: 1,
+                })
+                .collect(),
+            summary: SnapshotSummary {
+                root: Some("/r".to_string()),
+                scanned_at: None,
+                filesystem: None,
+                total_size: 0,
+                total_files: 0,
+                total_dirs: 0,
+                group_by: None,
+                incomplete: None,
+            },
+        }
+    }
+
+    fn alerts(now: &[(&str, u64)], before: &[(&str, u64)], elapsed: Duration) -> Vec<String> {
+        let limit = parse_limit("100MB/day", parse_size).unwrap();
+        check(&report(now), &baseline(before), elapsed, limit)
+            .unwrap()
+            .iter()
+            .map(|a| display_name(&a.name))
+            .collect()
+    }
+
+    #[test]
+    fn parses_limits() {
+        let limit = parse_limit("5GB/day", parse_size).unwrap();
+        assert_eq!((limit.bytes, limit.per), (5 * GB, DAY));
+        let limit = parse_limit("500MB/ h", parse_size).unwrap();
+        assert_eq!(
+            (limit.bytes, limit.per),
+            (500 * MB, Duration::from_secs(3600))
+        );
+        assert_eq!(parse_limit("1GB/w", parse_size).unwrap().per, 7 * DAY);
+        let weekly = parse_limit("1GB/week", parse_size).unwrap();
+        assert!((weekly.bytes_per_day() - GB as f64 / 7.0).abs() < 1.0);
+        assert!(parse_limit("5GB", parse_size).is_err());
+        assert!(parse_limit("5GB/month", parse_size).is_err());
+        assert!(parse_limit("lots/day", parse_size).is_err());
+    }
+
+    #[test]
+    fn alerts_on_entries_growing_faster_than_the_limit() {
+        let before = [("fast", 10 * MB), ("slow", 10 * MB), ("shrunk", 900 * MB)];
+        let now = [("fast", 400 * MB), ("slow", 150 * MB), ("shrunk", 10 * MB)];
+        // 390 MB and 140 MB over two days: only `fast` is above 100 MB/day.
+        assert_eq!(alerts(&now, &before, 2 * DAY), ["fast"]);
+        assert_eq!(alerts(&now, &before, DAY / 2), ["fast", "slow"]);
+    }
+
+    #[test]
+    fn entries_missing_from_the_baseline_are_skipped() {
+        // `d1` was below the baseline's --top cut, not absent.
+        let before = [("big", 900 * MB)];
+        let now = [("big", 900 * MB), ("d1", 250 * MB)];
+        assert!(alerts(&now, &before, DAY).is_empty());
+    }
+
+    #[test]
+    fn recent_baselines_count_as_a_minute_old() {
+        // 1 MB in a second would be 84 GB/day; over a minute it's 1.4 GB/day.
+        let alert = check(
+            &report(&[("a", 2 * MB)]),
+            &baseline(&[("a", MB)]),
+            Duration::from_secs(1),
+            parse_limit("1GB/day", parse_size).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(alert.len(), 1);
+        assert_eq!(alert[0].bytes_per_day, MB as f64 * 1440.0);
+    }
+}
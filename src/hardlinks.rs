@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+/// How much of an entry's size is also reachable through hardlinks elsewhere.
+pub struct Sharing {
+    pub shared: u64,
+    /// Bytes shared with each other entry, largest first. `None` stands for
+    /// links outside the scanned tree.
+    pub with: Vec<(Option<PathBuf>, u64)>,
+}
+
+struct Inode {
+    size: u64,
+    nlink: u64,
+    keys: Vec<PathBuf>,
+}
+
+/// Multiply-linked files seen during the walk, by device and inode.
+#[derive(Default)]
+pub struct Hardlinks {
+    inodes: HashMap<(u64, u64), Inode>,
+}
+
+impl Hardlinks {
+    /// Records a file counted under `key`. Files with a single link are
+    /// ignored.
+    #[cfg(unix)]
+    pub fn add(&mut self, key: &Path, metadata: &Metadata) {
+        use std::os::unix::fs::MetadataExt;
+        if metadata.nlink() <= 1 {
+            return;
+        }
+        self.inodes
+            .entry((metadata.dev(), metadata.ino()))
+            .or_insert_with(|| Inode {
+                size: metadata.len(),
+                nlink: metadata.nlink(),
+                keys: Vec::new(),
+            })
+            .keys
+            .push(key.to_path_buf());
+    }
+
+    /// Inode numbers aren't exposed by std on Windows.
+    #[cfg(not(unix))]
+    pub fn add(&mut self, _key: &Path, _metadata: &Metadata) {}
+
+    /// Bytes each key shares with other keys. Links between files under the
+    /// same key don't count as shared.
+    pub fn sharing(self) -> HashMap<PathBuf, Sharing> {
+        let mut shared: HashMap<PathBuf, u64> = HashMap::new();
+        let mut pairs: HashMap<PathBuf, HashMap<Option<PathBuf>, u64>> = HashMap::new();
+        for inode in self.inodes.into_values() {
+            let outside = inode.nlink > inode.keys.len() as u64;
+            let mut keys = inode.keys;
+            keys.sort();
+            keys.dedup();
+            if keys.len() < 2 && !outside {
+                continue;
+            }
+            for key in &keys {
+                *shared.entry(key.clone()).or_insert(0) += inode.size;
+                let partners = pairs.entry(key.clone()).or_default();
+                for other in keys.iter().filter(|k| *k != key) {
+                    *partners.entry(Some(other.clone())).or_insert(0) += inode.size;
+                }
+                if outside {
+                    *partners.entry(None).or_insert(0) += inode.size;
+                }
+            }
+        }
+
+        pairs
+            .into_iter()
+            .map(|(key, partners)| {
+                let mut with: Vec<_> = partners.into_iter().collect();
+                with.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+                let shared = shared[&key];
+                (key, Sharing { shared, with })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,172 @@
+use crate::locale;
+use crate::output::SCHEMA_VERSION;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Number of size buckets: under 1 KiB, then one per factor of 4 up to
+/// 1 GiB and over.
+pub const BUCKETS: usize = 12;
+
+/// File counts and bytes by logarithmic size bucket.
+#[derive(Clone, Copy, Default)]
+pub struct Histogram {
+    pub counts: [u64; BUCKETS],
+    pub bytes: [u64; BUCKETS],
+}
+
+impl Histogram {
+    pub fn add(&mut self, size: u64) {
+        let bucket = bucket(size);
+        self.counts[bucket] += 1;
+        self.bytes[bucket] += size;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for i in 0..BUCKETS {
+            self.counts[i] += other.counts[i];
+            self.bytes[i] += other.bytes[i];
+        }
+    }
+
+    /// One block character per bucket, scaled to the fullest bucket; empty
+    /// buckets are blank.
+    pub fn sparkline(&self) -> String {
+        const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = self.counts.iter().copied().max().unwrap_or(0);
+        self.counts
+            .iter()
+            .map(|&count| match count {
+                0 => ' ',
+                _ => BLOCKS[(count * 7 / max) as usize],
+            })
+            .collect()
+    }
+}
+
+/// Inclusive lower bound of bucket `i` in bytes.
+fn lower_bound(i: usize) -> u64 {
+    match i {
+        0 => 0,
+        _ => 1024 << (2 * (i - 1)),
+    }
+}
+
+fn bucket_label(i: usize) -> String {
+    match i {
+        0 => format!("< {}", locale::size(lower_bound(1))),
+        _ if i == BUCKETS - 1 => format!(">= {}", locale::size(lower_bound(i))),
+        _ => format!(
+            "{} - {}",
+            locale::size(lower_bound(i)),
+            locale::size(lower_bound(i + 1))
+        ),
+    }
+}
+
+fn bucket(size: u64) -> usize {
+    if size < 1024 {
+        return 0;
+    }
+    ((size.ilog2() as usize - 10) / 2 + 1).min(BUCKETS - 1)
+}
+
+/// Adds every key's histogram to all of its ancestors, matching
+/// `tree::cumulative`.
+pub fn cumulative(by_key: HashMap<PathBuf, Histogram>) -> HashMap<PathBuf, Histogram> {
+    let mut out: HashMap<PathBuf, Histogram> = HashMap::new();
+    for (key, histogram) in &by_key {
+        for ancestor in key.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+            out.entry(ancestor.to_path_buf())
+                .or_default()
+                .merge(histogram);
+        }
+    }
+    out
+}
+
+/// `diskhound hist`: files and bytes per bucket, with the running share of
+/// all bytes held by files up to that size.
+pub fn write_table(w: &mut dyn Write, histogram: &Histogram) -> Result<()> {
+    let files: u64 = histogram.counts.iter().sum();
+    let bytes: u64 = histogram.bytes.iter().sum();
+    let max_count = histogram.counts.iter().copied().max().unwrap_or(0);
+    let labels: Vec<String> = (0..BUCKETS).map(bucket_label).collect();
+    let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let bar_width = 20;
+
+    writeln!(
+        w,
+        "  {:<width$}  {:>12}  {:bar_width$}  {:>10}  cumulative",
+        "size", "files", "", "bytes",
+    )?;
+    let mut running = 0;
+    for (i, label) in labels.iter().enumerate() {
+        running += histogram.bytes[i];
+        let filled = if max_count > 0 {
+            (histogram.counts[i] as f64 / max_count as f64 * bar_width as f64).round() as usize
+        } else {
+            0
+        };
+        let bar = "\u{2588}".repeat(filled) + &"\u{2591}".repeat(bar_width - filled);
+        let cumulative = if bytes > 0 {
+            running as f64 / bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+        writeln!(
+            w,
+            "  {:<width$}  {:>12}  {}  {:>10}  {:>9}%",
+            label,
+            locale::count(histogram.counts[i]),
+            bar,
+            locale::size(histogram.bytes[i]),
+            locale::fixed(cumulative, 1),
+        )?;
+    }
+    writeln!(w)?;
+    writeln!(w, "{} files, {}", locale::count(files), locale::size(bytes))?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonHistogram {
+    schema_version: u32,
+    buckets: Vec<JsonBucket>,
+}
+
+#[derive(Serialize)]
+struct JsonBucket {
+    /// Inclusive lower bound in bytes.
+    min_size: u64,
+    /// Exclusive upper bound in bytes; `null` for the last bucket.
+    max_size: Option<u64>,
+    files: u64,
+    bytes: u64,
+    /// Bytes in this bucket and all smaller ones.
+    cumulative_bytes: u64,
+}
+
+pub fn write_json(w: &mut dyn Write, histogram: &Histogram) -> Result<()> {
+    let mut running = 0;
+    let buckets = (0..BUCKETS)
+        .map(|i| {
+            running += histogram.bytes[i];
+            JsonBucket {
+                min_size: lower_bound(i),
+                max_size: (i + 1 < BUCKETS).then(|| lower_bound(i + 1)),
+                files: histogram.counts[i],
+                bytes: histogram.bytes[i],
+                cumulative_bytes: running,
+            }
+        })
+        .collect();
+    let output = JsonHistogram {
+        schema_version: SCHEMA_VERSION,
+        buckets,
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use std::sync::OnceLock;
+
+/// Translatable text shown to people: table summaries and the progress line.
+/// Machine formats, logs, and error messages stay in English.
+#[derive(Clone, Copy)]
+pub enum Msg {
+    NoEntries,
+    Files,
+    MeanSize,
+    Reclaimable,
+    UnreadableCount,
+    SharedWith,
+    OutsideScan,
+    More,
+    Total,
+    FileSizes,
+    Incomplete,
+    ChangedSince,
+    SkippedMounts,
+    AutoExcluded,
+    AutoExcludedPreset,
+    NothingAutoExcluded,
+    Budgets,
+    BudgetExceeded,
+    BudgetWithin,
+    Quota,
+    NearQuota,
+    Filesystems,
+    FilesystemTotal,
+    Subvolumes,
+    SubvolumeTotal,
+    Subvolume,
+    Snapshot,
+    SubvolumeQgroup,
+    Datasets,
+    DatasetTotal,
+    DatasetZfs,
+    SameDataAs,
+    SkippedFiles,
+    SpecialCounted,
+    SpecialSkipped,
+    SpecialListed,
+    AllReadable,
+    Unreadable,
+    NoDuplicates,
+    Duplicates,
+    DuplicateGroup,
+    NoDuplicateTrees,
+    DuplicateTrees,
+    NoSimilar,
+    Similar,
+    SimilarPair,
+    Progress,
+    ProgressEta,
+}
+
+#[derive(Clone, Copy)]
+enum Language {
+    English,
+    German,
+}
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Picks the language from `--locale`, or else from `LC_ALL`, `LC_MESSAGES`,
+/// or `LANG`. Languages without a catalog fall back to English.
+pub fn init(name: Option<&str>) {
+    let name = name.map(str::to_string).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+    });
+    let language = match name
+        .as_deref()
+        .map(|n| n.split(['_', '-', '.', '@']).next())
+    {
+        Some(Some("de")) => Language::German,
+        _ => Language::English,
+    };
+    let _ = LANGUAGE.set(language);
+}
+
+/// The message in the current language with each `{name}` placeholder
+/// replaced by its value from `args`.
+pub fn tr(msg: Msg, args: &[(&str, &str)]) -> String {
+    let template = match LANGUAGE.get().copied().unwrap_or(Language::English) {
+        Language::English => english(msg),
+        Language::German => german(msg),
+    };
+    // One pass, so braces inside values (paths, reasons) are left alone.
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + len];
+        match args.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn english(msg: Msg) -> &'static str {
+    match msg {
+        Msg::NoEntries => "No subdirectories found in {root}",
+        Msg::Files => "({count} files)",
+        Msg::MeanSize => "{size} avg",
+        Msg::Reclaimable => "{size} reclaimable",
+        Msg::UnreadableCount => "{count} unreadable",
+        Msg::SharedWith => "of which {size} shared with {partner}",
+        Msg::OutsideScan => "files outside the scan",
+        Msg::More => "(+{count} more)",
+        Msg::Total => {
+            "Total: {size} in {files} files across {dirs} directories (showing top {shown})"
+        }
+        Msg::FileSizes => {
+            "File sizes: median {median}, p90 {p90}, p99 {p99}; largest {largest} ({path})"
+        }
+        Msg::Incomplete => "Incomplete: {reason}; sizes only cover what was scanned",
+        Msg::ChangedSince => "Only files created or modified since the earlier scan, {days} days ago",
+        Msg::SkippedMounts => "Skipped mounts already counted elsewhere:",
+        Msg::AutoExcluded => "Auto-excluded: {presets}",
+        Msg::AutoExcludedPreset => "{preset} ({count} directories)",
+        Msg::NothingAutoExcluded => "Auto-excluded: nothing, no project markers found",
+        Msg::Budgets => "Size budgets:",
+        Msg::BudgetExceeded => "EXCEEDED  {path}: {size}, over its {limit} budget",
+        Msg::BudgetWithin => "ok        {path}: {size} of {limit}",
+        Msg::Quota => "quota {used} of {limit} ({percent}%)",
+        Msg::NearQuota => "near limit",
+        Msg::Filesystems => "Filesystems in this scan:",
+        Msg::FilesystemTotal => "{size}  {mount} ({type}), {files} files",
+        Msg::Subvolumes => "Btrfs subvolumes (walked sizes count snapshot-shared data in full):",
+        Msg::SubvolumeTotal => "{size}  {path} ({kind})",
+        Msg::Subvolume => "subvolume",
+        Msg::Snapshot => "snapshot",
+        Msg::SubvolumeQgroup => "{exclusive} exclusive, {referenced} referenced",
+        Msg::Datasets => "ZFS datasets:",
+        Msg::DatasetTotal => "{size}  {path} ({name})",
+        Msg::DatasetZfs => {
+            "{referenced} referenced on disk, {logical} logical ({ratio}x compression), {used} used with snapshots and children"
+        }
+        Msg::SameDataAs => "{path} (same data as {original})",
+        Msg::SkippedFiles => {
+            "Skipped {count} files over {limit}, {size} in total (showing largest {shown})"
+        }
+        Msg::SpecialCounted => "Counted {count} special files as files ({kinds})",
+        Msg::SpecialSkipped => "Skipped {count} special files ({kinds})",
+        Msg::SpecialListed => {
+            "Left {count} special files out of the totals ({kinds}; showing first {shown})"
+        }
+        Msg::AllReadable => "Every file passed the read check",
+        Msg::Unreadable => {
+            "Unreadable: {count} files failed the read check (showing first {shown})"
+        }
+        Msg::NoDuplicates => "No duplicate files found",
+        Msg::Duplicates => {
+            "Duplicates: {groups} groups, {size} reclaimable (showing largest {shown})"
+        }
+        Msg::DuplicateGroup => "{copies} x {size} ({reclaimable} reclaimable)",
+        Msg::NoDuplicateTrees => "No duplicate directory trees found",
+        Msg::DuplicateTrees => {
+            "Duplicate trees: {groups} groups, {size} reclaimable (showing largest {shown})"
+        }
+        Msg::NoSimilar => "No similar files found",
+        Msg::Similar => {
+            "Similar files: {pairs} pairs, {size} saved by delta storage (showing largest {shown})"
+        }
+        Msg::SimilarPair => "{similarity}% similar, {shared} shared",
+        Msg::Progress => "Scanning... {spinner} {entries} entries",
+        Msg::ProgressEta => "Scanning... {percent}% of ~{entries} entries (ETA {eta})",
+    }
+}
+
+fn german(msg: Msg) -> &'static str {
+    match msg {
+        Msg::NoEntries => "Keine Unterverzeichnisse in {root} gefunden",
+        Msg::Files => "({count} Dateien)",
+        Msg::MeanSize => "Ø {size}",
+        Msg::Reclaimable => "{size} einsparbar",
+        Msg::UnreadableCount => "{count} nicht lesbar",
+        Msg::SharedWith => "davon {size} gemeinsam mit {partner}",
+        Msg::OutsideScan => "Dateien außerhalb des Scans",
+        Msg::More => "(+{count} weitere)",
+        Msg::Total => {
+            "Gesamt: {size} in {files} Dateien in {dirs} Verzeichnissen (die größten {shown})"
+        }
+        Msg::FileSizes => {
+            "Dateigrößen: Median {median}, p90 {p90}, p99 {p99}; größte {largest} ({path})"
+        }
+        Msg::Incomplete => "Unvollständig: {reason}; die Größen umfassen nur den gescannten Teil",
+        Msg::ChangedSince => "Nur seit dem früheren Scan vor {days} Tagen neue oder geänderte Dateien",
+        Msg::SkippedMounts => "Übersprungene Einhängepunkte, die bereits anderswo gezählt sind:",
+        Msg::AutoExcluded => "Automatisch ausgeschlossen: {presets}",
+        Msg::AutoExcludedPreset => "{preset} ({count} Verzeichnisse)",
+        Msg::NothingAutoExcluded => "Automatisch ausgeschlossen: nichts, keine Projektmarker gefunden",
+        Msg::Budgets => "Größenbudgets:",
+        Msg::BudgetExceeded => "ÜBERSCHRITTEN  {path}: {size}, über dem Budget von {limit}",
+        Msg::BudgetWithin => "ok             {path}: {size} von {limit}",
+        Msg::Quota => "Kontingent {used} von {limit} ({percent} %)",
+        Msg::NearQuota => "fast erschöpft",
+        Msg::Filesystems => "Dateisysteme in diesem Scan:",
+        Msg::FilesystemTotal => "{size}  {mount} ({type}), {files} Dateien",
+        Msg::Subvolumes => {
+            "Btrfs-Subvolumes (gescannte Größen zählen mit Snapshots geteilte Daten voll):"
+        }
+        Msg::SubvolumeTotal => "{size}  {path} ({kind})",
+        Msg::Subvolume => "Subvolume",
+        Msg::Snapshot => "Snapshot",
+        Msg::SubvolumeQgroup => "{exclusive} exklusiv, {referenced} referenziert",
+        Msg::Datasets => "ZFS-Datasets:",
+        Msg::DatasetTotal => "{size}  {path} ({name})",
+        Msg::DatasetZfs => {
+            "{referenced} referenziert auf Disk, {logical} logisch ({ratio}x Kompression), {used} belegt mit Snapshots und Kindern"
+        }
+        Msg::SameDataAs => "{path} (dieselben Daten wie {original})",
+        Msg::SkippedFiles => {
+            "{count} Dateien über {limit} übersprungen, insgesamt {size} (die größten {shown})"
+        }
+        Msg::SpecialCounted => "{count} Spezialdateien als Dateien gezählt ({kinds})",
+        Msg::SpecialSkipped => "{count} Spezialdateien übersprungen ({kinds})",
+        Msg::SpecialListed => {
+            "{count} Spezialdateien nicht mitgezählt ({kinds}; die ersten {shown})"
+        }
+        Msg::AllReadable => "Alle Dateien haben die Leseprüfung bestanden",
+        Msg::Unreadable => {
+            "Nicht lesbar: {count} Dateien haben die Leseprüfung nicht bestanden (die ersten {shown})"
+        }
+        Msg::NoDuplicates => "Keine doppelten Dateien gefunden",
+        Msg::Duplicates => "Duplikate: {groups} Gruppen, {size} einsparbar (die größten {shown})",
+        Msg::DuplicateGroup => "{copies} x {size} ({reclaimable} einsparbar)",
+        Msg::NoDuplicateTrees => "Keine doppelten Verzeichnisbäume gefunden",
+        Msg::DuplicateTrees => {
+            "Doppelte Verzeichnisbäume: {groups} Gruppen, {size} einsparbar (die größten {shown})"
+        }
+        Msg::NoSimilar => "Keine ähnlichen Dateien gefunden",
+        Msg::Similar => {
+            "Ähnliche Dateien: {pairs} Paare, {size} einsparbar per Delta-Speicherung (die größten {shown})"
+        }
+        Msg::SimilarPair => "{similarity}% ähnlich, {shared} gemeinsam",
+        Msg::Progress => "Scanne... {spinner} {entries} Einträge",
+        Msg::ProgressEta => "Scanne... {percent}% von ~{entries} Einträgen (noch {eta})",
+    }
+}
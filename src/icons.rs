@@ -0,0 +1,88 @@
+use crate::group::GroupBy;
+use crate::output;
+use std::path::Path;
+
+/// Glyph set for `--icons`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Icons {
+    /// Emoji, shown by most terminals
+    Emoji,
+    /// Nerd Fonts glyphs, as used by eza and lsd
+    Nerd,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Folder,
+    Media,
+    Archive,
+    Code,
+    Document,
+    File,
+}
+
+const MEDIA: &[&str] = &[
+    "mp4", "mkv", "avi", "mov", "webm", "wmv", "flv", "m4v", "mp3", "flac", "wav", "ogg", "m4a",
+    "aac", "opus", "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "heic", "svg", "raw",
+    "cr2", "nef", "psd",
+];
+const ARCHIVE: &[&str] = &[
+    "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "iso", "dmg", "deb", "rpm", "jar",
+    "whl",
+];
+const CODE: &[&str] = &[
+    "rs", "c", "h", "cc", "cpp", "hpp", "py", "js", "ts", "jsx", "tsx", "go", "java", "kt", "rb",
+    "php", "sh", "swift", "cs", "scala", "lua", "pl", "hs", "ml", "json", "yaml", "yml", "toml",
+    "xml", "html", "css", "sql",
+];
+const DOCUMENT: &[&str] = &[
+    "pdf", "doc", "docx", "odt", "txt", "md", "rtf", "xls", "xlsx", "ods", "csv", "ppt", "pptx",
+    "odp", "epub",
+];
+
+impl Icons {
+    /// Icon for a report entry. Directories get a folder; with
+    /// `--group-by ext` each extension gets its file type's icon.
+    pub fn for_entry(self, key: &Path, group_by: GroupBy) -> &'static str {
+        let kind = match group_by {
+            _ if output::is_root_files(key) => Kind::File,
+            _ if key == Path::new("(no project)") => Kind::File,
+            GroupBy::Dir | GroupBy::Project => Kind::Folder,
+            GroupBy::Ext => kind_of(&key.to_string_lossy()),
+            _ => Kind::File,
+        };
+        match (self, kind) {
+            (Icons::Emoji, Kind::Folder) => "📁",
+            (Icons::Emoji, Kind::Media) => "🎬",
+            (Icons::Emoji, Kind::Archive) => "📦",
+            (Icons::Emoji, Kind::Code) => "💻",
+            (Icons::Emoji, Kind::Document) => "📝",
+            (Icons::Emoji, Kind::File) => "📄",
+            (Icons::Nerd, Kind::Folder) => "\u{f07b}",
+            (Icons::Nerd, Kind::Media) => "\u{f03d}",
+            (Icons::Nerd, Kind::Archive) => "\u{f410}",
+            (Icons::Nerd, Kind::Code) => "\u{f121}",
+            (Icons::Nerd, Kind::Document) => "\u{f15c}",
+            (Icons::Nerd, Kind::File) => "\u{f15b}",
+        }
+    }
+}
+
+/// `label` is an `--group-by ext` key: `.gz`, `(none)`, or `(directories)`.
+fn kind_of(label: &str) -> Kind {
+    if label == "(directories)" {
+        return Kind::Folder;
+    }
+    let ext = label.trim_start_matches('.');
+    if MEDIA.contains(&ext) {
+        Kind::Media
+    } else if ARCHIVE.contains(&ext) {
+        Kind::Archive
+    } else if CODE.contains(&ext) {
+        Kind::Code
+    } else if DOCUMENT.contains(&ext) {
+        Kind::Document
+    } else {
+        Kind::File
+    }
+}
@@ -0,0 +1,334 @@
+use crate::group::GroupBy;
+use crate::output::{DirStats, Format, PercentOf, Report, SortBy};
+use crate::tree;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Dumps written by other tools that `import` reads.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Source {
+    /// `du` directory totals in 1 KiB blocks (the default) or with `-h`
+    /// suffixes
+    Du,
+    /// `du -b` directory totals in bytes
+    DuBytes,
+    /// ncdu JSON export (`ncdu -o`)
+    Ncdu,
+}
+
+/// Bytes found directly in one directory of the dump, outside any
+/// subdirectory it lists.
+struct Own {
+    /// Relative to the dump's root; empty for the root itself.
+    dir: PathBuf,
+    size: u64,
+    files: u64,
+}
+
+/// A dump, reduced to what the aggregation needs.
+pub struct Dump {
+    root: PathBuf,
+    own: Vec<Own>,
+    total_dirs: u64,
+}
+
+pub fn read(source: Source, path: &Path) -> Result<Dump> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    match source {
+        Source::Du => read_du(&bytes, true),
+        Source::DuBytes => read_du(&bytes, false),
+        Source::Ncdu => read_ncdu(&bytes),
+    }
+    .with_context(|| format!("failed to import {}", path.display()))
+}
+
+/// `du` prints one cumulative total per directory. Each directory's own bytes
+/// are its total minus the totals of the directories listed below it. File
+/// counts aren't part of the output and stay zero.
+fn read_du(bytes: &[u8], kib: bool) -> Result<Dump> {
+    let mut totals: HashMap<PathBuf, u64> = HashMap::new();
+    for (number, line) in bytes.split(|&b| b == b'\n').enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let Some(tab) = line.iter().position(|&b| b == b'\t') else {
+            bail!("line {}: expected <size><TAB><path>", number + 1);
+        };
+        let size = std::str::from_utf8(&line[..tab])
+            .ok()
+            .and_then(|s| du_size(s.trim(), kib))
+            .with_context(|| format!("line {}: invalid size", number + 1))?;
+        totals.insert(PathBuf::from(os_string(&line[tab + 1..])), size);
+    }
+
+    // du lists the root last, but any line works: it's the ancestor of all.
+    let Some(root) = totals
+        .keys()
+        .min_by_key(|p| p.components().count())
+        .cloned()
+    else {
+        bail!("no directories listed");
+    };
+    let mut own: HashMap<PathBuf, i64> = HashMap::new();
+    for (path, &size) in &totals {
+        let Ok(relative) = path.strip_prefix(&root) else {
+            bail!("{} is outside {}", path.display(), root.display());
+        };
+        *own.entry(relative.to_path_buf()).or_insert(0) += size as i64;
+        // Subtract from the closest listed ancestor; `du -d N` leaves gaps.
+        if let Some(parent) = path.ancestors().skip(1).find(|a| totals.contains_key(*a)) {
+            let parent = parent.strip_prefix(&root).unwrap().to_path_buf();
+            *own.entry(parent).or_insert(0) -= size as i64;
+        }
+    }
+
+    Ok(Dump {
+        root,
+        total_dirs: totals.len() as u64 - 1,
+        own: own
+            .into_iter()
+            .map(|(dir, size)| Own {
+                dir,
+                // Hardlinks du counted once under an earlier directory can make
+                // a parent smaller than its children.
+                size: size.max(0) as u64,
+                files: 0,
+            })
+            .collect(),
+    })
+}
+
+fn du_size(s: &str, kib: bool) -> Option<u64> {
+    match s.parse::<u64>() {
+        Ok(n) if kib => Some(n * 1024),
+        Ok(n) => Some(n),
+        Err(_) if kib => crate::parse_human_size(s).ok(),
+        Err(_) => None,
+    }
+}
+
+#[cfg(unix)]
+fn os_string(bytes: &[u8]) -> OsString {
+    std::os::unix::ffi::OsStringExt::from_vec(bytes.to_vec())
+}
+
+#[cfg(not(unix))]
+fn os_string(bytes: &[u8]) -> OsString {
+    OsString::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// ncdu's export is `[major, minor, {metadata}, root]`, where a directory is
+/// an array of its own info object followed by its children: objects for
+/// files, arrays for subdirectories.
+fn read_ncdu(bytes: &[u8]) -> Result<Dump> {
+    let export: Value = serde_json::from_slice(bytes).context("not valid JSON")?;
+    let Some(root) = export.get(3).filter(|r| r.is_array()) else {
+        bail!("not an ncdu export");
+    };
+    let mut dump = Dump {
+        root: PathBuf::from(ncdu_name(root)?),
+        own: Vec::new(),
+        total_dirs: 0,
+    };
+    ncdu_dir(root, PathBuf::new(), &mut dump)?;
+    Ok(dump)
+}
+
+fn ncdu_dir(dir: &Value, relative: PathBuf, dump: &mut Dump) -> Result<()> {
+    let mut own = Own {
+        dir: relative,
+        size: 0,
+        files: 0,
+    };
+    for child in dir.as_array().into_iter().flatten().skip(1) {
+        match child {
+            Value::Array(_) => {
+                dump.total_dirs += 1;
+                ncdu_dir(child, own.dir.join(ncdu_name(child)?), dump)?;
+            }
+            // Entries ncdu didn't count itself are left out here too.
+            info if info.get("excluded").is_some() => {}
+            info => {
+                // Apparent size, like a scan; disk usage for old exports.
+                own.size += ["asize", "dsize"]
+                    .iter()
+                    .find_map(|field| info.get(*field).and_then(Value::as_u64))
+                    .unwrap_or(0);
+                own.files += 1;
+            }
+        }
+    }
+    dump.own.push(own);
+    Ok(())
+}
+
+fn ncdu_name(dir: &Value) -> Result<&str> {
+    dir.get(0)
+        .and_then(|info| info.get("name"))
+        .and_then(Value::as_str)
+        .context("directory without a name")
+}
+
+/// Walks `fs` from its root (the empty path) into a dump shown as `root`,
+/// for trees listed somewhere other than the local filesystem.
+pub fn walk(fs: &dyn crate::vfs::FileSystem, root: PathBuf) -> Result<Dump> {
+    let mut own: HashMap<PathBuf, Own> = HashMap::new();
+    let mut total_dirs = 0;
+    let options = crate::ScanOptions::default();
+    crate::scan_fs_with(fs, Path::new(""), &options, |event| match event {
+        crate::Event::Entered { depth, .. } if depth > 0 => total_dirs += 1,
+        crate::Event::File { path, size, .. } => {
+            let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            let own = own.entry(dir.clone()).or_insert(Own {
+                dir,
+                size: 0,
+                files: 0,
+            });
+            own.size += size;
+            own.files += 1;
+        }
+        _ => {}
+    })?;
+    Ok(Dump {
+        root,
+        own: own.into_values().collect(),
+        total_dirs,
+    })
+}
+
+impl Dump {
+    /// Groups the dump like a scan with `--group-by dir`.
+    pub fn into_report(
+        self,
+        format: Format,
+        depth: usize,
+        percent_of: PercentOf,
+        min_size: u64,
+        sort: SortBy,
+        top: usize,
+    ) -> Report {
+        let max_depth = if depth == 0 { usize::MAX } else { depth };
+        let zero = DirStats {
+            size: 0,
+            file_count: 0,
+        };
+        let mut dir_sizes: HashMap<PathBuf, DirStats> = HashMap::new();
+        let mut root_files = zero;
+        let (mut total_size, mut total_files) = (0, 0);
+        for own in &self.own {
+            let parts: Vec<_> = own.dir.iter().collect();
+            let stats = if parts.is_empty() {
+                &mut root_files
+            } else {
+                let key = parts[..max_depth.min(parts.len())].iter().collect();
+                dir_sizes.entry(key).or_insert(zero)
+            };
+            stats.size += own.size;
+            stats.file_count += own.files;
+            total_size += own.size;
+            total_files += own.files;
+        }
+
+        let tree = format
+            .needs_tree()
+            .then(|| tree::build(self.root.as_os_str(), &dir_sizes, total_size, top, min_size));
+        let (entries, parent_sizes) = tree::rank(
+            dir_sizes, root_files, depth, percent_of, min_size, sort, top,
+        );
+
+        Report {
+            parent_sizes,
+            total_size,
+            total_files,
+            total_dirs: self.total_dirs,
+            tree,
+            ..Report::new(self.root, entries, percent_of, GroupBy::Dir, top)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each directory's own bytes and files, by path relative to the root.
+    fn own(dump: &Dump) -> Vec<(&str, u64, u64)> {
+        let mut own: Vec<_> = dump
+            .own
+            .iter()
+            .map(|o| (o.dir.to_str().unwrap(), o.size, o.files))
+            .collect();
+        own.sort();
+        own
+    }
+
+    #[test]
+    fn du_totals_become_own_sizes() {
+        let dump = read_du(b"4\t/srv/a/b\n12\t/srv/a\n20\t/srv\n", true).unwrap();
+        assert_eq!(dump.root, Path::new("/srv"));
+        assert_eq!(dump.total_dirs, 2);
+        assert_eq!(
+            own(&dump),
+            [("", 8192, 0), ("a", 8192, 0), ("a/b", 4096, 0)]
+        );
+    }
+
+    #[test]
+    fn du_sizes() {
+        let dump = read_du(b"1.5K\t/r/x\n2K\t/r\n", true).unwrap();
+        assert_eq!(own(&dump), [("", 512, 0), ("x", 1536, 0)]);
+        let dump = read_du(b"100\t/r/x\n300\t/r\n", false).unwrap();
+        assert_eq!(own(&dump), [("", 200, 0), ("x", 100, 0)]);
+        assert!(read_du(b"1K\t/r\n", false).is_err());
+    }
+
+    #[test]
+    fn du_gaps_and_hardlinks() {
+        // `du -d 1` style gaps subtract from the closest listed ancestor.
+        let dump = read_du(b"3\t/r/a/b/c\n10\t/r\n", false).unwrap();
+        assert_eq!(own(&dump), [("", 7, 0), ("a/b/c", 3, 0)]);
+        // A parent du counted smaller than its child clamps to zero.
+        let dump = read_du(b"10\t/r/a\n4\t/r\n", false).unwrap();
+        assert_eq!(own(&dump), [("", 0, 0), ("a", 10, 0)]);
+    }
+
+    #[test]
+    fn du_errors() {
+        let err = |text: &[u8]| format!("{:#}", read_du(text, true).err().unwrap());
+        assert_eq!(
+            err(b"4\t/r\n12 /r/a\n"),
+            "line 2: expected <size><TAB><path>"
+        );
+        assert_eq!(err(b"big\t/r\n"), "line 1: invalid size");
+        assert_eq!(err(b"\n\n"), "no directories listed");
+        assert!(err(b"1\t/a/x\n1\t/b/y\n").contains("is outside"));
+    }
+
+    #[test]
+    fn ncdu_export() {
+        let export = br#"[1, 2, {"progname": "ncdu"},
+            [{"name": "/srv"},
+             {"name": "f", "asize": 100, "dsize": 4096},
+             {"name": "old", "dsize": 512},
+             {"name": "skipped", "asize": 9, "excluded": "pattern"},
+             [{"name": "a"},
+              {"name": "g", "asize": 7},
+              [{"name": "empty"}]]]]"#;
+        let dump = read_ncdu(export).unwrap();
+        assert_eq!(dump.root, Path::new("/srv"));
+        assert_eq!(dump.total_dirs, 2);
+        assert_eq!(own(&dump), [("", 612, 2), ("a", 7, 1), ("a/empty", 0, 0)]);
+    }
+
+    #[test]
+    fn ncdu_errors() {
+        let err = |text: &[u8]| format!("{:#}", read_ncdu(text).err().unwrap());
+        assert!(err(b"{").starts_with("not valid JSON"));
+        assert_eq!(err(br#"[1, 2, {}]"#), "not an ncdu export");
+        assert_eq!(err(br#"[1, 2, {}, [{}]]"#), "directory without a name");
+    }
+}
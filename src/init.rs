@@ -0,0 +1,161 @@
+use crate::output::{self, Format};
+use crate::preset::{self, Preset};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// `diskhound init`: asks for the usual defaults on `output` and reads the
+/// answers from `input`, then writes them as a config file to `path`.
+/// Empty answers keep diskhound's built-in behavior.
+pub fn run(path: &Path, input: &mut dyn BufRead, output: &mut dyn Write) -> Result<()> {
+    writeln!(
+        output,
+        "Writing {}. Press Enter to keep a default.\n",
+        path.display()
+    )?;
+    if path.exists() {
+        let overwrite = ask(
+            input,
+            output,
+            &format!("{} exists. Replace it? (y/n)", path.display()),
+            "n",
+            |answer| Ok(answer.to_ascii_lowercase()),
+        )?;
+        if !matches!(overwrite.as_str(), "y" | "yes") {
+            bail!("left {} unchanged", path.display());
+        }
+    }
+
+    let excludes = ask(
+        input,
+        output,
+        "Directory names to always exclude, comma-separated (e.g. .git, .cache)",
+        "",
+        |answer| Ok(list(answer).join(", ")),
+    )?;
+    let built_in: Vec<&str> = preset::BUILT_IN.iter().map(|(n, _)| *n).collect();
+    let presets = ask(
+        input,
+        output,
+        &format!("Presets to always apply ({})", built_in.join(", ")),
+        "",
+        |answer| {
+            let names = list(answer);
+            if let Some(name) = names.iter().find(|n| Preset::from_name(n).is_none()) {
+                bail!("unknown preset {name:?}");
+            }
+            Ok(names.join(", "))
+        },
+    )?;
+    let auto_exclude = ask(
+        input,
+        output,
+        "Skip target/, node_modules/ and virtualenvs beside the project files that create them? (y/n)",
+        "n",
+        |answer| match answer.to_ascii_lowercase().as_str() {
+            "y" | "yes" => Ok("true".to_string()),
+            "n" | "no" => Ok(String::new()),
+            _ => bail!("answer y or n"),
+        },
+    )?;
+    let formats: Vec<String> = Format::value_variants()
+        .iter()
+        .filter_map(|f| Some(f.to_possible_value()?.get_name().to_string()))
+        .collect();
+    let format = ask(
+        input,
+        output,
+        &format!("Output format ({})", formats.join(", ")),
+        "table",
+        |answer| match Format::from_str(answer, true) {
+            Ok(Format::Table) => Ok(String::new()),
+            Ok(_) => Ok(answer.to_ascii_lowercase()),
+            Err(_) => bail!("unknown format {answer:?}"),
+        },
+    )?;
+    let top = ask(
+        input,
+        output,
+        "Entries to show",
+        "10",
+        |answer| match answer.parse::<usize>() {
+            Ok(10) => Ok(String::new()),
+            Ok(n) => Ok(n.to_string()),
+            Err(_) => bail!("expected a number"),
+        },
+    )?;
+
+    let settings = [
+        ("exclude", excludes),
+        ("preset", presets),
+        ("auto-exclude", auto_exclude),
+        ("format", format),
+        ("top", top),
+    ];
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    output::write_atomic(path, |w| {
+        writeln!(w, "# diskhound configuration, written by `diskhound init`.")?;
+        writeln!(
+            w,
+            "# Command-line flags and DISKHOUND_* variables override these."
+        )?;
+        writeln!(w, "[defaults]")?;
+        for (key, value) in &settings {
+            if value.is_empty() {
+                writeln!(w, "# {key} =")?;
+            } else {
+                writeln!(w, "{key} = {value}")?;
+            }
+        }
+        writeln!(w)?;
+        writeln!(w, "# Size limits diskhound fails on, e.g. `target = 2GB`.")?;
+        writeln!(w, "[budgets]")?;
+        writeln!(w)?;
+        writeln!(w, "# Tags for --by-tag, e.g. `/srv/ml-data = team:vision`.")?;
+        writeln!(w, "[tags]")?;
+        Ok(())
+    })?;
+    writeln!(output, "\nWrote {}", path.display())?;
+    Ok(())
+}
+
+/// Asks until `check` accepts the answer (or `default` on an empty line or
+/// end of input), returning what it makes of it.
+fn ask(
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    question: &str,
+    default: &str,
+    check: impl Fn(&str) -> Result<String>,
+) -> Result<String> {
+    loop {
+        if default.is_empty() {
+            write!(output, "{question}: ")?;
+        } else {
+            write!(output, "{question} [{default}]: ")?;
+        }
+        output.flush()?;
+        let mut line = String::new();
+        let eof = input.read_line(&mut line)? == 0;
+        let answer = match line.trim() {
+            "" => default,
+            answer => answer,
+        };
+        match check(answer) {
+            Ok(value) => return Ok(value),
+            Err(err) if eof => return Err(err),
+            Err(err) => writeln!(output, "  {err}")?,
+        }
+    }
+}
+
+fn list(answer: &str) -> Vec<String> {
+    answer
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
@@ -0,0 +1,53 @@
+use crate::output::Report;
+use anyhow::{bail, Result};
+use std::io::Write;
+
+/// `--format junit`: one test suite with a test case per size budget,
+/// failing when the budget is exceeded, for CI systems that already collect
+/// JUnit XML.
+pub fn write(w: &mut dyn Write, report: &Report) -> Result<()> {
+    let Some(budgets) = report.budgets.as_deref() else {
+        bail!("--format junit needs size budgets (--budget PATH=SIZE or [budgets] in the config file)");
+    };
+    let failures = budgets.iter().filter(|m| m.exceeded()).count();
+    let suite = escape(&format!("disk budgets of {}", report.root.display()));
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<testsuites name="diskhound" tests="{}" failures="{failures}">"#,
+        budgets.len()
+    )?;
+    writeln!(
+        w,
+        r#"  <testsuite name="{suite}" tests="{}" failures="{failures}" errors="0" skipped="0">"#,
+        budgets.len()
+    )?;
+    for measured in budgets {
+        let name = escape(&measured.name());
+        let message = escape(&measured.describe());
+        writeln!(
+            w,
+            r#"    <testcase classname="diskhound.budget" name="{name}">"#
+        )?;
+        if measured.exceeded() {
+            writeln!(
+                w,
+                r#"      <failure message="{message}" type="BudgetExceeded">{message}</failure>"#
+            )?;
+        } else {
+            writeln!(w, "      <system-out>{message}</system-out>")?;
+        }
+        writeln!(w, "    </testcase>")?;
+    }
+    writeln!(w, "  </testsuite>")?;
+    writeln!(w, "</testsuites>")?;
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,139 @@
+use crate::snapshot::{self, Snapshot};
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Where the binary is copied inside the pod; /tmp is writable in most
+/// images, read-only root filesystems aside.
+const REMOTE_BINARY: &str = "/tmp/diskhound";
+
+/// A running pod's container that mounts the claim, and where.
+pub struct Target {
+    pub pod: String,
+    pub container: String,
+    pub mount_path: String,
+}
+
+/// Finds a running pod mounting PVC `claim`, copies `binary` (a static
+/// diskhound built for the pod's architecture) into it, runs it over the
+/// mount with `scan_args`, and reads back the JSON report it prints.
+/// Everything goes through `kubectl`, with its current context.
+pub fn scan(
+    claim: &str,
+    namespace: Option<&str>,
+    binary: &Path,
+    scan_args: &[String],
+) -> Result<(Target, Snapshot)> {
+    let target = find(claim, namespace)?;
+    tracing::info!(
+        "scanning PVC {claim} at {}:{} (container {})",
+        target.pod,
+        target.mount_path,
+        target.container
+    );
+    let executable = std::fs::File::open(binary)
+        .with_context(|| format!("failed to read {}", binary.display()))?;
+    // `kubectl cp` needs tar in the image; sh and cat are more common.
+    let status = exec(namespace, &target)
+        .args(["sh", "-c"])
+        .arg(format!("cat > {REMOTE_BINARY} && chmod +x {REMOTE_BINARY}"))
+        .stdin(executable)
+        .status()
+        .context("failed to run kubectl")?;
+    if !status.success() {
+        bail!("couldn't copy diskhound into pod {}", target.pod);
+    }
+    let output = exec(namespace, &target)
+        .arg(REMOTE_BINARY)
+        .arg(&target.mount_path)
+        .args(["--format", "json", "--no-pager", "--no-progress"])
+        .args(scan_args)
+        // The remote scan's warnings show up as they happen.
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed to run kubectl")?;
+    let cleaned = exec(namespace, &target)
+        .args(["rm", "-f", REMOTE_BINARY])
+        .stdout(Stdio::null())
+        .status();
+    if !cleaned.is_ok_and(|status| status.success()) {
+        tracing::warn!("couldn't remove {REMOTE_BINARY} from pod {}", target.pod);
+    }
+    if !output.status.success() {
+        bail!("the scan in pod {} failed", target.pod);
+    }
+    let text = String::from_utf8(output.stdout).context("the pod's scan wrote invalid UTF-8")?;
+    let what = format!("the report from pod {}", target.pod);
+    let snapshot = snapshot::parse(&text, &what)?;
+    Ok((target, snapshot))
+}
+
+fn kubectl(namespace: Option<&str>) -> Command {
+    let mut command = Command::new("kubectl");
+    if let Some(namespace) = namespace {
+        command.args(["--namespace", namespace]);
+    }
+    command
+}
+
+fn exec(namespace: Option<&str>, target: &Target) -> Command {
+    let mut command = kubectl(namespace);
+    command
+        .args(["exec", "-i", &target.pod, "-c", &target.container, "--"])
+        .stdin(Stdio::null());
+    command
+}
+
+/// The first running pod (by name) with a container mounting `claim`.
+fn find(claim: &str, namespace: Option<&str>) -> Result<Target> {
+    let output = kubectl(namespace)
+        .args([
+            "get",
+            "pods",
+            "--field-selector=status.phase=Running",
+            "-o",
+            "json",
+        ])
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed to run kubectl")?;
+    if !output.status.success() {
+        bail!("kubectl get pods failed");
+    }
+    let pods: Value =
+        serde_json::from_slice(&output.stdout).context("kubectl wrote invalid JSON")?;
+    let mut targets: Vec<Target> = pods["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|pod| mounting(pod, claim))
+        .collect();
+    targets.sort_by(|a, b| a.pod.cmp(&b.pod));
+    match targets.into_iter().next() {
+        Some(target) => Ok(target),
+        None => bail!(
+            "no running pod mounts PVC {claim}; start one that does (e.g. a sleeping busybox with the claim mounted)"
+        ),
+    }
+}
+
+fn mounting(pod: &Value, claim: &str) -> Option<Target> {
+    let spec = &pod["spec"];
+    let volume = spec["volumes"]
+        .as_array()?
+        .iter()
+        .find(|v| v["persistentVolumeClaim"]["claimName"] == claim)?["name"]
+        .as_str()?;
+    spec["containers"].as_array()?.iter().find_map(|container| {
+        let mount = container["volumeMounts"]
+            .as_array()?
+            .iter()
+            .find(|m| m["name"] == volume)?;
+        Some(Target {
+            pod: pod["metadata"]["name"].as_str()?.to_string(),
+            container: container["name"].as_str()?.to_string(),
+            mount_path: mount["mountPath"].as_str()?.to_string(),
+        })
+    })
+}
@@ -0,0 +1,155 @@
+use crate::breakdown::extension_label;
+use crate::locale;
+use crate::output::{raw_base64, SCHEMA_VERSION};
+use anyhow::Result;
+use humansize::{format_size, BINARY};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Default)]
+struct Extension {
+    size: u64,
+    file_count: u64,
+    /// Min-heap of the largest files seen, capped at `per_ext`.
+    largest: BinaryHeap<Reverse<(u64, PathBuf)>>,
+}
+
+/// The largest files of every extension, kept in bounded heaps so memory
+/// doesn't grow with the number of files.
+pub struct LargestByExt {
+    per_ext: usize,
+    by_ext: HashMap<String, Extension>,
+}
+
+/// An extension's totals with its largest files, largest first.
+pub struct ExtFiles {
+    pub extension: String,
+    pub size: u64,
+    pub file_count: u64,
+    pub files: Vec<(PathBuf, u64)>,
+}
+
+impl LargestByExt {
+    pub fn new(per_ext: usize) -> Self {
+        Self {
+            per_ext,
+            by_ext: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, path: &Path, size: u64) {
+        let ext = self.by_ext.entry(extension_label(path)).or_default();
+        ext.size += size;
+        ext.file_count += 1;
+        if ext.largest.len() < self.per_ext {
+            ext.largest.push(Reverse((size, path.to_path_buf())));
+        } else if ext
+            .largest
+            .peek()
+            .is_some_and(|Reverse((min, _))| size > *min)
+        {
+            ext.largest.pop();
+            ext.largest.push(Reverse((size, path.to_path_buf())));
+        }
+    }
+
+    /// The `top` extensions by total size.
+    pub fn finish(self, top: usize) -> Vec<ExtFiles> {
+        let mut exts: Vec<ExtFiles> = self
+            .by_ext
+            .into_iter()
+            .map(|(extension, ext)| ExtFiles {
+                extension,
+                size: ext.size,
+                file_count: ext.file_count,
+                // Ascending by the reversed key is descending by size.
+                files: ext
+                    .largest
+                    .into_sorted_vec()
+                    .into_iter()
+                    .map(|Reverse((size, path))| (path, size))
+                    .collect(),
+            })
+            .collect();
+        exts.sort_by_key(|e| Reverse(e.size));
+        exts.truncate(top);
+        exts
+    }
+}
+
+pub fn write_table(w: &mut dyn Write, exts: &[ExtFiles]) -> Result<()> {
+    if exts.is_empty() {
+        writeln!(w, "No files found")?;
+        return Ok(());
+    }
+    for (i, ext) in exts.iter().enumerate() {
+        if i > 0 {
+            writeln!(w)?;
+        }
+        writeln!(
+            w,
+            "{}  {} in {} files",
+            ext.extension,
+            locale::size(ext.size),
+            locale::count(ext.file_count)
+        )?;
+        for (path, size) in &ext.files {
+            writeln!(w, "  {:>10}  {}", locale::size(*size), path.display())?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonLargest {
+    schema_version: u32,
+    extensions: Vec<JsonExtFiles>,
+}
+
+#[derive(Serialize)]
+struct JsonExtFiles {
+    /// Lowercased, with the leading dot; `(none)` for files without one.
+    extension: String,
+    size: u64,
+    file_count: u64,
+    /// Largest first.
+    files: Vec<JsonLargeFile>,
+}
+
+#[derive(Serialize)]
+struct JsonLargeFile {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_base64: Option<String>,
+    size: u64,
+    size_human: String,
+}
+
+pub fn write_json(w: &mut dyn Write, exts: &[ExtFiles]) -> Result<()> {
+    let output = JsonLargest {
+        schema_version: SCHEMA_VERSION,
+        extensions: exts
+            .iter()
+            .map(|ext| JsonExtFiles {
+                extension: ext.extension.clone(),
+                size: ext.size,
+                file_count: ext.file_count,
+                files: ext
+                    .files
+                    .iter()
+                    .map(|(path, size)| JsonLargeFile {
+                        path: path.to_string_lossy().to_string(),
+                        path_base64: raw_base64(path.as_os_str()),
+                        size: *size,
+                        size_human: format_size(*size, BINARY),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
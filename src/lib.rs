@@ -0,0 +1,294 @@
+//! diskhound's directory walk for embedding: [`scan_with`] walks a tree and
+//! hands every directory and file to a callback as it goes, so callers can
+//! build their own aggregations (billing keys, custom rollups) without the
+//! command line's reports. [`scan_fs_with`] does the same over any
+//! [`vfs::FileSystem`], which is what a wasm32 build (without the real
+//! filesystem walk) uses to explore uploaded listings, and
+//! [`stream::ScanStream`] yields a walk's results to async code.
+//!
+//! The command line's report model lives here too, so other frontends
+//! (including wasm32 ones) render the same reports: [`snapshot::parse`]
+//! reads a saved `--format json` report and [`import::walk`] aggregates any
+//! [`vfs::FileSystem`], each into an [`output::Report`] that
+//! [`output::write`] renders in any of the text formats.
+
+pub mod breakdown;
+pub mod btrfs;
+pub mod budget;
+#[cfg(any(feature = "parquet", feature = "arrow"))]
+pub mod columnar;
+pub mod dupes;
+pub mod filesystem;
+pub mod github;
+pub mod group;
+pub mod hardlinks;
+pub mod histogram;
+pub mod i18n;
+pub mod icons;
+pub mod import;
+pub mod junit;
+pub mod largest;
+pub mod locale;
+pub mod mounts;
+pub mod output;
+pub mod qdirstat;
+pub mod quota;
+pub mod readcheck;
+pub mod similar;
+pub mod snapshot;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stream;
+pub mod throttle;
+pub mod timings;
+pub mod tree;
+#[cfg(feature = "png")]
+pub mod treemap;
+pub mod vfs;
+pub mod zfs;
+
+use anyhow::{bail, Context, Result};
+use std::ffi::{OsStr, OsString};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// What the walk has reached, in depth-first order: a directory's
+/// `Entered`, then everything below it, then its `DirComplete`.
+pub enum Event<'a> {
+    /// A directory (the root first), `depth` levels below the root.
+    Entered { path: &'a Path, depth: usize },
+    /// A regular file. Symlinks and other special files are skipped.
+    File {
+        path: &'a Path,
+        depth: usize,
+        size: u64,
+        /// The file's metadata when walking the real filesystem.
+        metadata: Option<&'a Metadata>,
+    },
+    /// Everything below a directory has been seen: its files' total size
+    /// and count, subdirectories included.
+    DirComplete {
+        path: &'a Path,
+        depth: usize,
+        size: u64,
+        file_count: u64,
+    },
+}
+
+/// How [`scan_with`] walks.
+#[derive(Clone, Default)]
+pub struct ScanOptions {
+    /// Worker threads for the walk (0 = one per CPU, 1 = serial). Ignored
+    /// by [`scan_fs_with`], which walks serially.
+    pub walk_threads: usize,
+    /// Directory names skipped with everything below them.
+    pub exclude: Vec<OsString>,
+    /// Skip dotfiles and dot-directories.
+    pub skip_hidden: bool,
+}
+
+impl ScanOptions {
+    fn skips(&self, name: &OsStr, is_dir: bool) -> bool {
+        (self.skip_hidden && name.as_encoded_bytes().starts_with(b"."))
+            || (is_dir && self.exclude.iter().any(|e| e == name))
+    }
+}
+
+/// Bytes in a size like `100MB`, `1.5G`, or `500K` (binary units; a suffix
+/// is required).
+pub fn parse_human_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let (num_part, suffix) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => (&s[..i], s[i..].to_uppercase()),
+        None => bail!("invalid size format: {s}"),
+    };
+    let num: f64 = num_part
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid number in size: {s}"))?;
+    let multiplier: u64 = match suffix.as_str() {
+        "B" => 1,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        "T" | "TB" | "TIB" => 1024 * 1024 * 1024 * 1024,
+        _ => bail!("unknown size suffix: {suffix}"),
+    };
+    Ok((num * multiplier as f64) as u64)
+}
+
+/// Walks `root` without following symlinks, calling `visit` for each event.
+/// Entries that can't be read are skipped (and logged through `tracing`),
+/// like the command line does. Fails only if `root` itself can't be read.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn scan_with(
+    root: &Path,
+    options: &ScanOptions,
+    mut visit: impl FnMut(Event<'_>),
+) -> Result<()> {
+    use jwalk::{Parallelism, WalkDir};
+    use std::time::Duration;
+
+    let root_metadata =
+        std::fs::metadata(root).with_context(|| format!("failed to read {}", root.display()))?;
+    if !root_metadata.is_dir() {
+        bail!("{} is not a directory", root.display());
+    }
+    let parallelism = match options.walk_threads {
+        0 => Parallelism::RayonDefaultPool {
+            busy_timeout: Duration::from_secs(1),
+        },
+        1 => Parallelism::Serial,
+        n => Parallelism::RayonNewPool(n),
+    };
+    let filter = options.clone();
+    let walker = WalkDir::new(root)
+        .parallelism(parallelism)
+        .follow_links(false)
+        .skip_hidden(false)
+        .process_read_dir(move |_depth, _path, _state, children| {
+            children.retain(|entry| {
+                entry.as_ref().map_or(true, |entry| {
+                    !filter.skips(&entry.file_name, entry.file_type().is_dir())
+                })
+            });
+        });
+
+    let mut open = Open::default();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                info!("skipped: {err}");
+                continue;
+            }
+        };
+        open.close(entry.depth, &mut visit);
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            open.enter(path, entry.depth, &mut visit);
+        } else if entry.file_type().is_file() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    info!("no metadata for {}: {err}", path.display());
+                    continue;
+                }
+            };
+            open.file(
+                &path,
+                entry.depth,
+                metadata.len(),
+                Some(&metadata),
+                &mut visit,
+            );
+        }
+    }
+    open.close(0, &mut visit);
+    Ok(())
+}
+
+/// [`scan_with`] over `fs` instead of the real filesystem, serially and in
+/// the order `fs` lists entries.
+pub fn scan_fs_with(
+    fs: &dyn vfs::FileSystem,
+    root: &Path,
+    options: &ScanOptions,
+    mut visit: impl FnMut(Event<'_>),
+) -> Result<()> {
+    // Read up front so a missing root fails, like `scan_with`.
+    let mut children = fs
+        .read_dir(root)
+        .with_context(|| format!("failed to read {}", root.display()))?;
+    children.reverse();
+    let mut open = Open::default();
+    open.enter(root.to_path_buf(), 0, &mut visit);
+    // Directories being listed, innermost last, with their unvisited
+    // children in reverse.
+    let mut pending = vec![(root.to_path_buf(), 0, children)];
+    while let Some((dir, depth, children)) = pending.last_mut() {
+        let Some(child) = children.pop() else {
+            pending.pop();
+            continue;
+        };
+        let (path, depth) = (dir.join(&child.name), *depth + 1);
+        if options.skips(&child.name, child.kind == vfs::Kind::Dir) {
+            continue;
+        }
+        open.close(depth, &mut visit);
+        match child.kind {
+            vfs::Kind::Dir => match fs.read_dir(&path) {
+                Ok(mut grandchildren) => {
+                    grandchildren.reverse();
+                    open.enter(path.clone(), depth, &mut visit);
+                    pending.push((path, depth, grandchildren));
+                }
+                Err(err) => info!("skipped {}: {err}", path.display()),
+            },
+            vfs::Kind::File => open.file(&path, depth, child.size, None, &mut visit),
+            vfs::Kind::Other => {}
+        }
+    }
+    open.close(0, &mut visit);
+    Ok(())
+}
+
+/// Directories entered but not yet complete, innermost last.
+#[derive(Default)]
+struct Open(Vec<OpenDir>);
+
+struct OpenDir {
+    path: PathBuf,
+    depth: usize,
+    size: u64,
+    file_count: u64,
+}
+
+impl Open {
+    fn enter(&mut self, path: PathBuf, depth: usize, visit: &mut impl FnMut(Event<'_>)) {
+        visit(Event::Entered { path: &path, depth });
+        self.0.push(OpenDir {
+            path,
+            depth,
+            size: 0,
+            file_count: 0,
+        });
+    }
+
+    fn file(
+        &mut self,
+        path: &Path,
+        depth: usize,
+        size: u64,
+        metadata: Option<&Metadata>,
+        visit: &mut impl FnMut(Event<'_>),
+    ) {
+        if let Some(parent) = self.0.last_mut() {
+            parent.size += size;
+            parent.file_count += 1;
+        }
+        visit(Event::File {
+            path,
+            depth,
+            size,
+            metadata,
+        });
+    }
+
+    /// Completes the open directories at `depth` or deeper, adding each
+    /// one's totals to its parent: in depth-first order, reaching depth `d`
+    /// means every open directory at `d` or deeper is done.
+    fn close(&mut self, depth: usize, visit: &mut impl FnMut(Event<'_>)) {
+        while let Some(dir) = self.0.pop_if(|dir| dir.depth >= depth) {
+            if let Some(parent) = self.0.last_mut() {
+                parent.size += dir.size;
+                parent.file_count += dir.file_count;
+            }
+            visit(Event::DirComplete {
+                path: &dir.path,
+                depth: dir.depth,
+                size: dir.size,
+                file_count: dir.file_count,
+            });
+        }
+    }
+}
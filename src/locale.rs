@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+use humansize::{format_size, BINARY};
+use std::sync::OnceLock;
+
+/// How numbers are written in human-readable output. Machine formats always
+/// use plain digits.
+struct Numbers {
+    /// Between groups of three integer digits; empty for no grouping.
+    thousands: &'static str,
+    decimal: char,
+}
+
+const PLAIN: Numbers = Numbers {
+    thousands: "",
+    decimal: '.',
+};
+
+static NUMBERS: OnceLock<Numbers> = OnceLock::new();
+
+/// Picks the number format from `--locale`, or else from `LC_ALL`,
+/// `LC_NUMERIC`, or `LANG` like the C library does. The C locale and locales
+/// this table doesn't know keep plain numbers; an unknown `--locale` is an
+/// error.
+pub fn init(name: Option<&str>) -> Result<()> {
+    let numbers = match name {
+        Some(name) => match lookup(name) {
+            Some(numbers) => numbers,
+            None => bail!("unknown locale {name:?}; try e.g. en_US, de_DE, or fr_FR"),
+        },
+        None => ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            .and_then(|name| lookup(&name))
+            .unwrap_or(PLAIN),
+    };
+    let _ = NUMBERS.set(numbers);
+    Ok(())
+}
+
+/// `de_CH.UTF-8@euro` style names; only the language and region matter.
+fn lookup(name: &str) -> Option<Numbers> {
+    let name = name.split(['.', '@']).next().unwrap_or_default();
+    let (language, region) = name.split_once(['_', '-']).unwrap_or((name, ""));
+    let (thousands, decimal) = match (language, region) {
+        ("C" | "POSIX", _) => return Some(PLAIN),
+        (_, "CH") => ("'", '.'),
+        ("en" | "ja" | "zh" | "ko" | "he" | "th" | "hi" | "ga" | "ms", _) => (",", '.'),
+        (
+            "de" | "nl" | "es" | "it" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "sl" | "hr"
+            | "sr" | "vi",
+            _,
+        ) => (".", ','),
+        (
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "nn" | "no" | "uk" | "hu"
+            | "bg" | "lt" | "lv" | "et",
+            _,
+        ) => ("\u{a0}", ','),
+        _ => return None,
+    };
+    Some(Numbers { thousands, decimal })
+}
+
+fn numbers() -> &'static Numbers {
+    NUMBERS.get().unwrap_or(&PLAIN)
+}
+
+/// Rewrites a plain `1234.5` in the locale's style.
+fn localize(plain: &str) -> String {
+    let numbers = numbers();
+    let (int, frac) = match plain.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (plain, None),
+    };
+    let mut out = String::with_capacity(plain.len() + int.len() / 3);
+    for (i, digit) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            out.push_str(numbers.thousands);
+        }
+        out.push(digit);
+    }
+    if let Some(frac) = frac {
+        out.push(numbers.decimal);
+        out.push_str(frac);
+    }
+    out
+}
+
+/// A count like `1,234,567`.
+pub fn count(n: u64) -> String {
+    localize(&n.to_string())
+}
+
+/// A fixed-point number like `12.5`, with `decimals` digits after the point.
+pub fn fixed(x: f64, decimals: usize) -> String {
+    localize(&format!("{x:.decimals$}"))
+}
+
+/// A binary size like `1.5 GiB`.
+pub fn size(bytes: u64) -> String {
+    let plain = format_size(bytes, BINARY);
+    match plain.split_once(' ') {
+        Some((number, unit)) => format!("{} {unit}", localize(number)),
+        None => plain,
+    }
+}
@@ -0,0 +1,135 @@
+use crate::output::{display_name, Report};
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Set by a `cancel` request; the walk checks it like `--timeout`.
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+/// Whether the running scan should stop with partial results.
+pub fn cancelled() -> bool {
+    CANCEL.load(Ordering::Relaxed)
+}
+
+/// `--machine`: reads one JSON request per line from stdin and answers each
+/// with one JSON line on stdout, until stdin closes and the last scan has
+/// answered. Requests look like
+/// `{"id": 1, "method": "scan", "params": {"path": "."}}`; answers carry the
+/// same `id` with a `result` or an `error` object. Methods:
+///
+/// - `scan` (`path`): scans in the background and answers when done, with
+///   totals and whether the walk was complete. One scan runs at a time.
+/// - `top` (`n`, optional `under`): the `n` largest entries of the last
+///   scan, optionally only those directly below the entry `under`.
+/// - `cancel`: stops the running scan, which then answers with what it
+///   counted so far.
+pub fn run(scan: impl Fn(&Path) -> Result<Report> + Sync) -> Result<()> {
+    let out = Mutex::new(std::io::stdout());
+    let last: Mutex<Option<Report>> = Mutex::new(None);
+    std::thread::scope(|s| {
+        let mut running: Option<std::thread::ScopedJoinHandle<()>> = None;
+        for line in std::io::stdin().lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request: Value = match serde_json::from_str(&line) {
+                Ok(request) => request,
+                Err(err) => {
+                    respond(&out, &Value::Null, Err(format!("invalid request: {err}")))?;
+                    continue;
+                }
+            };
+            let id = request.get("id").cloned().unwrap_or(Value::Null);
+            let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+            let busy = running.as_ref().is_some_and(|scan| !scan.is_finished());
+            match request.get("method").and_then(Value::as_str) {
+                Some("scan") => {
+                    let Some(path) = params.get("path").and_then(Value::as_str) else {
+                        respond(&out, &id, Err("scan needs a \"path\"".to_string()))?;
+                        continue;
+                    };
+                    if busy {
+                        respond(&out, &id, Err("a scan is already running".to_string()))?;
+                        continue;
+                    }
+                    CANCEL.store(false, Ordering::Relaxed);
+                    let path = PathBuf::from(path);
+                    let (scan, out, last) = (&scan, &out, &last);
+                    running = Some(s.spawn(move || {
+                        let answer = match scan(&path) {
+                            Ok(report) => {
+                                let summary = summary(&report);
+                                *last.lock().unwrap() = Some(report);
+                                Ok(summary)
+                            }
+                            Err(err) => Err(format!("{err:#}")),
+                        };
+                        // Nothing to tell anyone if stdout is gone.
+                        let _ = respond(out, &id, answer);
+                    }));
+                }
+                Some("top") => {
+                    let answer = top(last.lock().unwrap().as_ref(), &params);
+                    respond(&out, &id, answer.map_err(|err| format!("{err:#}")))?;
+                }
+                Some("cancel") => {
+                    CANCEL.store(true, Ordering::Relaxed);
+                    respond(&out, &id, Ok(json!({ "cancelled": busy })))?;
+                }
+                Some(method) => respond(&out, &id, Err(format!("unknown method {method:?}")))?,
+                None => respond(&out, &id, Err("missing \"method\"".to_string()))?,
+            }
+        }
+        // Stdin closed: a running scan still answers before exiting.
+        Ok(())
+    })
+}
+
+fn respond(out: &Mutex<std::io::Stdout>, id: &Value, answer: Result<Value, String>) -> Result<()> {
+    let line = match answer {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(message) => json!({ "id": id, "error": { "message": message } }),
+    };
+    let mut out = out.lock().unwrap();
+    writeln!(out, "{line}")?;
+    out.flush()?;
+    Ok(())
+}
+
+fn summary(report: &Report) -> Value {
+    json!({
+        "root": report.root.to_string_lossy(),
+        "total_size": report.total_size,
+        "total_files": report.total_files,
+        "total_dirs": report.total_dirs,
+        "entries": report.entries.len(),
+        "complete": report.incomplete.is_none(),
+        "incomplete_reason": report.incomplete,
+    })
+}
+
+fn top(report: Option<&Report>, params: &Value) -> Result<Value> {
+    let Some(report) = report else {
+        bail!("no scan has finished yet");
+    };
+    let n = params.get("n").and_then(Value::as_u64).unwrap_or(10) as usize;
+    let under = params.get("under").and_then(Value::as_str).map(Path::new);
+    let entries: Vec<Value> = report
+        .entries
+        .iter()
+        .filter(|(name, _)| under.is_none_or(|under| name.parent() == Some(under)))
+        .take(n)
+        .map(|(name, stats)| {
+            json!({
+                "name": display_name(name),
+                "size": stats.size,
+                "file_count": stats.file_count,
+            })
+        })
+        .collect();
+    Ok(json!({ "entries": entries }))
+}
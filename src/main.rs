@@ -1,111 +1,946 @@
 use anyhow::{bail, Result};
-use clap::Parser;
-use humansize::{format_size, BINARY};
-use jwalk::WalkDir;
-use serde::Serialize;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use jwalk::{Parallelism, WalkDir};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+mod actions;
+mod backup;
+mod bench;
+mod compare;
+mod config;
+mod filter;
+mod forecast;
+mod growth;
+mod init;
+mod k8s;
+mod logging;
+mod machine;
+mod manifest;
+mod merge;
+mod pager;
+mod preset;
+mod priority;
+mod progress;
+mod remote;
+mod sketch;
+mod stale;
+
+use diskhound::{
+    breakdown, btrfs, budget, dupes, filesystem, group, hardlinks, histogram, i18n, icons, import,
+    largest, locale, mounts, output, parse_human_size, quota, readcheck, similar, snapshot,
+    throttle, timings, tree, zfs,
+};
+
+use breakdown::{Breakdown, Shares};
+use dupes::Candidates;
+use group::GroupBy;
+use hardlinks::Hardlinks;
+use histogram::Histogram;
+use largest::LargestByExt;
+use output::{
+    DirStats, FileSizes, Format, ListedFile, Oversized, PercentOf, Report, SortBy, SpecialFiles,
+    SpecialPolicy,
+};
+use preset::Preset;
+use priority::IoClass;
+use progress::{Progress, ProgressMode};
+use readcheck::Unreadable;
+use sketch::Quantiles;
+use throttle::RateLimiter;
+
+/// What to do when the walk exceeds `--max-entries`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum OnLimit {
+    /// Exit with an error and no report
+    Abort,
+    /// Stop walking and report what was counted so far, marked incomplete
+    Stop,
+}
+
+/// What a symlink adds to the totals, for `--count-symlinks`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum CountSymlinks {
+    /// The file it points to, as a backup that follows links would copy
+    Targets,
+    /// The link itself, the few bytes it takes up on disk
+    Links,
+    /// Nothing
+    Skip,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Scan two directories and show their entries side by side with size
+    /// deltas, e.g. to check an rsync mirror
+    Compare { left: PathBuf, right: PathBuf },
+    /// Combine JSON reports from several hosts or mounts into one, summing
+    /// entries by name and showing each source's share; --min-size applies
+    /// to the sums, while walk filters like --exclude apply when the reports
+    /// are scanned
+    Merge {
+        #[arg(required = true)]
+        reports: Vec<PathBuf>,
+    },
+    /// Show how many files and bytes fall in each file size range, from under
+    /// 1 KiB to over 1 GiB
+    Hist {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// List the largest files of each of the `--top` extensions by total
+    /// size, to find what to delete within a dominant file type
+    ExtFiles {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Files to list per extension
+        #[arg(long, default_value_t = 5)]
+        per_ext: usize,
+    },
+    /// Rank entries by size times the age of their newest file, putting
+    /// large, long-untouched data first
+    Stale {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Exponent applied to the size; raise it to favor big entries
+        #[arg(long, default_value_t = 1.0)]
+        size_weight: f64,
+        /// Exponent applied to the age in days; raise it to favor old entries
+        #[arg(long, default_value_t = 1.0)]
+        age_weight: f64,
+    },
+    /// Render a report saved with `--format json` again with different
+    /// display options, without rescanning
+    Render { report: PathBuf },
+    /// Report on a dump written by another tool, e.g. `du` or `ncdu -o`,
+    /// instead of scanning
+    Import {
+        #[arg(long)]
+        from: import::Source,
+        dump: PathBuf,
+    },
+    /// Predict when filesystems fill up from the usage recorded in saved
+    /// JSON reports plus a reading taken now
+    Forecast {
+        #[arg(required = true)]
+        reports: Vec<PathBuf>,
+    },
+    /// List mounted filesystems with their size, free space, inode use, and
+    /// type, like a richer `df`
+    Fs {
+        /// Scan the filesystem listed at this number instead
+        #[arg(long, value_name = "N")]
+        scan: Option<usize>,
+    },
+    /// Write a checksum manifest of a tree, or check a tree against one
+    Manifest {
+        #[command(subcommand)]
+        action: ManifestAction,
+    },
+    /// Time full scans of a directory with different --walk-threads settings
+    /// to find the fastest for its storage
+    Bench {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Thread counts to try, comma-separated (default: 1, then doubling up
+        /// to one per CPU)
+        #[arg(long, value_delimiter = ',')]
+        threads: Vec<usize>,
+        /// Timed scans per thread count
+        #[arg(long, default_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+        runs: u32,
+    },
+    /// Show a restic or borg repository's size, deduplication ratio, and
+    /// snapshots by the space each one costs
+    Backup {
+        #[arg(long, value_enum)]
+        tool: backup::Tool,
+        /// The repository, as the tool takes it (a path or a URL like
+        /// sftp:host:/srv/restic)
+        repo: String,
+    },
+    /// Build a config file by answering a few questions: default excludes,
+    /// presets, output format, and number of entries
+    Init,
+    /// Scan a Kubernetes PersistentVolumeClaim by copying diskhound into a
+    /// running pod that mounts it (through kubectl) and reporting here
+    K8s {
+        /// The claim's name
+        #[arg(long)]
+        pvc: String,
+        /// Defaults to kubectl's current namespace
+        #[arg(long, short)]
+        namespace: Option<String>,
+        /// A static diskhound for the pod's architecture (default: this
+        /// executable)
+        #[arg(long)]
+        binary: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum ManifestAction {
+    /// Hash every file and write its path, size, modification time, and
+    /// blake3 hash, to --output or stdout
+    Create {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Walk the manifest's tree again and report added, removed, resized,
+    /// and changed files, and files whose content couldn't be checked;
+    /// exits with an error if anything differs or went unchecked
+    Verify { manifest: PathBuf },
+}
 
 #[derive(Parser)]
 #[command(name = "diskhound")]
 #[command(about = "Find the largest subdirectories in a given path")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Directory to scan (defaults to current directory)
     #[arg(default_value = ".")]
     path: PathBuf,
 
     /// Number of top directories to show
-    #[arg(long, default_value = "10")]
+    #[arg(long, default_value = "10", global = true)]
     top: usize,
 
     /// Exclude directories by name (repeatable)
-    #[arg(long, action = clap::ArgAction::Append)]
-    exclude: Vec<String>,
+    #[arg(long, action = clap::ArgAction::Append, global = true)]
+    exclude: Vec<OsString>,
+
+    /// Skip version control directories (.git, .hg, .svn, .jj), like
+    /// --exclude for each
+    #[arg(long, global = true)]
+    no_vcs: bool,
+
+    /// Exclude patterns from a file, one per line in .gitignore style
+    /// (`#` comments, `!` to re-include, trailing `/` for directories);
+    /// repeatable
+    #[arg(long, action = clap::ArgAction::Append, global = true, value_name = "FILE")]
+    exclude_from: Vec<PathBuf>,
+
+    /// Include and exclude rules in rsync's filter syntax (`- PATTERN`,
+    /// `+ PATTERN`, a leading `/` to anchor at the scanned root), so a
+    /// backup's filter file can be reused as is
+    #[arg(long, global = true, value_name = "FILE")]
+    filter_from: Option<PathBuf>,
+
+    /// Match --exclude names case-insensitively
+    #[arg(long, global = true)]
+    ignore_case: bool,
+
+    /// Skip a named set of directories (repeatable): node (node_modules and
+    /// build caches), rust (target), python (virtualenvs and tool caches),
+    /// macos-system (Spotlight and Trash metadata), system-scan (/proc, /sys,
+    /// /dev, /run, or pagefile.sys and friends on Windows, when scanning a
+    /// filesystem root), or one defined under [presets] in the config file
+    #[arg(long, action = clap::ArgAction::Append, global = true)]
+    preset: Vec<String>,
+
+    /// Apply presets per project: skip target/ beside a Cargo.toml,
+    /// node_modules/ beside a package.json, and virtualenvs beside a
+    /// pyproject.toml, setup.py, or requirements.txt
+    #[arg(long, global = true)]
+    auto_exclude: bool,
+
+    /// Skip directories on these filesystem types, comma-separated as the
+    /// mount table names them, e.g. nfs,cifs,fuse.sshfs (Linux)
+    #[arg(long, value_delimiter = ',', global = true)]
+    skip_fs_types: Vec<String>,
+
+    /// Skip network filesystems: NFS, SMB/CIFS, sshfs, and the like (Linux)
+    #[arg(long, global = true)]
+    local_only: bool,
+
+    /// Skip dotfiles and dot-directories (and hidden-attribute entries on
+    /// Windows) along with everything below them
+    #[arg(long, conflicts_with = "hidden_only", global = true)]
+    skip_hidden: bool,
 
-    /// Grouping depth (1 = immediate children)
-    #[arg(long, default_value = "1")]
+    /// Count only hidden entries and everything below hidden directories
+    #[arg(long, global = true)]
+    hidden_only: bool,
+
+    /// Grouping depth (1 = immediate children, "all" or 0 = every directory)
+    #[arg(long, default_value = "1", value_parser = parse_depth, global = true)]
     depth: usize,
 
+    /// Group bytes by containing directory (dir), extension (ext), owner,
+    /// time since modification (age), nesting depth (depth), or modification
+    /// year or month (mtime:year, mtime:month), enclosing project (project),
+    /// or config file tag (tag)
+    #[arg(long, default_value = "dir", value_parser = group::parse, global = true)]
+    group_by: GroupBy,
+
+    /// Attribute all usage to the outermost enclosing project, at any depth;
+    /// a project root holds .git, Cargo.toml, package.json, pyproject.toml,
+    /// or .diskhound-project (same as --group-by project)
+    #[arg(long, conflicts_with = "group_by", global = true)]
+    by_project: bool,
+
+    /// Roll usage up by the tags given to directories in the config file's
+    /// [tags] section; the innermost tagged directory wins (same as
+    /// --group-by tag)
+    #[arg(long, conflicts_with_all = ["group_by", "by_project"], global = true)]
+    by_tag: bool,
+
+    /// Config file to read instead of diskhound/config in the user's
+    /// configuration directory (~/.config on Linux)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Count each directory's own entry size (e.g. its ext4 directory blocks)
+    /// toward that directory and the totals
+    #[arg(long, global = true)]
+    dir_sizes: bool,
+
+    /// Count each symlink as the file it points to, as the link itself, or
+    /// not at all; links are never followed into directories
+    #[arg(long, value_enum, default_value = "skip", global = true)]
+    count_symlinks: CountSymlinks,
+
+    /// Count sockets, FIFOs, and device nodes like files, skip them, or
+    /// list them apart from the totals; how many were found is reported
+    /// either way
+    #[arg(long, value_enum, default_value = "skip", global = true)]
+    special_files: SpecialPolicy,
+
+    /// Leave files larger than this out of all totals and list them separately
+    /// (e.g. 10GB)
+    #[arg(long, global = true)]
+    skip_files_over: Option<String>,
+
     /// Filter directories below this size (e.g. 100MB, 1.5GB, 500K)
-    #[arg(long)]
+    #[arg(long, global = true)]
     min_size: Option<String>,
 
-    /// Output results as JSON
-    #[arg(long)]
+    /// What entries are ranked by before --top applies
+    #[arg(long, value_enum, default_value = "size", global = true)]
+    sort: SortBy,
+
+    /// Show each entry's mean file size (implied by --sort mean)
+    #[arg(long, global = true)]
+    mean_size: bool,
+
+    /// What percentages are relative to
+    #[arg(long, value_enum, default_value = "total", global = true)]
+    percent_of: PercentOf,
+
+    /// Prefix table rows with a type icon (folder, media, archive, code, ...);
+    /// `--icons=nerd` for Nerd Fonts glyphs
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "emoji",
+        global = true
+    )]
+    icons: Option<icons::Icons>,
+
+    /// Show each entry's file size distribution as a sparkline, from under
+    /// 1 KiB on the left to over 1 GiB on the right
+    #[arg(long, global = true)]
+    sparkline: bool,
+
+    /// Annotate each entry with a secondary breakdown of its bytes
+    #[arg(long, value_enum, global = true)]
+    breakdown: Option<Breakdown>,
+
+    /// Show how much of each entry is shared with other entries through
+    /// hardlinks (e.g. snapshot-style backups)
+    #[arg(long, global = true)]
+    hardlinks: bool,
+
+    /// On Btrfs, read quota groups for each subvolume's exclusive and
+    /// referenced bytes (needs quotas enabled, the btrfs tool, and root)
+    #[arg(long, global = true)]
+    btrfs_qgroups: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "table", global = true)]
+    format: Format,
+
+    /// Output results as JSON (same as --format json)
+    #[arg(long, global = true)]
     json: bool,
+
+    /// Emit one row per file instead of per directory (parquet, arrow, and tsv
+    /// only)
+    #[arg(long, global = true)]
+    files: bool,
+
+    /// Language and number style for the table and progress, e.g. en_US
+    /// (1,234.5) or de_DE (1.234,5); defaults to LC_ALL, then LC_NUMERIC or
+    /// LC_MESSAGES, then LANG
+    #[arg(long, global = true)]
+    locale: Option<String>,
+
+    /// Leave out the header row of tsv output
+    #[arg(long, global = true)]
+    no_header: bool,
+
+    /// Print the JSON Schema of the --json output and exit
+    #[arg(long, global = true)]
+    schema: bool,
+
+    /// Serve editor plugins and other long-lived clients: read JSON requests
+    /// (scan, top, cancel) line by line on stdin and answer each on stdout
+    #[arg(long, conflicts_with = "schema")]
+    machine: bool,
+
+    /// Write the report to this file instead of stdout (written atomically)
+    #[arg(long, short, global = true)]
+    output: Option<PathBuf>,
+
+    /// Never pipe long output through $PAGER
+    #[arg(long, global = true)]
+    no_pager: bool,
+
+    /// Open the Nth largest directory (default 1) in the file manager
+    #[arg(long, value_name = "RANK", num_args = 0..=1, default_missing_value = "1", global = true)]
+    reveal: Option<usize>,
+
+    /// Start a shell in the Nth largest directory (default 1) after the report
+    #[arg(long, value_name = "RANK", num_args = 0..=1, default_missing_value = "1", global = true)]
+    shell: Option<usize>,
+
+    /// Copy the reported directory paths (or only the Nth largest) to the clipboard
+    #[arg(long, value_name = "RANK", num_args = 0..=1, global = true)]
+    copy: Option<Option<usize>>,
+
+    /// Report groups of files with identical content
+    #[arg(long, global = true)]
+    dupes: bool,
+
+    /// With --dupes, also report whole directory trees with identical
+    /// contents, e.g. copies of a project folder
+    #[arg(long, requires = "dupes", global = true)]
+    dupe_trees: bool,
+
+    /// Report pairs of files over 1 MiB that are mostly identical, e.g.
+    /// successive database dumps, with what delta storage would save
+    #[arg(long, global = true)]
+    similar: bool,
+
+    /// Minimum similarity in percent for --similar
+    #[arg(long, default_value_t = 50.0, global = true)]
+    similar_threshold: f64,
+
+    /// Read the first block of every file and report I/O errors per entry,
+    /// a quick sign of a failing disk
+    #[arg(long, global = true)]
+    check_read: bool,
+
+    /// Saved JSON report to compare against for --alert-growth, e.g. the
+    /// previous scheduled scan
+    #[arg(long, global = true)]
+    baseline: Option<PathBuf>,
+
+    /// Count only files created or modified since this saved JSON report was
+    /// scanned: what an incremental backup would copy, by directory
+    #[arg(long, global = true, value_name = "REPORT")]
+    changed_since: Option<PathBuf>,
+
+    /// Fail when an entry grew faster than this since --baseline
+    /// (e.g. 5GB/day, 500MB/hour)
+    #[arg(long, requires = "baseline", global = true)]
+    alert_growth: Option<String>,
+
+    /// Size budget for a path relative to the scanned root (or `.` for the
+    /// whole scan), e.g. target=2GB; diskhound fails when one is exceeded.
+    /// Repeatable, and added to the config file's [budgets]; unlike those,
+    /// the path must exist in the scan
+    #[arg(long, value_name = "PATH=SIZE", global = true)]
+    budget: Vec<String>,
+
+    /// Shell command to run on --alert-growth alerts, with them as JSON on
+    /// stdin (e.g. a curl call to a webhook)
+    #[arg(long, requires = "alert_growth", global = true)]
+    alert_command: Option<String>,
+
+    /// Stop the walk after this long and report what was counted so far
+    /// (e.g. 90s, 10m, 1h). A directory read that hangs, like on a stalled
+    /// network mount, still holds the walk until it returns
+    #[arg(long, value_parser = parse_duration, global = true)]
+    timeout: Option<Duration>,
+
+    /// Entry budget for the walk, protecting automation from pathological
+    /// trees
+    #[arg(long, global = true)]
+    max_entries: Option<u64>,
+
+    /// What to do when --max-entries is exceeded
+    #[arg(
+        long,
+        value_enum,
+        default_value = "abort",
+        requires = "max_entries",
+        global = true
+    )]
+    on_max_entries: OnLimit,
+
+    /// Limit filesystem operations (directory listings and stats) per second,
+    /// e.g. 200 or 200/s, to go easy on network shares
+    #[arg(long, value_parser = parse_ops_rate, global = true)]
+    throttle: Option<u64>,
+
+    /// Run at a lower CPU priority (nice value, default 10); on Windows, switch
+    /// to background mode
+    #[arg(long, num_args = 0..=1, default_missing_value = "10",
+          allow_negative_numbers = true, global = true)]
+    nice: Option<i32>,
+
+    /// I/O scheduling class (Linux); on Windows, switch to background mode
+    #[arg(long, value_enum, global = true)]
+    ionice: Option<IoClass>,
+
+    /// Worker threads for the directory walk (0 = one per CPU, 1 = serial)
+    #[arg(long, default_value = "0", global = true)]
+    walk_threads: usize,
+
+    /// Concurrent connections when scanning an sftp://, ftp://, dav://, or
+    /// davs:// URL (rclone checkers for rclone:remote:path)
+    #[arg(long, default_value = "4", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    connections: u32,
+
+    /// Worker threads for duplicate hashing and --check-read (0 = one per CPU)
+    #[arg(long, default_value = "0", global = true)]
+    hash_threads: usize,
+
+    /// Limit duplicate hashing and --check-read reads per second (e.g. 50MB)
+    #[arg(long, global = true)]
+    hash_rate: Option<String>,
+
+    /// After the report, print to stderr how long setup, the walk (and its
+    /// stat calls), aggregation, and rendering took, entries per second, and
+    /// peak memory
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Log more detail to stderr (-v: errors and timings, -vv: skipped entries)
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet", global = true)]
+    verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Write log messages to this file instead of stderr
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+
+    /// Disable the progress display on stderr
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// Emit progress as JSON lines on stderr (for wrappers and scripts)
+    #[arg(long, conflicts_with = "no_progress", global = true)]
+    progress_json: bool,
 }
 
-struct DirStats {
-    size: u64,
-    file_count: u64,
+/// `socket`, `fifo`, `block device`, or `character device`; `None` for
+/// everything else.
+#[cfg(unix)]
+fn special_kind(file_type: std::fs::FileType) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_socket() {
+        Some("socket")
+    } else if file_type.is_fifo() {
+        Some("fifo")
+    } else if file_type.is_block_device() {
+        Some("block device")
+    } else if file_type.is_char_device() {
+        Some("character device")
+    } else {
+        None
+    }
 }
 
-#[derive(Serialize)]
-struct JsonOutput {
-    directories: Vec<JsonDirEntry>,
-    summary: JsonSummary,
+#[cfg(not(unix))]
+fn special_kind(_file_type: std::fs::FileType) -> Option<&'static str> {
+    None
 }
 
-#[derive(Serialize)]
-struct JsonDirEntry {
-    name: String,
-    size: u64,
-    size_human: String,
-    file_count: u64,
-    percentage: f64,
+/// Whether a file's data or inode changed after `since`. The inode change
+/// time catches files moved or restored with old modification times, which
+/// backup tools copy again too.
+fn changed_after(metadata: &std::fs::Metadata, since: SystemTime) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let ctime = UNIX_EPOCH
+            + Duration::new(metadata.ctime().max(0) as u64, metadata.ctime_nsec() as u32);
+        if ctime > since {
+            return true;
+        }
+    }
+    metadata.modified().is_ok_and(|mtime| mtime > since)
 }
 
-#[derive(Serialize)]
-struct JsonSummary {
-    total_size: u64,
-    total_size_human: String,
-    total_files: u64,
-    total_dirs: u64,
-    shown: usize,
+/// Dotfiles everywhere, plus entries with the hidden attribute on Windows.
+fn is_hidden(entry: &jwalk::DirEntry<((), ())>) -> bool {
+    if entry.file_name.as_encoded_bytes().first() == Some(&b'.') {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = entry.metadata() {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+    false
 }
 
-fn parse_human_size(s: &str) -> Result<u64> {
-    let s = s.trim();
-    let (num_part, suffix) = match s.find(|c: char| c.is_ascii_alphabetic()) {
-        Some(i) => (&s[..i], s[i..].to_uppercase()),
-        None => bail!("invalid size format: {s}"),
-    };
-    let num: f64 = num_part
+/// `all` is spelled as 0, meaning no depth limit.
+fn parse_depth(s: &str) -> std::result::Result<usize, String> {
+    if s.eq_ignore_ascii_case("all") {
+        return Ok(0);
+    }
+    s.parse()
+        .map_err(|_| format!("expected a number or \"all\", got {s:?}"))
+}
+
+fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let (num, unit) = s.split_at(s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len()));
+    let secs: f64 = num
         .parse()
-        .map_err(|_| anyhow::anyhow!("invalid number in size: {s}"))?;
-    let multiplier: u64 = match suffix.as_str() {
-        "B" => 1,
-        "K" | "KB" | "KIB" => 1024,
-        "M" | "MB" | "MIB" => 1024 * 1024,
-        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
-        "T" | "TB" | "TIB" => 1024 * 1024 * 1024 * 1024,
-        _ => bail!("unknown size suffix: {suffix}"),
+        .map_err(|_| format!("expected a duration like 90s, 10m, or 1h, got {s:?}"))?;
+    let scale = match unit {
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        _ => {
+            return Err(format!(
+                "unknown duration unit {unit:?} (use s, m, h, or d)"
+            ))
+        }
     };
-    Ok((num * multiplier as f64) as u64)
+    Duration::try_from_secs_f64(secs * scale).map_err(|e| e.to_string())
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn parse_ops_rate(s: &str) -> std::result::Result<u64, String> {
+    s.strip_suffix("/s")
+        .unwrap_or(s)
+        .parse()
+        .map_err(|_| format!("expected operations per second like 200 or 200/s, got {s:?}"))
+}
+
+/// Absolute path of the directory at 1-based `rank` in the report.
+fn ranked_dir(root: &Path, report: &Report, rank: usize) -> Result<PathBuf> {
+    match rank.checked_sub(1).and_then(|i| report.entries.get(i)) {
+        Some((name, _)) if output::is_root_files(name) => Ok(root.to_path_buf()),
+        Some((name, _)) => Ok(root.join(name)),
+        None => bail!(
+            "no directory at rank {rank} (report has {})",
+            report.entries.len()
+        ),
+    }
+}
+
+/// Keeps the newest of the times seen for `key`.
+fn newest_modified(newest: &mut HashMap<PathBuf, SystemTime>, key: PathBuf, time: SystemTime) {
+    let entry = newest.entry(key).or_insert(time);
+    *entry = (*entry).max(time);
+}
 
+/// Walks `root` and aggregates it into a report keeping the `top` largest
+/// entries.
+fn scan(args: &Args, root: &Path, format: Format, top: usize) -> Result<Report> {
+    scan_on(args, root, format, top, None)
+}
+
+/// Scans `roots` at the same time, their walks sharing one pool of
+/// `--walk-threads` workers: separate roots usually sit on separate devices,
+/// so walking them side by side is close to free. Reports come back in the
+/// order of `roots`.
+fn scan_concurrently(
+    args: &Args,
+    roots: &[&Path],
+    format: Format,
+    top: usize,
+) -> Result<Vec<Report>> {
+    if args.walk_threads == 1 || roots.len() < 2 {
+        return roots
+            .iter()
+            .map(|root| scan(args, root, format, top))
+            .collect();
+    }
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(args.walk_threads)
+            .build()?,
+    );
+    std::thread::scope(|s| {
+        let scans: Vec<_> = roots
+            .iter()
+            .map(|root| {
+                let pool = Arc::clone(&pool);
+                s.spawn(move || scan_on(args, root, format, top, Some(pool)))
+            })
+            .collect();
+        scans
+            .into_iter()
+            .map(|scan| {
+                scan.join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err))
+            })
+            .collect()
+    })
+}
+
+/// Scans `root`, walking it on `pool` if given (shared with other scans
+/// running at the same time) or else as `--walk-threads` says.
+fn scan_on(
+    args: &Args,
+    root: &Path,
+    format: Format,
+    top: usize,
+    pool: Option<Arc<rayon::ThreadPool>>,
+) -> Result<Report> {
+    let scan_started = Instant::now();
     let min_size_bytes = match &args.min_size {
         Some(s) => Some(parse_human_size(s)?),
         None => None,
     };
+    let skip_files_over = match &args.skip_files_over {
+        Some(s) => Some(parse_human_size(s)?),
+        None => None,
+    };
+    let changed_since = match &args.changed_since {
+        Some(path) => Some(snapshot::taken(&snapshot::load(path)?, path)?),
+        None => None,
+    };
+    let hash_rate = match &args.hash_rate {
+        Some(s) => Some(parse_human_size(s)?),
+        None => None,
+    };
 
-    let exclude = args.exclude.clone();
-    let mut dir_sizes: HashMap<String, DirStats> = HashMap::new();
+    let ignore_case = args.ignore_case;
+    let fold = move |name: &std::ffi::OsStr| -> OsString {
+        if ignore_case {
+            name.to_string_lossy().to_lowercase().into()
+        } else {
+            name.to_os_string()
+        }
+    };
+    let config = config::load(args.config.as_deref(), parse_human_size)?;
+    let mut exclude: Vec<OsString> = args.exclude.iter().map(|name| fold(name)).collect();
+    let mut root_presets = Vec::new();
+    for name in &args.preset {
+        if let Some((_, names)) = config.presets.iter().find(|(n, _)| n == name) {
+            exclude.extend(names.iter().map(|n| fold(n.as_ref())));
+            continue;
+        }
+        let Some(preset) = Preset::from_name(name) else {
+            let built_in: Vec<&str> = preset::BUILT_IN.iter().map(|(n, _)| *n).collect();
+            bail!(
+                "unknown preset {name:?}; built in are {}, and more can be defined under [presets] in the config file",
+                built_in.join(", ")
+            );
+        };
+        exclude.extend(preset.excludes().iter().map(|n| fold(n.as_ref())));
+        root_presets.push(preset);
+    }
+    if args.no_vcs {
+        exclude.extend(preset::VCS_DIRECTORIES.iter().map(|n| fold(n.as_ref())));
+    }
+    let skip_hidden = args.skip_hidden;
+    let canonical_root = std::fs::canonicalize(root).ok();
+    let bind_mounts = canonical_root
+        .as_deref()
+        .map(|root| mounts::duplicate_mounts(&mounts::mountinfo(), root))
+        .unwrap_or_default();
+    for mount in &bind_mounts {
+        info!(
+            "skipping {}: same data as {}",
+            root.join(&mount.path).display(),
+            mount.same_as.display()
+        );
+    }
+    let mut skip_mounts: HashSet<PathBuf> = bind_mounts.iter().map(|m| m.path.clone()).collect();
+    let mut skip_types = args.skip_fs_types.clone();
+    if args.local_only {
+        skip_types.extend(mounts::NETWORK_TYPES.iter().map(|t| t.to_string()));
+    }
+    if let (false, Some(canonical)) = (skip_types.is_empty(), &canonical_root) {
+        for (path, fstype) in mounts::of_types(canonical, &skip_types) {
+            if path.as_os_str().is_empty() {
+                bail!(
+                    "{} is on a {fstype} filesystem, which this scan skips",
+                    root.display()
+                );
+            }
+            info!(
+                "skipping {}: {fstype} filesystem",
+                root.join(&path).display()
+            );
+            skip_mounts.insert(path);
+        }
+    }
+    let mut filter = match &args.filter_from {
+        Some(path) => filter::Filter::load_rules(path)?,
+        None => filter::Filter::default(),
+    };
+    for path in &args.exclude_from {
+        filter.extend(filter::Filter::load_excludes(path)?);
+    }
+    let walk_root = root.to_path_buf();
+    // Presets only apply when the scan starts at a filesystem root.
+    let preset_root = canonical_root
+        .as_ref()
+        .is_some_and(|root| root.parent().is_none())
+        .then(|| (root_presets, root.to_path_buf()));
+    let mut dir_sizes: HashMap<PathBuf, DirStats> = HashMap::new();
     let mut total_size: u64 = 0;
     let mut total_files: u64 = 0;
     let mut total_dirs: u64 = 0;
+    let mut candidates = Candidates::default();
+    let mut similar_candidates = Candidates::default();
+    let mut read_checks: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let list_files = args.files
+        || format.needs_files()
+        || matches!(args.command, Some(Command::Manifest { .. }));
+    let mut files: Vec<ListedFile> = Vec::new();
+    let mut oversized: Vec<(PathBuf, u64)> = Vec::new();
+    let mut special_counts: BTreeMap<&'static str, u64> = BTreeMap::new();
+    let mut special_listed: Vec<(PathBuf, &'static str)> = Vec::new();
+    let mut root_files = DirStats {
+        size: 0,
+        file_count: 0,
+    };
+    // Hidden directories seen so far, so `--hidden-only` can include their
+    // contents. Parents are always visited before their children.
+    let mut hidden_dirs: HashSet<PathBuf> = HashSet::new();
+    let mut hardlinks = args.hardlinks.then(Hardlinks::default);
+    let mut extensions = (args.breakdown == Some(Breakdown::Ext)).then(Shares::default);
+    let mut histograms = args.sparkline.then(HashMap::<PathBuf, Histogram>::new);
+    let mut spread = filesystem::Spread::default();
+    // Subvolumes below the root, parents before the ones nested in them.
+    let on_btrfs = canonical_root.as_deref().is_some_and(btrfs::contains);
+    let mut subvolumes: Vec<btrfs::Subvolume> = Vec::new();
+    let mut datasets = canonical_root
+        .as_deref()
+        .map(zfs::datasets)
+        .unwrap_or_default();
+    let mut size_histogram = Histogram::default();
+    let mut quantiles = Quantiles::default();
+    let mut largest: Option<(PathBuf, u64)> = None;
+    let mut largest_by_ext = match &args.command {
+        Some(Command::ExtFiles { per_ext, .. }) => Some(LargestByExt::new(*per_ext)),
+        _ => None,
+    };
+    // Every directory and file, for `--dupe-trees`; `None` sizes are files
+    // whose metadata couldn't be read.
+    let mut tree_dirs: Vec<PathBuf> = Vec::new();
+    let mut tree_files: Vec<(PathBuf, Option<u64>)> = Vec::new();
+    let mut modified = matches!(args.command, Some(Command::Stale { .. }))
+        .then(HashMap::<PathBuf, SystemTime>::new);
 
-    let walker = WalkDir::new(&args.path)
+    let ops_limiter = Arc::new(RateLimiter::new(args.throttle));
+    let walk_limiter = Arc::clone(&ops_limiter);
+    // Directories skipped by --auto-exclude, per preset.
+    let auto_excluded: Arc<Mutex<HashMap<Preset, u64>>> = Arc::default();
+    let auto_exclude = args.auto_exclude.then(|| Arc::clone(&auto_excluded));
+    // Scans sharing a pool would draw over each other's progress line.
+    let shared_pool = pool.is_some();
+    let parallelism = match (pool, args.walk_threads) {
+        (Some(pool), _) => Parallelism::RayonExistingPool {
+            pool,
+            busy_timeout: Some(Duration::from_secs(1)),
+        },
+        (None, 0) => Parallelism::RayonDefaultPool {
+            busy_timeout: Duration::from_secs(1),
+        },
+        (None, 1) => Parallelism::Serial,
+        (None, n) => Parallelism::RayonNewPool(n),
+    };
+
+    // The walk starts when it's first iterated, right after this.
+    let deadline = args.timeout.map(|timeout| Instant::now() + timeout);
+    let pruned_at_deadline = Arc::new(AtomicBool::new(false));
+    let pruned = Arc::clone(&pruned_at_deadline);
+    let walker = WalkDir::new(root)
+        .parallelism(parallelism)
         .follow_links(false)
+        // Hidden entries are counted unless --skip-hidden prunes them below.
+        .skip_hidden(false)
         .process_read_dir(move |_depth, _path, _state, children| {
+            // Past the timeout, stop descending so the workers wind down
+            // instead of listing the rest of the tree for nothing.
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                if !children.is_empty() {
+                    pruned.store(true, Ordering::Relaxed);
+                }
+                children.clear();
+                return;
+            }
+            walk_limiter.acquire(1);
+            let projects: Vec<Preset> = match &auto_exclude {
+                Some(_) => children
+                    .iter()
+                    .flatten()
+                    .filter(|entry| !entry.file_type().is_dir())
+                    .filter_map(|entry| Preset::for_marker(entry.file_name.to_str()?))
+                    .collect(),
+                None => Vec::new(),
+            };
             children.retain(|entry_result| {
                 entry_result.as_ref().map_or(true, |entry| {
-                    if entry.file_type().is_dir() {
-                        let name = entry.file_name.to_string_lossy().to_string();
-                        !exclude.contains(&name)
+                    if skip_hidden && is_hidden(entry) {
+                        debug!("skipped hidden {}", entry.path().display());
+                        false
+                    } else if preset_root.as_ref().is_some_and(|(presets, root)| {
+                        let path = entry.path();
+                        path.strip_prefix(root)
+                            .is_ok_and(|rel| presets.iter().any(|p| p.skips(rel)))
+                    }) {
+                        debug!("skipped by preset {}", entry.path().display());
+                        false
+                    } else if let Some(preset) = projects.iter().find(|p| {
+                        entry.file_type().is_dir()
+                            && entry
+                                .file_name
+                                .to_str()
+                                .is_some_and(|name| p.excludes().contains(&name))
+                    }) {
+                        debug!("auto-excluded {}", entry.path().display());
+                        if let Some(counts) = &auto_exclude {
+                            *counts.lock().unwrap().entry(*preset).or_default() += 1;
+                        }
+                        false
+                    } else if entry.file_type().is_dir() && !skip_mounts.is_empty() && {
+                        let path = entry.path();
+                        path.strip_prefix(&walk_root)
+                            .is_ok_and(|rel| skip_mounts.contains(rel))
+                    } {
+                        debug!("skipped mount {}", entry.path().display());
+                        false
+                    } else if entry
+                        .path()
+                        .strip_prefix(&walk_root)
+                        .is_ok_and(|rel| filter.excludes(rel, entry.file_type().is_dir()))
+                    {
+                        debug!("filtered out {}", entry.path().display());
+                        false
+                    } else if entry.file_type().is_dir() {
+                        let keep = !exclude.contains(&fold(&entry.file_name));
+                        if !keep {
+                            debug!("excluded {}", entry.path().display());
+                        }
+                        keep
                     } else {
                         true
                     }
@@ -113,55 +948,297 @@ fn main() -> Result<()> {
             });
         });
 
-    for entry in walker.into_iter().filter_map(|e| e.ok()) {
+    let progress_mode = if args.progress_json {
+        ProgressMode::Json
+    } else if args.no_progress || shared_pool {
+        ProgressMode::Off
+    } else {
+        ProgressMode::Human
+    };
+    let mut progress = Progress::new(root, progress_mode);
+
+    let max_depth = if args.depth == 0 {
+        usize::MAX
+    } else {
+        args.depth
+    };
+    let mut budgets = Vec::new();
+    for budget in &args.budget {
+        budgets.push(budget::parse(budget, parse_human_size)?);
+    }
+    // Tagged and budgeted absolute paths are canonical, like the root they're
+    // compared with.
+    let absolute_root = canonical_root.as_deref().unwrap_or(root);
+    let budgets = budget::resolve(absolute_root, budgets, config.budgets)?;
+    let mut budget_sizes = vec![0u64; budgets.len()];
+    let tags = if args.group_by == GroupBy::Tag {
+        if config.tags.is_empty() {
+            bail!(
+                "--by-tag needs tagged paths in the config file, e.g. `/srv/ml-data = team:vision` under [tags]"
+            );
+        }
+        config.tags
+    } else {
+        Vec::new()
+    };
+    let keyer = args.group_by.keyer(max_depth, absolute_root, &tags);
+
+    let mut incomplete = None;
+    let mut entries_seen: u64 = 0;
+    let mut stat_time = Duration::ZERO;
+    let walk_started = Instant::now();
+    let scanned_at = SystemTime::now();
+    let filesystem = filesystem::usage(root);
+    let timed_out = |incomplete: &mut Option<String>| {
+        let reason = format!(
+            "stopped at the {:?} timeout",
+            args.timeout.unwrap_or_default()
+        );
+        warn!("{reason}; results are partial");
+        *incomplete = Some(reason);
+    };
+    for entry in walker {
+        if args.timeout.is_some_and(|t| walk_started.elapsed() >= t) {
+            timed_out(&mut incomplete);
+            break;
+        }
+        if machine::cancelled() {
+            warn!("scan cancelled; results are partial");
+            incomplete = Some("cancelled".to_string());
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                info!("skipped: {err}");
+                continue;
+            }
+        };
+        progress.tick();
+        entries_seen += 1;
+        if let Some(max) = args.max_entries.filter(|&max| entries_seen > max) {
+            if args.on_max_entries == OnLimit::Abort {
+                progress.finish(false);
+                bail!("walk exceeded --max-entries {max}");
+            }
+            let reason = format!("stopped at the --max-entries limit of {max}");
+            warn!("{reason}; results are partial");
+            incomplete = Some(reason);
+            break;
+        }
         let path = entry.path();
-        if path == args.path.as_path() {
+        if path == root {
             continue;
         }
 
-        let relative = match path.strip_prefix(&args.path) {
+        let relative = match path.strip_prefix(root) {
             Ok(r) => r,
             Err(_) => continue,
         };
 
-        let components: Vec<_> = relative
-            .components()
-            .map(|c| c.as_os_str().to_string_lossy().to_string())
-            .collect();
+        if args.hidden_only {
+            let hidden =
+                is_hidden(&entry) || relative.parent().is_some_and(|p| hidden_dirs.contains(p));
+            if !hidden {
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                hidden_dirs.insert(relative.to_path_buf());
+            }
+        }
 
-        if entry.file_type().is_dir() {
+        let components: Vec<_> = relative.components().map(|c| c.as_os_str()).collect();
+
+        let is_dir = entry.file_type().is_dir();
+        let is_link = entry.file_type().is_symlink();
+        // Only regular files have content worth reading; a FIFO would block.
+        let regular = entry.file_type().is_file();
+        if is_dir {
             total_dirs += 1;
+            if args.dupe_trees {
+                tree_dirs.push(path.clone());
+            }
+            if let (true, Ok(metadata)) = (on_btrfs, entry.metadata()) {
+                if let Some((id, snapshot)) = btrfs::subvolume(&path, &metadata) {
+                    debug!("subvolume {}", path.display());
+                    subvolumes.push(btrfs::Subvolume {
+                        path: relative.to_path_buf(),
+                        id,
+                        snapshot,
+                        size: 0,
+                        referenced: None,
+                        exclusive: None,
+                    });
+                }
+            }
+            if !args.dir_sizes {
+                continue;
+            }
+        } else if let Some(kind) = special_kind(entry.file_type()) {
+            *special_counts.entry(kind).or_default() += 1;
+            match args.special_files {
+                SpecialPolicy::Count => {}
+                SpecialPolicy::Skip => {
+                    debug!("skipped {kind} {}", path.display());
+                    continue;
+                }
+                SpecialPolicy::List => {
+                    special_listed.push((path.clone(), kind));
+                    continue;
+                }
+            }
+        } else if !entry.file_type().is_file()
+            && (!is_link || args.count_symlinks == CountSymlinks::Skip)
+        {
+            debug!("skipped non-regular file {}", path.display());
             continue;
         }
 
-        if !entry.file_type().is_file() {
+        ops_limiter.acquire(1);
+        let stat_started = args.timings.then(Instant::now);
+        let metadata = if is_link && args.count_symlinks == CountSymlinks::Targets {
+            match std::fs::metadata(&path) {
+                Ok(m) if m.is_file() => Some(m),
+                Ok(_) => {
+                    debug!("skipped symlink to a non-file {}", path.display());
+                    continue;
+                }
+                Err(err) => {
+                    info!("skipped dangling symlink {}: {err}", path.display());
+                    continue;
+                }
+            }
+        } else {
+            match entry.metadata() {
+                Ok(m) => Some(m),
+                Err(err) => {
+                    info!("no metadata for {}: {err}", path.display());
+                    None
+                }
+            }
+        };
+        if let Some(started) = stat_started {
+            stat_time += started.elapsed();
+        }
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+        if args.dupe_trees && regular {
+            tree_files.push((path.clone(), metadata.as_ref().map(|m| m.len())));
+        }
+        if let Some(since) = changed_since {
+            if !is_dir && !metadata.as_ref().is_some_and(|m| changed_after(m, since)) {
+                continue;
+            }
+        }
+        if !is_dir && skip_files_over.is_some_and(|limit| size > limit) {
+            debug!("skipped oversized {}", path.display());
+            oversized.push((path, size));
             continue;
         }
-
-        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        progress.add_bytes(size);
         total_size += size;
-        total_files += 1;
-
-        // Root-level files don't belong to any subdirectory
-        if components.len() <= 1
-            && relative
-                .parent()
-                .is_none_or(|p| p == std::path::Path::new(""))
-        {
-            // File directly in scanned directory — count toward totals only
-            if components.len() == 1 && relative.is_file() {
-                continue;
+        for (budget, counted) in budgets.iter().zip(&mut budget_sizes) {
+            if relative.starts_with(&budget.path) {
+                *counted += size;
             }
         }
+        if let Some(subvolume) = subvolumes
+            .iter_mut()
+            .rev()
+            .find(|s| relative.starts_with(&s.path))
+        {
+            subvolume.size += size;
+        }
+        if let Some(dataset) = datasets
+            .iter_mut()
+            .rev()
+            .find(|d| relative.starts_with(&d.path))
+        {
+            dataset.size += size;
+        }
+
+        let key = keyer.key(&group::Item {
+            components: &components,
+            metadata: metadata.as_ref(),
+            is_dir,
+        });
+        if is_dir {
+            let Some(key) = key else { continue };
+            dir_sizes
+                .entry(key)
+                .or_insert(DirStats {
+                    size: 0,
+                    file_count: 0,
+                })
+                .size += size;
+            continue;
+        }
+
+        if args.check_read && regular {
+            let key = key
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(output::ROOT_FILES));
+            read_checks.push((key, path.clone()));
+        }
+        if let (true, true, Some(metadata)) = (args.dupes, regular, &metadata) {
+            candidates.add(path.clone(), metadata);
+        }
+        if let (true, true, Some(metadata)) = (args.similar, regular, &metadata) {
+            similar_candidates.add(path.clone(), metadata);
+        }
+        total_files += 1;
+        size_histogram.add(size);
+        quantiles.add(size);
+        if largest.as_ref().is_none_or(|(_, max)| size > *max) {
+            largest = Some((path.clone(), size));
+        }
+        if let Some(by_ext) = &mut largest_by_ext {
+            by_ext.add(&path, size);
+        }
+        let file_modified = metadata.as_ref().and_then(|m| m.modified().ok());
+        if list_files {
+            files.push(ListedFile {
+                path: relative.to_path_buf(),
+                size,
+                modified: file_modified,
+            });
+        }
 
-        // Build grouping key from first N components (depth)
-        let key_depth = args.depth.min(components.len().saturating_sub(1).max(1));
-        // For files directly under the root, key is just the filename's parent dir
-        if components.len() == 1 {
-            // This is a root-level file (e.g. README.md) — skip grouping
+        let Some(key) = key else {
+            root_files.size += size;
+            root_files.file_count += 1;
+            let key = Path::new(output::ROOT_FILES);
+            if let Some(exts) = &mut extensions {
+                exts.add(key, &breakdown::extension_label(relative), size);
+            }
+            if let (Some(links), Some(metadata)) = (&mut hardlinks, &metadata) {
+                links.add(key, metadata);
+            }
+            if let Some(histograms) = &mut histograms {
+                histograms.entry(key.to_path_buf()).or_default().add(size);
+            }
+            if let (Some(newest), Some(time)) = (&mut modified, file_modified) {
+                newest_modified(newest, key.to_path_buf(), time);
+            }
+            if let Some(metadata) = &metadata {
+                spread.add(key, &path, metadata);
+            }
             continue;
+        };
+        if let Some(exts) = &mut extensions {
+            exts.add(&key, &breakdown::extension_label(relative), size);
+        }
+        if let (Some(links), Some(metadata)) = (&mut hardlinks, &metadata) {
+            links.add(&key, metadata);
+        }
+        if let Some(histograms) = &mut histograms {
+            histograms.entry(key.clone()).or_default().add(size);
+        }
+        if let (Some(newest), Some(time)) = (&mut modified, file_modified) {
+            newest_modified(newest, key.clone(), time);
+        }
+        if let Some(metadata) = &metadata {
+            spread.add(&key, &path, metadata);
         }
-        let key = components[..key_depth].join("/");
 
         let stats = dir_sizes.entry(key).or_insert(DirStats {
             size: 0,
@@ -170,90 +1247,694 @@ fn main() -> Result<()> {
         stats.size += size;
         stats.file_count += 1;
     }
+    // The walk can run out before the loop sees the deadline, once every
+    // directory read after it came back empty.
+    if incomplete.is_none() && pruned_at_deadline.load(Ordering::Relaxed) {
+        timed_out(&mut incomplete);
+    }
+
+    progress.finish(incomplete.is_none());
+    let walk_time = walk_started.elapsed();
+    let aggregate_started = Instant::now();
+    info!("walk: {total_files} files, {total_dirs} directories in {walk_time:.2?}");
 
-    // Filter by min-size
-    if let Some(min) = min_size_bytes {
-        dir_sizes.retain(|_, stats| stats.size >= min);
+    let duplicates = if args.dupes {
+        let hash_started = Instant::now();
+        let limiter = RateLimiter::new(hash_rate);
+        let groups = dupes::find_duplicates(candidates.into_files(), args.hash_threads, &limiter)?;
+        info!(
+            "hashing: {} duplicate groups in {:.2?}",
+            groups.len(),
+            hash_started.elapsed()
+        );
+        Some(groups)
+    } else {
+        None
+    };
+    let similar = if args.similar {
+        let chunk_started = Instant::now();
+        let limiter = RateLimiter::new(hash_rate);
+        let pairs = similar::find_similar(
+            similar_candidates.into_files(),
+            args.similar_threshold,
+            args.hash_threads,
+            &limiter,
+        )?;
+        info!(
+            "chunking: {} similar pairs in {:.2?}",
+            pairs.len(),
+            chunk_started.elapsed()
+        );
+        Some(pairs)
+    } else {
+        None
+    };
+    let unreadable = if args.check_read {
+        let check_started = Instant::now();
+        let limiter = RateLimiter::new(hash_rate);
+        let failed = readcheck::check(read_checks, args.hash_threads, &limiter)?;
+        info!(
+            "read check: {} unreadable files in {:.2?}",
+            failed.len(),
+            check_started.elapsed()
+        );
+        let mut by_key: HashMap<PathBuf, DirStats> = HashMap::new();
+        for (key, _, _) in &failed {
+            by_key
+                .entry(key.clone())
+                .or_insert(DirStats {
+                    size: 0,
+                    file_count: 0,
+                })
+                .file_count += 1;
+        }
+        if args.depth == 0 {
+            by_key = tree::cumulative(&by_key);
+        }
+        Some(Unreadable {
+            by_key: by_key
+                .into_iter()
+                .map(|(key, stats)| (key, stats.file_count))
+                .collect(),
+            files: failed
+                .into_iter()
+                .map(|(_, path, err)| (path, err))
+                .collect(),
+        })
+    } else {
+        None
+    };
+    let duplicate_trees = match (&duplicates, args.dupe_trees) {
+        (Some(groups), true) => {
+            let trees = dupes::find_duplicate_trees(tree_dirs, &tree_files, groups);
+            info!("{} duplicate directory trees", trees.len());
+            Some(trees)
+        }
+        _ => None,
+    };
+
+    // Each copy in a duplicate group is charged (n-1)/n of the file, so every
+    // entry's figure is what it would shrink by, and they add up to the total.
+    let reclaimable = duplicates.as_ref().map(|groups| {
+        let mut by_key: HashMap<PathBuf, DirStats> = HashMap::new();
+        for group in groups {
+            let copies = group.paths.len() as u64;
+            for path in &group.paths {
+                let Ok(relative) = path.strip_prefix(root) else {
+                    continue;
+                };
+                let components: Vec<_> = relative.components().map(|c| c.as_os_str()).collect();
+                let metadata = std::fs::symlink_metadata(path).ok();
+                let key = keyer
+                    .key(&group::Item {
+                        components: &components,
+                        metadata: metadata.as_ref(),
+                        is_dir: false,
+                    })
+                    .unwrap_or_else(|| PathBuf::from(output::ROOT_FILES));
+                by_key
+                    .entry(key)
+                    .or_insert(DirStats {
+                        size: 0,
+                        file_count: 0,
+                    })
+                    .size += group.size * (copies - 1) / copies;
+            }
+        }
+        if args.depth == 0 {
+            // The pseudo-entry has no ancestors, so rolling up leaves it as is.
+            by_key = tree::cumulative(&by_key);
+        }
+        by_key
+            .into_iter()
+            .map(|(key, stats)| (key, stats.size))
+            .collect::<HashMap<_, _>>()
+    });
+
+    let tree = format.needs_tree().then(|| {
+        tree::build(
+            root.as_os_str(),
+            &dir_sizes,
+            total_size,
+            top,
+            min_size_bytes.unwrap_or(0),
+        )
+    });
+
+    // Without a depth limit every file is keyed by its own directory; roll those
+    // up so each directory in the table includes its subdirectories.
+    if args.depth == 0 {
+        extensions = extensions.map(Shares::cumulative);
+        histograms = histograms.map(histogram::cumulative);
+        modified = modified.map(stale::cumulative);
     }
+    let (sorted, parent_sizes) = tree::rank(
+        dir_sizes,
+        root_files,
+        args.depth,
+        args.percent_of,
+        min_size_bytes.unwrap_or(0),
+        args.sort,
+        top,
+    );
+    let quotas = match (&filesystem, args.group_by) {
+        (Some(fs), GroupBy::Owner) => Some(quota::user_quotas(
+            &fs.mount_point,
+            sorted.iter().map(|(key, _)| key.as_path()),
+        ))
+        .filter(|quotas| !quotas.is_empty()),
+        _ => None,
+    };
+    if let (true, false, Some(canonical)) =
+        (args.btrfs_qgroups, subvolumes.is_empty(), &canonical_root)
+    {
+        let qgroups = btrfs::qgroups(canonical);
+        if qgroups.is_empty() {
+            warn!("no Btrfs quota groups readable for {}", root.display());
+        }
+        for subvolume in &mut subvolumes {
+            if let Some(&(referenced, exclusive)) = subvolume.id.and_then(|id| qgroups.get(&id)) {
+                subvolume.referenced = Some(referenced);
+                subvolume.exclusive = Some(exclusive);
+            }
+        }
+    }
+    let subvolumes = (!subvolumes.is_empty()).then_some(subvolumes);
 
-    // Sort by size descending and take top N
-    let mut sorted: Vec<_> = dir_sizes.into_iter().collect();
-    sorted.sort_by(|a, b| b.1.size.cmp(&a.1.size));
-    sorted.truncate(args.top);
+    Ok(Report {
+        scanned_at: Some(scanned_at),
+        filesystem,
+        spread: spread.finish(args.depth == 0),
+        parent_sizes,
+        total_size,
+        total_files,
+        total_dirs,
+        incomplete,
+        bind_mounts,
+        reclaimable,
+        duplicates,
+        duplicate_trees,
+        similar,
+        unreadable,
+        oversized: skip_files_over.map(|limit| {
+            oversized.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+            Oversized {
+                limit,
+                files: oversized,
+            }
+        }),
+        files: list_files.then_some(files),
+        extensions,
+        histograms,
+        size_histogram: Some(size_histogram),
+        file_sizes: largest.map(|largest| FileSizes {
+            median: quantiles.quantile(0.5).unwrap_or(0),
+            p90: quantiles.quantile(0.9).unwrap_or(0),
+            p99: quantiles.quantile(0.99).unwrap_or(0),
+            largest,
+        }),
+        largest_by_ext: largest_by_ext.map(|by_ext| by_ext.finish(args.top)),
+        modified,
+        hardlinks: hardlinks.map(Hardlinks::sharing),
+        quotas,
+        subvolumes,
+        datasets: (!datasets.is_empty()).then_some(datasets),
+        special_files: (!special_counts.is_empty()).then(|| {
+            special_listed.sort();
+            SpecialFiles {
+                policy: args.special_files,
+                counts: special_counts,
+                files: special_listed,
+            }
+        }),
+        changed_since,
+        timings: args.timings.then(|| timings::Timings {
+            started: scan_started,
+            setup: walk_started - scan_started,
+            walk: walk_time,
+            stat: stat_time,
+            aggregate: aggregate_started.elapsed(),
+            entries: entries_seen,
+        }),
+        auto_excluded: args.auto_exclude.then(|| {
+            let mut counts: Vec<(String, u64)> = auto_excluded
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(preset, count)| (preset.name().to_string(), *count))
+                .collect();
+            counts.sort();
+            counts
+        }),
+        budgets: (!budgets.is_empty()).then(|| {
+            budgets
+                .into_iter()
+                .zip(budget_sizes)
+                .map(|(budget, size)| budget::Measured { budget, size })
+                .collect()
+        }),
+        tree,
+        ..Report::new(
+            root.to_path_buf(),
+            sorted,
+            args.percent_of,
+            args.group_by,
+            top,
+        )
+    })
+}
 
-    if args.json {
-        let directories: Vec<JsonDirEntry> = sorted
-            .iter()
-            .map(|(name, stats)| JsonDirEntry {
-                name: name.clone(),
-                size: stats.size,
-                size_human: format_size(stats.size, BINARY),
-                file_count: stats.file_count,
-                percentage: if total_size > 0 {
-                    (stats.size as f64 / total_size as f64) * 100.0
-                } else {
-                    0.0
-                },
-            })
-            .collect();
+/// Parses the command line over `DISKHOUND_*` environment variables and the
+/// config file's [defaults].
+fn parse_args() -> Result<Args> {
+    // `init` writes the config file, so it mustn't need a readable one.
+    if let Ok(
+        args @ Args {
+            command: Some(Command::Init),
+            ..
+        },
+    ) = Args::try_parse()
+    {
+        return Ok(args);
+    }
+    let path = config::path_from_args(std::env::args_os());
+    let config = config::load(path.as_deref(), parse_human_size)?;
+    let command = config::layer_defaults(Args::command(), &config.defaults)?;
+    Ok(Args::from_arg_matches(&command.get_matches()).unwrap_or_else(|err| err.exit()))
+}
+
+fn main() -> Result<()> {
+    let mut args = parse_args()?;
+    if args.by_project {
+        args.group_by = GroupBy::Project;
+    }
+    if args.by_tag {
+        args.group_by = GroupBy::Tag;
+    }
+    logging::init(args.verbose, args.quiet, args.log_file.as_deref())?;
+    priority::lower(args.nice, args.ionice)?;
+    locale::init(args.locale.as_deref())?;
+    i18n::init(args.locale.as_deref());
+
+    if args.schema {
+        return output::write_json_schema(&mut std::io::stdout().lock());
+    }
 
-        let output = JsonOutput {
-            summary: JsonSummary {
-                total_size,
-                total_size_human: format_size(total_size, BINARY),
-                total_files,
-                total_dirs,
-                shown: directories.len(),
-            },
-            directories,
+    if args.machine {
+        return machine::run(|path| scan(&args, path, Format::Table, usize::MAX));
+    }
+
+    let format = if args.json { Format::Json } else { args.format };
+    if format.is_binary() && args.output.is_none() && std::io::stdout().is_terminal() {
+        bail!("refusing to write binary output to a terminal; use --output or redirect stdout");
+    }
+    if args.files && !matches!(format, Format::Parquet | Format::Arrow | Format::Tsv) {
+        bail!("--files requires --format parquet, arrow, or tsv");
+    }
+
+    if args.hardlinks && args.depth == 0 {
+        bail!("--hardlinks needs a fixed --depth");
+    }
+    // Projects are found at any depth anyway; `all` would only roll them up
+    // into the directories above them.
+    if args.group_by == GroupBy::Project && args.depth == 0 {
+        bail!("--group-by project doesn't take --depth all");
+    }
+    if args.group_by != GroupBy::Dir
+        && (args.reveal.is_some() || args.shell.is_some() || args.copy.is_some())
+    {
+        bail!("--reveal, --shell, and --copy need --group-by dir");
+    }
+
+    if let Some(Command::Compare { left, right }) = &args.command {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("compare supports --format table or json");
+        }
+        let reports = scan_concurrently(&args, &[left, right], Format::Table, usize::MAX)?;
+        let comparison = compare::Comparison::new(&reports[0], &reports[1], args.top);
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => compare::write_json(w, &comparison),
+            _ => compare::write_table(w, &comparison),
+        });
+    }
+
+    if let Some(Command::Bench {
+        path,
+        threads,
+        runs,
+    }) = &args.command
+    {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("bench supports --format table or json");
+        }
+        let (path, runs) = (path.clone(), *runs);
+        let threads = match threads.as_slice() {
+            [] => bench::default_threads(),
+            threads => threads.to_vec(),
         };
+        // Untimed, so every setting is measured with the same warm caches.
+        scan(&args, &path, Format::Table, 0)?;
+        let mut timings = Vec::new();
+        for walk_threads in threads {
+            args.walk_threads = walk_threads;
+            let mut timing = bench::Timing {
+                walk_threads,
+                runs: Vec::new(),
+                files: 0,
+            };
+            for _ in 0..runs {
+                let started = Instant::now();
+                timing.files = scan(&args, &path, Format::Table, 0)?.total_files;
+                timing.runs.push(started.elapsed());
+            }
+            info!(
+                "bench: {walk_threads} walk threads, best {:.2?}",
+                timing.best()
+            );
+            timings.push(timing);
+        }
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => bench::write_json(w, &timings),
+            _ => bench::write_table(w, &timings),
+        });
+    }
 
-        println!("{}", serde_json::to_string_pretty(&output)?);
-        return Ok(());
+    if let Some(Command::Backup { tool, repo }) = &args.command {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("backup supports --format table or json");
+        }
+        let repository = backup::read(*tool, repo)?;
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => backup::write_json(w, &repository, args.top),
+            _ => backup::write_table(w, &repository, args.top),
+        });
     }
 
-    if sorted.is_empty() {
-        println!("No subdirectories found in {:?}", args.path);
-        return Ok(());
+    if let Some(Command::Hist { path }) = &args.command {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("hist supports --format table or json");
+        }
+        let report = scan(&args, path, Format::Table, 0)?;
+        let histogram = report.size_histogram.unwrap_or_default();
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => histogram::write_json(w, &histogram),
+            _ => histogram::write_table(w, &histogram),
+        });
     }
 
-    let max_name_len = sorted.iter().map(|(n, _)| n.len()).max().unwrap_or(10);
-    let max_size = sorted.first().map(|(_, s)| s.size).unwrap_or(1);
-    let bar_width = 20;
+    if let Some(Command::ExtFiles { path, .. }) = &args.command {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("ext-files supports --format table or json");
+        }
+        let report = scan(&args, path, Format::Table, 0)?;
+        let exts = report.largest_by_ext.unwrap_or_default();
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => largest::write_json(w, &exts),
+            _ => largest::write_table(w, &exts),
+        });
+    }
 
-    for (name, stats) in &sorted {
-        let filled = if max_size > 0 {
-            ((stats.size as f64 / max_size as f64) * bar_width as f64).round() as usize
-        } else {
-            0
+    if let Some(Command::Stale {
+        path,
+        size_weight,
+        age_weight,
+    }) = &args.command
+    {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("stale supports --format table or json");
+        }
+        let report = scan(&args, path, Format::Table, usize::MAX)?;
+        let entries = stale::rank(&report, *size_weight, *age_weight, args.top);
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => stale::write_json(w, &entries),
+            _ => stale::write_table(w, &entries),
+        });
+    }
+
+    if let Some(Command::Init) = &args.command {
+        let Some(path) = args.config.clone().or_else(config::default_path) else {
+            bail!("no configuration directory found; pass --config PATH");
         };
-        let empty = bar_width - filled;
-        let bar: String = "\u{2588}".repeat(filled) + &"\u{2591}".repeat(empty);
-        let percentage = if total_size > 0 {
-            (stats.size as f64 / total_size as f64) * 100.0
-        } else {
-            0.0
+        return init::run(&path, &mut std::io::stdin().lock(), &mut std::io::stderr());
+    }
+
+    if let Some(Command::Fs { scan: None }) = &args.command {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("fs supports --format table or json");
+        }
+        let filesystems = filesystem::mounted();
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => filesystem::write_json(w, &filesystems),
+            _ => filesystem::write_table(w, &filesystems),
+        });
+    }
+
+    if let Some(Command::Forecast { reports }) = &args.command {
+        if !matches!(format, Format::Table | Format::Json) {
+            bail!("forecast supports --format table or json");
+        }
+        let snapshots = reports
+            .iter()
+            .map(|path| snapshot::load(path))
+            .collect::<Result<Vec<_>>>()?;
+        let forecasts: Vec<_> = forecast::history(&snapshots)?
+            .into_iter()
+            .filter_map(forecast::Forecast::new)
+            .collect();
+        if forecasts.is_empty() {
+            bail!("forecast needs readings from at least two different times");
+        }
+        return pager::write_stdout(!args.no_pager, |w| match format {
+            Format::Json => forecast::write_json(w, &forecasts),
+            _ => forecast::write_table(w, &forecasts),
+        });
+    }
+
+    if let Some(Command::Manifest { action }) = &args.command {
+        let hash_rate = match &args.hash_rate {
+            Some(s) => Some(parse_human_size(s)?),
+            None => None,
         };
+        let limiter = RateLimiter::new(hash_rate);
+        match action {
+            ManifestAction::Create { path } => {
+                let report = scan(&args, path, Format::Table, 0)?;
+                let root = std::fs::canonicalize(path)?;
+                let files = report.files.unwrap_or_default();
+                let create = |w: &mut dyn Write| {
+                    manifest::create(w, &root, &files, args.hash_threads, &limiter)
+                };
+                return match &args.output {
+                    Some(output) => output::write_atomic(output, create),
+                    None => create(&mut std::io::stdout().lock()),
+                };
+            }
+            ManifestAction::Verify { manifest } => {
+                if !matches!(format, Format::Table | Format::Json) {
+                    bail!("manifest verify supports --format table or json");
+                }
+                let manifest = manifest::load(manifest)?;
+                let report = scan(&args, &manifest.root, Format::Table, 0)?;
+                let files = report.files.unwrap_or_default();
+                let verification = manifest::verify(manifest, &files, args.hash_threads, &limiter)?;
+                pager::write_stdout(!args.no_pager, |w| match format {
+                    Format::Json => manifest::write_json(w, &verification),
+                    _ => manifest::write_table(w, &verification),
+                })?;
+                let differences = verification.differences.len();
+                if differences > 0 {
+                    bail!("{differences} files differ from the manifest or couldn't be checked");
+                }
+                return Ok(());
+            }
+        }
+    }
 
-        println!(
-            "  {:<width$}  {}  {:>10}  {:>5.1}%  ({} files)",
-            name,
-            bar,
-            format_size(stats.size, BINARY),
-            percentage,
-            stats.file_count,
-            width = max_name_len,
-        );
+    // Saved reports, imported dumps, and remote trees aren't tied to a
+    // scanned directory.
+    if (args.command.is_some() || remote::Remote::parse(&args.path)?.is_some())
+        && (args.reveal.is_some() || args.shell.is_some() || args.copy.is_some())
+    {
+        bail!("--reveal, --shell, and --copy need a fresh scan");
     }
+    let min_size = match &args.min_size {
+        Some(s) => parse_human_size(s)?,
+        None => 0,
+    };
+    let mut report = match &args.command {
+        Some(Command::Merge { reports }) => {
+            merge::merge(reports, args.percent_of, min_size, args.sort, args.top)?
+        }
+        Some(Command::Render { report }) => snapshot::load(report)?.into_report(
+            report,
+            args.percent_of,
+            min_size,
+            args.sort,
+            args.top,
+        )?,
+        Some(Command::Import { from, dump }) => {
+            if args.group_by != GroupBy::Dir {
+                bail!("import only supports --group-by dir");
+            }
+            import::read(*from, dump)?.into_report(
+                format,
+                args.depth,
+                args.percent_of,
+                min_size,
+                args.sort,
+                args.top,
+            )
+        }
+        Some(Command::K8s {
+            pvc,
+            namespace,
+            binary,
+        }) => {
+            let binary = match binary {
+                Some(binary) => binary.clone(),
+                None => std::env::current_exe()?,
+            };
+            let mut scan_args = vec![
+                "--depth".to_string(),
+                args.depth.to_string(),
+                "--top".to_string(),
+                args.top.to_string(),
+            ];
+            for name in &args.exclude {
+                scan_args.extend(["--exclude".to_string(), name.to_string_lossy().to_string()]);
+            }
+            if args.skip_hidden {
+                scan_args.push("--skip-hidden".to_string());
+            }
+            if args.no_vcs {
+                scan_args.push("--no-vcs".to_string());
+            }
+            let (target, snapshot) = k8s::scan(pvc, namespace.as_deref(), &binary, &scan_args)?;
+            let mut report = snapshot.into_report(
+                Path::new(pvc),
+                args.percent_of,
+                min_size,
+                args.sort,
+                args.top,
+            )?;
+            report.root = format!("{}:{}", target.pod, target.mount_path).into();
+            report
+        }
+        Some(Command::Fs { scan: Some(n) }) => {
+            let filesystems = filesystem::mounted();
+            let Some(fs) = n.checked_sub(1).and_then(|i| filesystems.get(i)) else {
+                bail!(
+                    "no filesystem number {n}; `diskhound fs` lists {}",
+                    filesystems.len()
+                );
+            };
+            scan(&args, &fs.usage.mount_point, format, args.top)?
+        }
+        _ => match remote::Remote::parse(&args.path)? {
+            Some(remote) => {
+                if args.group_by != GroupBy::Dir {
+                    bail!("remote scans only support --group-by dir");
+                }
+                let mut exclude = args.exclude.clone();
+                if args.no_vcs {
+                    exclude.extend(preset::VCS_DIRECTORIES.iter().map(OsString::from));
+                }
+                let listing = remote.list(&exclude, args.skip_hidden, args.connections as usize)?;
+                import::walk(&listing, args.path.clone())?.into_report(
+                    format,
+                    args.depth,
+                    args.percent_of,
+                    min_size,
+                    args.sort,
+                    args.top,
+                )
+            }
+            None => scan(&args, &args.path, format, args.top)?,
+        },
+    };
+    report.header = !args.no_header;
+    report.icons = args.icons;
+    report.mean_size = args.mean_size || args.sort == SortBy::Mean;
 
-    println!();
-    println!(
-        "Total: {} in {} files across {} directories (showing top {})",
-        format_size(total_size, BINARY),
-        total_files,
-        total_dirs,
-        sorted.len(),
-    );
+    // Checked before rendering so a bad baseline fails without output.
+    let alerts = match (&args.alert_growth, &args.baseline) {
+        (Some(limit), Some(baseline_path)) => {
+            let limit = growth::parse_limit(limit, parse_human_size)?;
+            let baseline = snapshot::load(baseline_path)?;
+            let taken = snapshot::taken(&baseline, baseline_path)?;
+            let now = report.scanned_at.unwrap_or_else(SystemTime::now);
+            let elapsed = now.duration_since(taken).unwrap_or_default();
+            let alerts = growth::check(&report, &baseline, elapsed, limit)?;
+            Some((alerts, elapsed, limit))
+        }
+        _ => None,
+    };
+
+    let render = |w: &mut dyn Write| output::write(w, format, &report);
+
+    let render_started = Instant::now();
+    match &args.output {
+        Some(path) => {
+            output::write_atomic(path, render)?;
+            // Machine-readable output went to the file; keep the table on stdout.
+            if format != Format::Table {
+                pager::write_stdout(!args.no_pager, |w| output::write_table(w, &report))?;
+            }
+        }
+        None => pager::write_stdout(!args.no_pager, render)?,
+    }
+    let render_time = render_started.elapsed();
+    info!("render: {render_time:.2?}");
+    if let Some(timings) = &report.timings {
+        timings::write(&mut std::io::stderr(), timings, render_time)?;
+    }
+
+    if let Some(rank) = args.copy {
+        let paths = match rank {
+            Some(rank) => vec![ranked_dir(&args.path, &report, rank)?],
+            None => report
+                .entries
+                .iter()
+                .filter(|(name, _)| !output::is_root_files(name))
+                .map(|(name, _)| args.path.join(name))
+                .collect(),
+        };
+        let text: String = paths.iter().map(|p| format!("{}\n", p.display())).collect();
+        actions::copy_to_clipboard(&text)?;
+        eprint!("Copied {} path(s) to the clipboard:\n{text}", paths.len());
+    }
+    if let Some(rank) = args.reveal {
+        actions::reveal(&ranked_dir(&args.path, &report, rank)?)?;
+    }
+    if let Some(rank) = args.shell {
+        actions::shell(&ranked_dir(&args.path, &report, rank)?)?;
+    }
+
+    if let Some((alerts, elapsed, limit)) = &alerts {
+        if !alerts.is_empty() {
+            for alert in alerts {
+                warn!("{}", growth::describe(alert, *elapsed));
+            }
+            if let Some(command) = &args.alert_command {
+                let json = growth::to_json(&report, alerts, *elapsed, *limit)?;
+                actions::run_hook(command, &json)?;
+            }
+            bail!(
+                "{} entries grew faster than {}",
+                alerts.len(),
+                args.alert_growth.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    let exceeded = report
+        .budgets
+        .iter()
+        .flatten()
+        .filter(|m| m.exceeded())
+        .count();
+    if exceeded > 0 {
+        bail!("{exceeded} size budgets exceeded");
+    }
 
     Ok(())
 }
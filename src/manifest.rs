@@ -0,0 +1,556 @@
+use crate::dupes::hash_full;
+use crate::locale;
+use crate::output::{decode_raw, display_name, encode_raw, raw_base64, ListedFile, SCHEMA_VERSION};
+use crate::throttle::RateLimiter;
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// First line of every manifest; the number is the format version.
+const HEADER: &str = "# diskhound manifest 1";
+
+/// Hashes every listed file under `root` and writes one line per file:
+/// blake3, size, modification time in Unix seconds, and the path relative
+/// to `root`, tab-separated. A fifth column carries the raw path bytes,
+/// base64-encoded, when the path isn't valid UTF-8. Files that can't be
+/// read get `-` for a hash.
+pub fn create(
+    w: &mut dyn Write,
+    root: &Path,
+    files: &[ListedFile],
+    threads: usize,
+    limiter: &RateLimiter,
+) -> Result<()> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    let hashes: Vec<Option<String>> = pool.install(|| {
+        files
+            .par_iter()
+            .map(|file| {
+                hash_full(&root.join(&file.path), limiter)
+                    .map_err(|err| warn!("could not hash {}: {err}", file.path.display()))
+                    .ok()
+                    .map(|hash| hash.to_hex().to_string())
+            })
+            .collect()
+    });
+
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by(|&a, &b| files[a].path.cmp(&files[b].path));
+    writeln!(w, "{HEADER}")?;
+    writeln!(w, "# root: {}", escape(&root.to_string_lossy()))?;
+    for i in order {
+        let file = &files[i];
+        write!(
+            w,
+            "{}\t{}\t{}\t{}",
+            hashes[i].as_deref().unwrap_or("-"),
+            file.size,
+            file.modified
+                .map_or("-".to_string(), |m| unix_time(m).to_string()),
+            escape(&display_name(&file.path)),
+        )?;
+        if file.path.to_str().is_none() {
+            write!(w, "\t{}", encode_raw(file.path.as_os_str()))?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+fn unix_time(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+fn escape(path: &str) -> String {
+    path.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// A file as recorded in a manifest.
+pub struct Recorded {
+    /// `None` when the file couldn't be read at creation.
+    hash: Option<String>,
+    size: u64,
+    modified: Option<i64>,
+}
+
+/// A parsed manifest: the tree it describes and its files by relative path.
+pub struct Manifest {
+    pub root: PathBuf,
+    files: HashMap<PathBuf, Recorded>,
+}
+
+pub fn load(path: &Path) -> Result<Manifest> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse(&text, path)
+}
+
+/// Parses a manifest's `text`; errors name `path` and the line.
+fn parse(text: &str, path: &Path) -> Result<Manifest> {
+    let mut lines = text.lines();
+    if lines.next() != Some(HEADER) {
+        bail!("{} is not a diskhound manifest", path.display());
+    }
+    let Some(root) = lines.next().and_then(|l| l.strip_prefix("# root: ")) else {
+        bail!("{} has no root line", path.display());
+    };
+    let mut files = HashMap::new();
+    // File lines start after the header and the root line.
+    for (n, line) in (3..).zip(lines) {
+        let at = || format!("{}:{n}", path.display());
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (&[hash, size, modified, name], raw) = (&fields[..4.min(fields.len())], fields.get(4))
+        else {
+            bail!("{}: expected at least 4 fields", at());
+        };
+        let relative = match raw {
+            Some(raw) => {
+                PathBuf::from(decode_raw(raw).with_context(|| format!("{}: bad raw path", at()))?)
+            }
+            None => unescape(name).split('/').collect(),
+        };
+        files.insert(
+            relative,
+            Recorded {
+                hash: (hash != "-").then(|| hash.to_string()),
+                size: size
+                    .parse()
+                    .with_context(|| format!("{}: bad size", at()))?,
+                modified: modified.parse().ok(),
+            },
+        );
+    }
+    Ok(Manifest {
+        root: PathBuf::from(unescape(root)),
+        files,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Removed,
+    Resized,
+    /// Different content with a new modification time: an ordinary edit.
+    Modified,
+    /// Different content with the recorded size and modification time, which
+    /// normal writes don't produce: likely silent corruption.
+    Corrupt,
+    /// Recorded with a hash but failing to read now, often the first sign
+    /// of a failing disk.
+    Unreadable,
+    /// Recorded without a hash (unreadable at creation), so its content
+    /// can't be checked.
+    Unverified,
+}
+
+impl Change {
+    fn label(self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Resized => "resized",
+            Change::Modified => "modified",
+            Change::Corrupt => "CORRUPT",
+            Change::Unreadable => "UNREADABLE",
+            Change::Unverified => "unverified",
+        }
+    }
+}
+
+pub struct Difference {
+    pub path: PathBuf,
+    pub change: Change,
+    pub expected_size: Option<u64>,
+    pub actual_size: Option<u64>,
+}
+
+pub struct Verification {
+    pub root: PathBuf,
+    pub unchanged: u64,
+    /// In path order.
+    pub differences: Vec<Difference>,
+}
+
+/// Compares the files found by a fresh walk of the manifest's root with the
+/// recorded ones, hashing only files whose size still matches. A file is
+/// unchanged only once its hash has been checked.
+pub fn verify(
+    manifest: Manifest,
+    files: &[ListedFile],
+    threads: usize,
+    limiter: &RateLimiter,
+) -> Result<Verification> {
+    let Manifest {
+        root,
+        files: mut recorded,
+    } = manifest;
+    let mut differences = Vec::new();
+    let mut candidates = Vec::new();
+    for file in files {
+        match recorded.remove(&file.path) {
+            None => differences.push(Difference {
+                path: file.path.clone(),
+                change: Change::Added,
+                expected_size: None,
+                actual_size: Some(file.size),
+            }),
+            Some(old) if old.size != file.size => differences.push(Difference {
+                path: file.path.clone(),
+                change: Change::Resized,
+                expected_size: Some(old.size),
+                actual_size: Some(file.size),
+            }),
+            Some(old) => candidates.push((file, old)),
+        }
+    }
+    differences.extend(recorded.into_iter().map(|(path, old)| Difference {
+        path,
+        change: Change::Removed,
+        expected_size: Some(old.size),
+        actual_size: None,
+    }));
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    let checked: Vec<Option<Change>> = pool.install(|| {
+        candidates
+            .par_iter()
+            .map(|(file, old)| {
+                let Some(expected) = old.hash.as_deref() else {
+                    return Some(Change::Unverified);
+                };
+                let actual = match hash_full(&root.join(&file.path), limiter) {
+                    Ok(actual) => actual,
+                    Err(err) => {
+                        warn!("could not hash {}: {err}", file.path.display());
+                        return Some(Change::Unreadable);
+                    }
+                };
+                if actual.to_hex().as_str() == expected {
+                    return None;
+                }
+                let touched = file.modified.map(unix_time) != old.modified;
+                Some(if touched {
+                    Change::Modified
+                } else {
+                    Change::Corrupt
+                })
+            })
+            .collect()
+    });
+    let mut unchanged = 0;
+    for ((file, old), change) in candidates.iter().zip(checked) {
+        match change {
+            Some(change) => differences.push(Difference {
+                path: file.path.clone(),
+                change,
+                expected_size: Some(old.size),
+                actual_size: Some(file.size),
+            }),
+            None => unchanged += 1,
+        }
+    }
+    differences.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(Verification {
+        root,
+        unchanged,
+        differences,
+    })
+}
+
+fn count(verification: &Verification, change: Change) -> u64 {
+    verification
+        .differences
+        .iter()
+        .filter(|d| d.change == change)
+        .count() as u64
+}
+
+pub fn write_table(w: &mut dyn Write, verification: &Verification) -> Result<()> {
+    for d in &verification.differences {
+        let sizes = match (d.expected_size, d.actual_size) {
+            (Some(old), Some(new)) if old != new => {
+                format!("  ({} -> {})", locale::size(old), locale::size(new))
+            }
+            _ => String::new(),
+        };
+        writeln!(
+            w,
+            "  {:<10}  {}{sizes}",
+            d.change.label(),
+            display_name(&d.path)
+        )?;
+    }
+    if !verification.differences.is_empty() {
+        writeln!(w)?;
+    }
+    writeln!(
+        w,
+        "{}: {} unchanged, {} added, {} removed, {} resized, {} modified, {} corrupt, {} unreadable, {} unverified",
+        verification.root.display(),
+        locale::count(verification.unchanged),
+        locale::count(count(verification, Change::Added)),
+        locale::count(count(verification, Change::Removed)),
+        locale::count(count(verification, Change::Resized)),
+        locale::count(count(verification, Change::Modified)),
+        locale::count(count(verification, Change::Corrupt)),
+        locale::count(count(verification, Change::Unreadable)),
+        locale::count(count(verification, Change::Unverified)),
+    )?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonVerification {
+    schema_version: u32,
+    root: String,
+    unchanged: u64,
+    /// Every file that differs from the manifest or couldn't be checked
+    /// against it, in path order.
+    differences: Vec<JsonDifference>,
+}
+
+#[derive(Serialize)]
+struct JsonDifference {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_base64: Option<String>,
+    change: Change,
+    /// `null` for added files.
+    expected_size: Option<u64>,
+    /// `null` for removed files.
+    actual_size: Option<u64>,
+}
+
+pub fn write_json(w: &mut dyn Write, verification: &Verification) -> Result<()> {
+    let output = JsonVerification {
+        schema_version: SCHEMA_VERSION,
+        root: verification.root.to_string_lossy().to_string(),
+        unchanged: verification.unchanged,
+        differences: verification
+            .differences
+            .iter()
+            .map(|d| JsonDifference {
+                path: display_name(&d.path),
+                path_base64: raw_base64(d.path.as_os_str()),
+                change: d.change,
+                expected_size: d.expected_size,
+                actual_size: d.actual_size,
+            })
+            .collect(),
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(path: impl Into<PathBuf>, size: u64) -> ListedFile {
+        ListedFile {
+            path: path.into(),
+            size,
+            modified: Some(UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)),
+        }
+    }
+
+    fn round_trip(root: &Path, files: &[ListedFile]) -> (String, Manifest) {
+        let mut out = Vec::new();
+        create(&mut out, root, files, 1, &RateLimiter::new(None)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let manifest = parse(&text, Path::new("m.txt")).unwrap();
+        (text, manifest)
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        for name in [
+            "plain",
+            "tab\there",
+            "new\nline",
+            "back\\slash\\t",
+            "cr\r",
+            "trailing\\",
+        ] {
+            assert_eq!(unescape(&escape(name)), name);
+            assert!(!escape(name).contains(['\t', '\n', '\r']));
+        }
+    }
+
+    #[test]
+    fn create_then_load() {
+        let root = std::env::temp_dir().join(format!("diskhound-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        let names = ["a\tb", "c\nd", "e\\f", "sub/g"];
+        for name in names {
+            std::fs::write(root.join(name), name).unwrap();
+        }
+        let mut files: Vec<ListedFile> = names
+            .iter()
+            .map(|name| listed(*name, name.len() as u64))
+            .collect();
+        // Listed by the walk but gone before hashing.
+        files.push(listed("missing", 3));
+        let (text, manifest) = round_trip(&root, &files);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(text.lines().count(), 2 + files.len());
+        assert_eq!(manifest.root, root);
+        assert_eq!(manifest.files.len(), files.len());
+        for name in names {
+            let recorded = &manifest.files[Path::new(name)];
+            assert_eq!(recorded.size, name.len() as u64);
+            assert_eq!(recorded.modified, Some(1_700_000_000));
+            assert_eq!(
+                recorded.hash.as_deref(),
+                Some(blake3::hash(name.as_bytes()).to_hex().as_str())
+            );
+        }
+        assert!(manifest.files[Path::new("missing")].hash.is_none());
+    }
+
+    #[test]
+    fn root_with_a_newline_stays_on_one_line() {
+        let (text, manifest) = round_trip(Path::new("/odd\nroot"), &[]);
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(manifest.root, Path::new("/odd\nroot"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_keep_their_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = PathBuf::from(std::ffi::OsStr::from_bytes(b"caf\xe9"));
+        let (text, manifest) = round_trip(Path::new("/nonexistent"), &[listed(name.clone(), 1)]);
+        let line = text.lines().nth(2).unwrap();
+        assert_eq!(line.split('\t').count(), 5);
+        assert!(line.starts_with("-\t1\t1700000000\tcaf\u{fffd}\t"));
+        assert!(manifest.files.contains_key(&name));
+    }
+
+    #[test]
+    fn malformed_lines_name_their_line() {
+        let error = |text: &str| {
+            parse(text, Path::new("m.txt"))
+                .err()
+                .map(|e| format!("{e:#}"))
+        };
+        let header = format!("{HEADER}\n# root: /r\n");
+        assert!(error(&format!("{header}-\t1\t-\tok\n")).is_none());
+        assert_eq!(
+            error(&format!("{header}-\t1\t-\tok\nonly\ttwo\n")).unwrap(),
+            "m.txt:4: expected at least 4 fields"
+        );
+        assert!(error(&format!("{header}-\tbig\t-\tname\n"))
+            .unwrap()
+            .starts_with("m.txt:3: bad size"));
+        assert!(error(&format!("{header}-\t1\t-\tname\t!!\n"))
+            .unwrap()
+            .starts_with("m.txt:3: bad raw path"));
+        assert_eq!(
+            error("# not a manifest\n").unwrap(),
+            "m.txt is not a diskhound manifest"
+        );
+        assert_eq!(
+            error(&format!("{HEADER}\n")).unwrap(),
+            "m.txt has no root line"
+        );
+    }
+
+    #[test]
+    fn verify_reports_what_it_could_not_check() {
+        let root = std::env::temp_dir().join(format!("diskhound-verify-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for name in ["same", "vanishes", "edited", "rotted", "unhashed"] {
+            std::fs::write(root.join(name), "1234").unwrap();
+        }
+        let files: Vec<ListedFile> = ["same", "vanishes", "edited", "rotted", "unhashed"]
+            .into_iter()
+            .map(|name| listed(name, 4))
+            .collect();
+        let (text, _) = round_trip(&root, &files);
+        // As if `unhashed` couldn't be read when the manifest was made.
+        let text: String = text
+            .lines()
+            .map(|line| {
+                if line.ends_with("\tunhashed") {
+                    format!("-{}\n", &line[line.find('\t').unwrap()..])
+                } else {
+                    format!("{line}\n")
+                }
+            })
+            .collect();
+        let manifest = parse(&text, Path::new("m.txt")).unwrap();
+
+        std::fs::remove_file(root.join("vanishes")).unwrap();
+        std::fs::write(root.join("edited"), "abcd").unwrap();
+        std::fs::write(root.join("rotted"), "abcd").unwrap();
+        let mut now = files;
+        now[2].modified = Some(SystemTime::now());
+        let verification = verify(manifest, &now, 1, &RateLimiter::new(None)).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let changes: Vec<(String, Change)> = verification
+            .differences
+            .iter()
+            .map(|d| (display_name(&d.path), d.change))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("edited".to_string(), Change::Modified),
+                ("rotted".to_string(), Change::Corrupt),
+                ("unhashed".to_string(), Change::Unverified),
+                ("vanishes".to_string(), Change::Unreadable),
+            ]
+        );
+        assert_eq!(verification.unchanged, 1);
+
+        let mut table = Vec::new();
+        write_table(&mut table, &verification).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        assert!(table.contains("  UNREADABLE  vanishes\n"));
+        assert!(table.contains("1 corrupt, 1 unreadable, 1 unverified"));
+        let mut json = Vec::new();
+        write_json(&mut json, &verification).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"change\": \"unreadable\""));
+        assert!(json.contains("\"change\": \"unverified\""));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::breakdown::Shares;
+use crate::group::GroupBy;
+use crate::output::{DirStats, PercentOf, Report, SortBy};
+use crate::snapshot;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Combines saved JSON reports, e.g. one per host or mount, into a single
+/// report whose entries are summed by name. Each snapshot only holds its own
+/// top entries, so an entry missing from one counts as zero there.
+/// `min_size` applies to the summed entries.
+pub fn merge(
+    files: &[PathBuf],
+    percent_of: PercentOf,
+    min_size: u64,
+    sort: SortBy,
+    top: usize,
+) -> Result<Report> {
+    if percent_of == PercentOf::Parent {
+        bail!("merge doesn't support --percent-of parent");
+    }
+
+    let labels = source_labels(files);
+    let mut group_by: Option<GroupBy> = None;
+    let mut entries: HashMap<PathBuf, DirStats> = HashMap::new();
+    let mut sources = Shares::default();
+    let (mut total_size, mut total_files, mut total_dirs) = (0, 0, 0);
+    let mut incomplete = Vec::new();
+
+    for (file, label) in files.iter().zip(&labels) {
+        let snapshot = snapshot::load(file)?;
+        let grouped = snapshot.group_by()?;
+        match group_by {
+            Some(g) if g != grouped => {
+                bail!("can't merge reports grouped by {g} and {grouped} ({label})")
+            }
+            _ => group_by = Some(grouped),
+        }
+
+        for entry in &snapshot.directories {
+            let key = entry.key()?;
+            let stats = entries.entry(key.clone()).or_insert(DirStats {
+                size: 0,
+                file_count: 0,
+            });
+            stats.size += entry.size;
+            stats.file_count += entry.file_count;
+            sources.add(&key, label, entry.size);
+        }
+        total_size += snapshot.summary.total_size;
+        total_files += snapshot.summary.total_files;
+        total_dirs += snapshot.summary.total_dirs;
+        if let Some(reason) = snapshot.summary.incomplete {
+            incomplete.push(format!("{label}: {reason}"));
+        }
+    }
+
+    let mut sorted: Vec<_> = entries
+        .into_iter()
+        .filter(|(_, stats)| stats.size >= min_size)
+        .collect();
+    sort.sort(&mut sorted);
+    sorted.truncate(top);
+
+    Ok(Report {
+        total_size,
+        total_files,
+        total_dirs,
+        incomplete: (!incomplete.is_empty()).then(|| incomplete.join("; ")),
+        sources: Some(sources),
+        ..Report::new(
+            PathBuf::from(labels.join(", ")),
+            sorted,
+            percent_of,
+            group_by.unwrap_or(GroupBy::Dir),
+            top,
+        )
+    })
+}
+
+/// Each file's stem (`web1` for `web1.json`), or its full path when two files
+/// share a stem.
+fn source_labels(files: &[PathBuf]) -> Vec<String> {
+    let stem = |f: &Path| f.file_stem().map(|s| s.to_string_lossy().to_string());
+    files
+        .iter()
+        .map(|file| {
+            let clashes = files.iter().filter(|f| stem(f) == stem(file)).count() > 1;
+            match stem(file) {
+                Some(s) if !clashes => s,
+                _ => file.display().to_string(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,300 @@
+use std::path::{Path, PathBuf};
+
+/// A mount inside the scan whose contents are already reachable through
+/// another scanned path, such as a bind mount of a directory the walk covers
+/// anyway.
+pub struct BindMount {
+    /// Mount point, relative to the scanned root.
+    pub path: PathBuf,
+    /// Absolute path where the same data is counted.
+    pub same_as: PathBuf,
+}
+
+/// This process's mount table, in the format of /proc/self/mountinfo; empty
+/// where it can't be read, and outside Linux.
+pub fn mountinfo() -> String {
+    if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/proc/self/mountinfo").unwrap_or_default()
+    } else {
+        String::new()
+    }
+}
+
+/// Finds mounts below `root` (canonical) in the `mountinfo` table that would
+/// count data twice. The first place a filesystem subtree is seen, in order
+/// of mount point depth, is the one that keeps it.
+#[cfg(target_os = "linux")]
+pub fn duplicate_mounts(mountinfo: &str, root: &Path) -> Vec<BindMount> {
+    // Later lines mount over earlier ones at the same point.
+    let mut by_point: std::collections::HashMap<PathBuf, Mount> = std::collections::HashMap::new();
+    for mount in mountinfo.lines().filter_map(Mount::parse) {
+        by_point.insert(mount.point.clone(), mount);
+    }
+
+    let Some(containing) = by_point
+        .values()
+        .filter(|m| root.starts_with(&m.point))
+        .max_by_key(|m| m.point.components().count())
+    else {
+        return Vec::new();
+    };
+    // (device, subtree of that device, where the subtree is visible)
+    let mut visible: Vec<(&str, PathBuf, PathBuf)> = vec![(
+        containing.dev.as_str(),
+        containing.root.join(
+            root.strip_prefix(&containing.point)
+                .unwrap_or(Path::new("")),
+        ),
+        root.to_path_buf(),
+    )];
+
+    let mut inner: Vec<&Mount> = by_point
+        .values()
+        .filter(|m| m.point != root && m.point.starts_with(root))
+        .collect();
+    inner.sort_by(|a, b| {
+        (a.point.components().count(), &a.point).cmp(&(b.point.components().count(), &b.point))
+    });
+
+    let mut duplicates: Vec<BindMount> = Vec::new();
+    for mount in inner {
+        // Not walked at all when it sits below a mount that's already skipped.
+        if duplicates
+            .iter()
+            .any(|d| mount.point.starts_with(root.join(&d.path)))
+        {
+            continue;
+        }
+        let original = visible.iter().find_map(|(dev, subtree, at)| {
+            let rest = mount.root.strip_prefix(subtree).ok()?;
+            (*dev == mount.dev).then(|| at.join(rest))
+        });
+        match original {
+            Some(same_as) => duplicates.push(BindMount {
+                path: mount.point.strip_prefix(root).unwrap().to_path_buf(),
+                same_as,
+            }),
+            None => visible.push((mount.dev.as_str(), mount.root.clone(), mount.point.clone())),
+        }
+    }
+    duplicates
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn duplicate_mounts(_mountinfo: &str, _root: &Path) -> Vec<BindMount> {
+    Vec::new()
+}
+
+/// Network and remote filesystem types, which `--local-only` skips.
+pub const NETWORK_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afs",
+    "9p",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "gpfs",
+    "davfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "fuse.glusterfs",
+    "fuse.s3fs",
+];
+
+/// Mount points below `root` (canonical), relative to it, whose filesystem
+/// type is one of `types`, with the type. The root's own mount counts too,
+/// as the empty path.
+pub fn of_types(root: &Path, types: &[String]) -> Vec<(PathBuf, String)> {
+    let all = list(&mountinfo());
+    let mut found: Vec<(PathBuf, String)> = all
+        .iter()
+        .filter(|m| types.contains(&m.fstype))
+        .filter_map(|m| {
+            let relative = m.point.strip_prefix(root).ok()?.to_path_buf();
+            Some((relative, m.fstype.clone()))
+        })
+        .collect();
+    // A root inside (not at) such a mount is on it as well.
+    if let Some(enclosing) = all
+        .into_iter()
+        .filter(|m| root.starts_with(&m.point) && m.point != root)
+        .max_by_key(|m| m.point.components().count())
+        .filter(|m| types.contains(&m.fstype))
+    {
+        found.push((PathBuf::new(), enclosing.fstype));
+    }
+    found
+}
+
+/// A mounted filesystem, for `diskhound fs`.
+pub struct MountEntry {
+    pub point: PathBuf,
+    pub fstype: String,
+    /// Device or remote the filesystem comes from, e.g. `/dev/sda1`.
+    pub source: String,
+}
+
+/// The filesystems in the `mountinfo` table in mount order, without the
+/// second and later mounts of the same device subtree.
+#[cfg(target_os = "linux")]
+pub fn list(mountinfo: &str) -> Vec<MountEntry> {
+    let mut seen = std::collections::HashSet::new();
+    let mut entries = Vec::new();
+    for line in mountinfo.lines() {
+        let Some(mount) = Mount::parse(line) else {
+            continue;
+        };
+        // Optional fields end at a lone `-`, followed by type and source.
+        let mut rest = line.split(" - ").nth(1).unwrap_or("").split(' ');
+        let fstype = rest.next().unwrap_or("").to_string();
+        let source = rest.next().unwrap_or("").to_string();
+        if seen.insert((mount.dev, mount.root)) {
+            entries.push(MountEntry {
+                point: mount.point,
+                fstype,
+                source,
+            });
+        }
+    }
+    entries
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn list(_mountinfo: &str) -> Vec<MountEntry> {
+    Vec::new()
+}
+
+#[cfg(target_os = "linux")]
+struct Mount {
+    /// `major:minor` of the backing device.
+    dev: String,
+    /// Directory of the filesystem that is mounted (not `/` for bind mounts).
+    root: PathBuf,
+    point: PathBuf,
+}
+
+#[cfg(target_os = "linux")]
+impl Mount {
+    /// One line of /proc/self/mountinfo: `id parent major:minor root point ...`.
+    fn parse(line: &str) -> Option<Mount> {
+        let mut fields = line.split(' ');
+        let dev = fields.nth(2)?.to_string();
+        let root = unescape(fields.next()?);
+        let point = unescape(fields.next()?);
+        Some(Mount { dev, root, point })
+    }
+}
+
+/// Undoes the `\040`-style octal escapes the kernel uses for spaces, tabs,
+/// newlines, and backslashes in mountinfo paths.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, 8).ok()
+        });
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                out.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(std::ffi::OsString::from_vec(out))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "20 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw\n";
+
+    fn duplicates(mountinfo: &str, root: &str) -> Vec<(PathBuf, PathBuf)> {
+        duplicate_mounts(mountinfo, Path::new(root))
+            .into_iter()
+            .map(|m| (m.path, m.same_as))
+            .collect()
+    }
+
+    #[test]
+    fn bind_mount_of_a_scanned_directory() {
+        let mountinfo =
+            format!("{ROOT}30 20 8:1 /data/a /data/b rw shared:1 - ext4 /dev/sda1 rw\n");
+        assert_eq!(
+            duplicates(&mountinfo, "/data"),
+            [(PathBuf::from("b"), PathBuf::from("/data/a"))]
+        );
+        // Outside the scan, the original isn't counted, so the bind mount is.
+        assert!(duplicates(&mountinfo, "/data/b").is_empty());
+    }
+
+    #[test]
+    fn second_mount_of_the_same_subtree() {
+        let mountinfo = format!(
+            "{ROOT}\
+             31 20 8:2 / /mnt/x rw - xfs /dev/sdb1 rw\n\
+             32 20 8:2 / /mnt/y rw - xfs /dev/sdb1 rw\n\
+             33 20 8:2 /sub /mnt/z rw - xfs /dev/sdb1 rw\n"
+        );
+        assert_eq!(
+            duplicates(&mountinfo, "/mnt"),
+            [
+                (PathBuf::from("y"), PathBuf::from("/mnt/x")),
+                (PathBuf::from("z"), PathBuf::from("/mnt/x/sub")),
+            ]
+        );
+        let points: Vec<PathBuf> = list(&mountinfo).into_iter().map(|m| m.point).collect();
+        assert_eq!(
+            points,
+            [Path::new("/"), Path::new("/mnt/x"), Path::new("/mnt/z")]
+        );
+    }
+
+    #[test]
+    fn stacked_mount_replaces_the_one_below() {
+        // A bind of /data/a at /data/c, then a fresh filesystem over it.
+        let mountinfo = format!(
+            "{ROOT}\
+             30 20 8:1 /data/a /data/c rw - ext4 /dev/sda1 rw\n\
+             31 30 8:3 / /data/c rw - ext4 /dev/sdc1 rw\n"
+        );
+        assert!(duplicates(&mountinfo, "/data").is_empty());
+    }
+
+    #[test]
+    fn escaped_paths() {
+        let mountinfo =
+            format!("{ROOT}30 20 8:1 /my\\040dir /bind\\011dir rw - ext4 /dev/sda1 rw\n");
+        assert_eq!(
+            duplicates(&mountinfo, "/"),
+            [(PathBuf::from("bind\tdir"), PathBuf::from("/my dir"))]
+        );
+        assert_eq!(unescape("a\\134b\\012c"), PathBuf::from("a\\b\nc"));
+        // Not an escape: kept as written.
+        assert_eq!(unescape("a\\9x\\"), PathBuf::from("a\\9x\\"));
+    }
+
+    #[test]
+    fn type_and_source_follow_the_optional_fields() {
+        let entries =
+            list("36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 shared:2 - nfs4 host:/export rw\n");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].point, Path::new("/mnt2"));
+        assert_eq!(entries[0].fstype, "nfs4");
+        assert_eq!(entries[0].source, "host:/export");
+        assert!(Mount::parse("not a mountinfo line").is_none());
+    }
+}
@@ -0,0 +1,1663 @@
+use crate::breakdown::{Shares, DOMINANT};
+use crate::btrfs::Subvolume;
+use crate::budget::Measured;
+use crate::dupes::DuplicateGroup;
+use crate::filesystem::{Spread, Usage};
+use crate::group::GroupBy;
+use crate::hardlinks::Sharing;
+use crate::histogram::Histogram;
+use crate::i18n::{tr, Msg};
+use crate::icons::Icons;
+use crate::largest::ExtFiles;
+use crate::locale;
+use crate::mounts::BindMount;
+use crate::quota::Quota;
+use crate::readcheck::Unreadable;
+use crate::similar::SimilarPair;
+use crate::timings::Timings;
+use crate::tree::Node;
+use crate::zfs::Dataset;
+use anyhow::{Context, Result};
+use humansize::{format_size, BINARY};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Tsv,
+    Parquet,
+    Arrow,
+    Dot,
+    Folded,
+    Png,
+    Qdirstat,
+    Github,
+    Junit,
+}
+
+impl Format {
+    pub fn needs_tree(self) -> bool {
+        matches!(self, Format::Dot | Format::Folded | Format::Png)
+    }
+
+    /// Formats that list every file rather than the aggregated entries.
+    pub fn needs_files(self) -> bool {
+        matches!(self, Format::Qdirstat)
+    }
+
+    pub fn is_binary(self) -> bool {
+        matches!(self, Format::Parquet | Format::Arrow | Format::Png)
+    }
+}
+
+/// Grouping key of the pseudo-entry that aggregates files directly in the
+/// scanned directory.
+pub const ROOT_FILES: &str = "<files in .>";
+
+pub fn is_root_files(key: &Path) -> bool {
+    key.as_os_str() == ROOT_FILES
+}
+
+/// What the percentage column is relative to.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PercentOf {
+    /// Everything scanned
+    Total,
+    /// The directory one level up (the scanned root for top-level entries)
+    Parent,
+    /// The sum of the entries in the report
+    Shown,
+}
+
+#[derive(Clone, Copy)]
+pub struct DirStats {
+    pub size: u64,
+    pub file_count: u64,
+}
+
+impl DirStats {
+    /// Mean file size, 0 for entries without files.
+    pub fn mean_size(&self) -> u64 {
+        self.size.checked_div(self.file_count).unwrap_or(0)
+    }
+}
+
+/// What entries are ranked by before `--top` applies.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SortBy {
+    /// Total bytes
+    Size,
+    /// Number of files
+    Files,
+    /// Mean file size, to tell a few huge files from millions of small ones
+    Mean,
+}
+
+impl SortBy {
+    /// Largest first.
+    pub fn sort(self, entries: &mut [(PathBuf, DirStats)]) {
+        let key = |stats: &DirStats| match self {
+            SortBy::Size => stats.size,
+            SortBy::Files => stats.file_count,
+            SortBy::Mean => stats.mean_size(),
+        };
+        entries.sort_by_key(|(_, stats)| std::cmp::Reverse((key(stats), stats.size)));
+    }
+}
+
+/// A counted file, for formats that list every file.
+pub struct ListedFile {
+    /// Relative to the scanned root.
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// How individual file sizes are distributed over the whole scan. The
+/// percentiles are estimates within 1%.
+pub struct FileSizes {
+    pub median: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub largest: (PathBuf, u64),
+}
+
+/// What the walk does with sockets, FIFOs, and device nodes.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecialPolicy {
+    /// Count them like files, at the size they report (usually nothing)
+    Count,
+    /// Leave them out
+    Skip,
+    /// Leave them out of the totals and list them after the report
+    List,
+}
+
+/// Sockets, FIFOs, and device nodes the walk came across.
+pub struct SpecialFiles {
+    pub policy: SpecialPolicy,
+    /// How many of each kind (`socket`, `fifo`, `block device`, `character
+    /// device`).
+    pub counts: BTreeMap<&'static str, u64>,
+    /// With `--special-files list`, each one and its kind, by path.
+    pub files: Vec<(PathBuf, &'static str)>,
+}
+
+impl SpecialFiles {
+    pub fn count(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
+
+/// Files left out of the aggregation by `--skip-files-over`, largest first.
+pub struct Oversized {
+    pub limit: u64,
+    pub files: Vec<(PathBuf, u64)>,
+}
+
+/// Everything the renderers need, already filtered, sorted, and truncated.
+pub struct Report {
+    pub root: PathBuf,
+    /// When the walk started; `None` for merged and imported reports.
+    pub scanned_at: Option<SystemTime>,
+    /// With `--changed-since`, when the earlier scan was taken; only files
+    /// changed after it were counted.
+    pub changed_since: Option<SystemTime>,
+    /// Space on the scanned filesystem when the walk started; `None` for
+    /// merged and imported reports and where it can't be read.
+    pub filesystem: Option<Usage>,
+    /// Bytes per filesystem overall and within each entry; `None` unless the
+    /// scan crossed a mount point.
+    pub spread: Option<Spread>,
+    pub entries: Vec<(PathBuf, DirStats)>,
+    pub percent_of: PercentOf,
+    pub group_by: GroupBy,
+    /// Cumulative size of every directory, used as the baseline for
+    /// `--percent-of parent`; empty otherwise.
+    pub parent_sizes: HashMap<PathBuf, u64>,
+    pub total_size: u64,
+    pub total_files: u64,
+    pub total_dirs: u64,
+    /// Why the walk stopped early, if it did.
+    pub incomplete: Option<String>,
+    /// Mounts left out because their data is counted at another path.
+    pub bind_mounts: Vec<BindMount>,
+    /// Bytes each entry would shrink by if duplicates were deduplicated
+    /// (`--dupes`).
+    pub reclaimable: Option<HashMap<PathBuf, u64>>,
+    pub duplicates: Option<Vec<DuplicateGroup>>,
+    /// Directories with identical subtrees (`--dupe-trees`).
+    pub duplicate_trees: Option<Vec<DuplicateGroup>>,
+    /// Pairs of mostly identical files (`--similar`).
+    pub similar: Option<Vec<SimilarPair>>,
+    /// Files that failed `--check-read`.
+    pub unreadable: Option<Unreadable>,
+    pub oversized: Option<Oversized>,
+    /// Sockets, FIFOs, and device nodes found; `None` when there were none
+    /// and for merged and imported reports.
+    pub special_files: Option<SpecialFiles>,
+    /// Every counted file (`--files`, `--format qdirstat`).
+    pub files: Option<Vec<ListedFile>>,
+    /// Bytes per extension for each entry (`--breakdown ext`).
+    pub extensions: Option<Shares>,
+    /// File size distribution of each entry (`--sparkline`).
+    pub histograms: Option<HashMap<PathBuf, Histogram>>,
+    /// File size distribution of the whole scan; `None` for reports that
+    /// weren't scanned.
+    pub size_histogram: Option<Histogram>,
+    /// `None` when no files were counted or the report wasn't scanned.
+    pub file_sizes: Option<FileSizes>,
+    /// The largest files of the top extensions (`ext-files`).
+    pub largest_by_ext: Option<Vec<ExtFiles>>,
+    /// Modification time of the newest file in each entry (`stale`).
+    pub modified: Option<HashMap<PathBuf, SystemTime>>,
+    /// Bytes each entry shares with others through hardlinks (`--hardlinks`).
+    pub hardlinks: Option<HashMap<PathBuf, Sharing>>,
+    /// Filesystem quotas of the owners in a `--group-by owner` view, where
+    /// quotas are enabled and readable.
+    pub quotas: Option<HashMap<PathBuf, Quota>>,
+    /// Btrfs subvolumes and snapshots below the root; `None` without any.
+    pub subvolumes: Option<Vec<Subvolume>>,
+    /// ZFS datasets the scan walked, with `zfs list` sizes; `None` off ZFS.
+    pub datasets: Option<Vec<Dataset>>,
+    /// With `--auto-exclude`, the presets that were applied and how many
+    /// directories each skipped, by preset name.
+    pub auto_excluded: Option<Vec<(String, u64)>>,
+    /// Size budgets that apply to the scan, with what was counted against
+    /// them; `None` without any.
+    pub budgets: Option<Vec<Measured>>,
+    /// Where the scan spent its time (`--timings`).
+    pub timings: Option<Timings>,
+    /// Bytes per snapshot within each entry, for merged reports.
+    pub sources: Option<Shares>,
+    /// Whether tsv output starts with a header row (off with `--no-header`).
+    pub header: bool,
+    /// Icon set the table prefixes entries with (`--icons`).
+    pub icons: Option<Icons>,
+    /// Whether the table shows each entry's mean file size (`--mean-size`).
+    pub mean_size: bool,
+    /// Cumulative directory tree, built only for tree-shaped formats.
+    pub tree: Option<Node>,
+    pub top: usize,
+}
+
+impl Report {
+    /// A report of `entries` with zero totals and nothing else computed,
+    /// for filling in the rest with struct update syntax.
+    pub fn new(
+        root: PathBuf,
+        entries: Vec<(PathBuf, DirStats)>,
+        percent_of: PercentOf,
+        group_by: GroupBy,
+        top: usize,
+    ) -> Self {
+        Report {
+            root,
+            scanned_at: None,
+            changed_since: None,
+            filesystem: None,
+            spread: None,
+            entries,
+            percent_of,
+            group_by,
+            parent_sizes: HashMap::new(),
+            total_size: 0,
+            total_files: 0,
+            total_dirs: 0,
+            incomplete: None,
+            bind_mounts: Vec::new(),
+            reclaimable: None,
+            duplicates: None,
+            duplicate_trees: None,
+            similar: None,
+            unreadable: None,
+            oversized: None,
+            special_files: None,
+            files: None,
+            extensions: None,
+            histograms: None,
+            size_histogram: None,
+            file_sizes: None,
+            largest_by_ext: None,
+            modified: None,
+            hardlinks: None,
+            quotas: None,
+            subvolumes: None,
+            datasets: None,
+            auto_excluded: None,
+            budgets: None,
+            timings: None,
+            sources: None,
+            header: true,
+            icons: None,
+            mean_size: false,
+            tree: None,
+            top,
+        }
+    }
+
+    /// Percentage of each entry, in `entries` order, against `percent_of`.
+    pub fn percentages(&self) -> Vec<f64> {
+        let shown: u64 = self.entries.iter().map(|(_, s)| s.size).sum();
+        self.entries
+            .iter()
+            .map(|(name, stats)| {
+                let base = match self.percent_of {
+                    PercentOf::Total => self.total_size,
+                    PercentOf::Shown => shown,
+                    PercentOf::Parent => {
+                        match name.parent().filter(|p| !p.as_os_str().is_empty()) {
+                            Some(parent) => self.parent_sizes.get(parent).copied().unwrap_or(0),
+                            None => self.total_size,
+                        }
+                    }
+                };
+                if base > 0 {
+                    (stats.size as f64 / base as f64) * 100.0
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+}
+
+/// Version of the JSON output format. Adding fields keeps the version; removing,
+/// renaming, or changing the meaning or type of a field bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, JsonSchema)]
+struct JsonOutput {
+    schema_version: u32,
+    directories: Vec<JsonDirEntry>,
+    summary: JsonSummary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicates: Option<Vec<JsonDuplicateGroup>>,
+    /// Directories whose whole subtrees are identical; `size` is one copy's
+    /// size.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_trees: Option<Vec<JsonDuplicateGroup>>,
+    /// Pairs of files that are mostly identical, most shared bytes first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    similar_files: Option<Vec<JsonSimilarPair>>,
+    /// With `--check-read`, files whose first block couldn't be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unreadable_files: Option<Vec<JsonUnreadable>>,
+    /// Mount points that were not walked because the same data is counted
+    /// at `same_as` (bind mounts, filesystems mounted twice).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    bind_mounts: Vec<JsonBindMount>,
+    /// Every filesystem the scan crossed into, largest first; absent when
+    /// everything was on one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filesystems: Option<Vec<JsonFilesystemTotal>>,
+    /// Btrfs subvolumes and snapshots below the root, in walk order. Their
+    /// walked sizes count data shared between snapshots in full.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subvolumes: Option<Vec<JsonSubvolume>>,
+    /// ZFS datasets the scan walked, the root's own first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datasets: Option<Vec<JsonDataset>>,
+    /// With `--auto-exclude`, the presets applied to projects in the tree.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto_excluded: Option<Vec<JsonAutoExcluded>>,
+    /// Files excluded from all totals by `--skip-files-over`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped_files: Option<JsonSkippedFiles>,
+    /// Sockets, FIFOs, and device nodes the walk found, and what it did with
+    /// them (`--special-files`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    special_files: Option<JsonSpecialFiles>,
+    /// Size budgets from `--budget` and the config file that apply to this
+    /// scan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    budgets: Option<Vec<JsonBudget>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSubvolume {
+    /// Relative to the scanned root, like directory names.
+    path: String,
+    /// Tree ID, as in `btrfs subvolume list`.
+    id: Option<u64>,
+    snapshot: Option<bool>,
+    /// Bytes walked below it, not counting nested subvolumes.
+    size: u64,
+    /// From quota groups (`--btrfs-qgroups`).
+    referenced: Option<u64>,
+    /// Bytes only this subvolume references, which deleting it would free.
+    exclusive: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonDataset {
+    /// E.g. `tank/home`.
+    name: String,
+    /// Mount point relative to the scanned root; `.` for the root's own.
+    path: String,
+    /// Bytes walked on it (apparent sizes).
+    size: u64,
+    /// The rest are from `zfs list`, absent where it couldn't be run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    used: Option<u64>,
+    /// On-disk bytes of its own data, after compression.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    referenced: Option<u64>,
+    /// `referenced` before compression, comparable to `size`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logical_referenced: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compress_ratio: Option<f64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonAutoExcluded {
+    preset: String,
+    /// Directories skipped; their contents weren't walked or counted.
+    directories: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonBudget {
+    /// Relative to the scanned root; `.` for the whole scan.
+    path: String,
+    limit: u64,
+    size: u64,
+    exceeded: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonBindMount {
+    /// Relative to the scanned root, like directory names.
+    path: String,
+    same_as: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonFilesystemTotal {
+    mount_point: String,
+    /// Filesystem type from the mount table, e.g. `ext4` or `nfs4`; empty
+    /// when it couldn't be read.
+    #[serde(rename = "type")]
+    fstype: String,
+    /// The mounted device or share, e.g. `/dev/sda1` or `//nas/media`.
+    source: String,
+    size: u64,
+    size_human: String,
+    file_count: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSkippedFiles {
+    limit: u64,
+    count: usize,
+    total_size: u64,
+    /// The largest skipped files, up to `--top`.
+    files: Vec<JsonFile>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSpecialFiles {
+    policy: SpecialPolicy,
+    count: u64,
+    /// How many of each kind: `socket`, `fifo`, `block device`, `character
+    /// device`.
+    kinds: BTreeMap<String, u64>,
+    /// With `--special-files list`, the first ones by path, up to `--top`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    files: Vec<JsonSpecialFile>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSpecialFile {
+    path: String,
+    /// Raw path bytes, base64-encoded; only present when `path` isn't valid
+    /// UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_base64: Option<String>,
+    kind: &'static str,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonFile {
+    path: String,
+    /// Raw path bytes, base64-encoded; only present when `path` isn't valid
+    /// UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_base64: Option<String>,
+    size: u64,
+    size_human: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonDirEntry {
+    name: String,
+    /// Raw name bytes, base64-encoded; only present when `name` isn't valid UTF-8
+    /// and had to be converted lossily.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_base64: Option<String>,
+    /// Set on the pseudo-entry aggregating files directly in the scanned
+    /// directory; absent on real directories.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    root_files: bool,
+    size: u64,
+    size_human: String,
+    file_count: u64,
+    /// `size / file_count`, 0 for entries without files.
+    mean_file_size: u64,
+    percentage: f64,
+    /// Largest file extensions in this entry (`--breakdown ext`), with their
+    /// percentage of the entry's file bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<Vec<JsonExtension>>,
+    /// With `--sparkline`, file counts per size bucket: under 1 KiB, then
+    /// one bucket per factor of 4 (1-4 KiB, 4-16 KiB, ...) up to 1 GiB and over.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_histogram: Option<Vec<u64>>,
+    /// With `--dupes`, bytes this entry would shrink by if duplicate files
+    /// across the scan were deduplicated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reclaimable: Option<u64>,
+    /// With `--check-read`, files in this entry whose first block couldn't
+    /// be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unreadable: Option<u64>,
+    /// With `--hardlinks`, set on entries that share data with others.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hardlinks: Option<JsonSharing>,
+    /// In `--group-by owner` views, the owner's quota on the scanned
+    /// filesystem. `used` covers the whole filesystem, not just the scan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quota: Option<JsonQuota>,
+    /// In merged reports, bytes of this entry contributed by each snapshot,
+    /// largest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sources: Option<Vec<JsonSource>>,
+    /// When the scan spans several filesystems, bytes of this entry on each,
+    /// largest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filesystems: Option<Vec<JsonEntryFilesystem>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonEntryFilesystem {
+    mount_point: String,
+    /// Filesystem type, as in the report's `filesystems`.
+    #[serde(rename = "type")]
+    fstype: String,
+    source: String,
+    size: u64,
+    percentage: f64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonQuota {
+    used: u64,
+    soft_limit: Option<u64>,
+    hard_limit: Option<u64>,
+    /// `used` against the soft limit, or the hard one without a soft limit.
+    percentage: Option<f64>,
+    /// Whether `percentage` is at least 90.
+    near_limit: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSource {
+    source: String,
+    size: u64,
+    percentage: f64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSharing {
+    /// Bytes of this entry that are also linked from somewhere else.
+    shared: u64,
+    shared_human: String,
+    /// Largest sharing partners first.
+    with: Vec<JsonSharedWith>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSharedWith {
+    /// Name of the other entry; `null` for links outside the scanned tree.
+    name: Option<String>,
+    size: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonExtension {
+    /// Lowercased, with the leading dot; `(none)` for files without one.
+    extension: String,
+    size: u64,
+    percentage: f64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSummary {
+    /// The scanned directory, as given on the command line
+    root: String,
+    /// When the scan started, in seconds since the Unix epoch; absent for
+    /// merged and imported reports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scanned_at: Option<u64>,
+    /// With `--changed-since`, when the earlier scan started, in seconds
+    /// since the Unix epoch; only files changed after it are counted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changed_since: Option<u64>,
+    /// Space on the scanned filesystem when the scan started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filesystem: Option<Usage>,
+    total_size: u64,
+    total_size_human: String,
+    total_files: u64,
+    total_dirs: u64,
+    shown: usize,
+    /// Baseline of each entry's `percentage`
+    percent_of: PercentOf,
+    /// What entries are grouped by, as passed to `--group-by`
+    group_by: String,
+    /// Why the scan stopped early; absent when every entry was counted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    incomplete: Option<String>,
+    /// Distribution of individual file sizes; absent when no files were
+    /// counted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_sizes: Option<JsonFileSizes>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonFileSizes {
+    /// Percentiles are streaming estimates, within 1% of the exact size.
+    median: u64,
+    p90: u64,
+    p99: u64,
+    largest: JsonFile,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonDuplicateGroup {
+    size: u64,
+    size_human: String,
+    hash: String,
+    reclaimable: u64,
+    paths: Vec<String>,
+    /// Raw bytes of every path, base64-encoded and in the same order as `paths`;
+    /// only present when one of them isn't valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paths_base64: Option<Vec<String>>,
+}
+
+fn json_duplicates(groups: &[DuplicateGroup], top: usize) -> Vec<JsonDuplicateGroup> {
+    groups
+        .iter()
+        .take(top)
+        .map(|g| JsonDuplicateGroup {
+            size: g.size,
+            size_human: format_size(g.size, BINARY),
+            hash: g.hash.clone(),
+            reclaimable: g.reclaimable(),
+            paths: g
+                .paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            paths_base64: g
+                .paths
+                .iter()
+                .any(|p| p.to_str().is_none())
+                .then(|| g.paths.iter().map(|p| encode_raw(p.as_os_str())).collect()),
+        })
+        .collect()
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonUnreadable {
+    path: String,
+    /// Raw path bytes, base64-encoded; only present when `path` isn't valid
+    /// UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_base64: Option<String>,
+    error: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSimilarPair {
+    paths: [String; 2],
+    /// Raw bytes of both paths, base64-encoded; only present when one of them
+    /// isn't valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paths_base64: Option<[String; 2]>,
+    sizes: [u64; 2],
+    /// Estimated share of content in common, 0-100.
+    similarity: f64,
+    /// Estimated bytes saved by storing one file as a delta of the other.
+    shared: u64,
+}
+
+/// Grouping keys shown with `/` separators on every platform. Non-UTF-8
+/// components are converted lossily; JSON carries the raw bytes alongside.
+pub fn display_name(key: &Path) -> String {
+    key.iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A dataset's mount point, `.` for the one the root is on.
+fn dataset_path(path: &Path) -> String {
+    if path.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        display_name(path)
+    }
+}
+
+pub fn raw_base64(s: &OsStr) -> Option<String> {
+    s.to_str().is_none().then(|| encode_raw(s))
+}
+
+pub fn encode_raw(s: &OsStr) -> String {
+    use base64::Engine;
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(s).to_vec();
+    // Windows names are UTF-16; encode the code units little-endian.
+    #[cfg(windows)]
+    let bytes: Vec<u8> = std::os::windows::ffi::OsStrExt::encode_wide(s)
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    #[cfg(not(any(unix, windows)))]
+    let bytes = s.to_string_lossy().into_owned().into_bytes();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Inverse of [`encode_raw`], for reading saved reports back.
+pub fn decode_raw(encoded: &str) -> Result<OsString> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+    #[cfg(unix)]
+    let raw = std::os::unix::ffi::OsStringExt::from_vec(bytes);
+    #[cfg(windows)]
+    let raw = {
+        let wide: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        std::os::windows::ffi::OsStringExt::from_wide(&wide)
+    };
+    #[cfg(not(any(unix, windows)))]
+    let raw = OsString::from(String::from_utf8_lossy(&bytes).into_owned());
+    Ok(raw)
+}
+
+pub fn write(w: &mut dyn Write, format: Format, report: &Report) -> Result<()> {
+    match format {
+        Format::Table => write_table(w, report),
+        Format::Json => write_json(w, report),
+        Format::Tsv => write_tsv(w, report),
+        #[cfg(feature = "parquet")]
+        Format::Parquet => crate::columnar::write_parquet(w, report),
+        #[cfg(not(feature = "parquet"))]
+        Format::Parquet => anyhow::bail!("diskhound was built without parquet support"),
+        #[cfg(feature = "arrow")]
+        Format::Arrow => crate::columnar::write_arrow(w, report),
+        #[cfg(not(feature = "arrow"))]
+        Format::Arrow => anyhow::bail!("diskhound was built without arrow support"),
+        Format::Dot => write_dot(w, report),
+        Format::Folded => write_folded(w, report),
+        #[cfg(feature = "png")]
+        Format::Png => match &report.tree {
+            Some(tree) => crate::treemap::write_png(w, tree),
+            None => anyhow::bail!("png output requires a directory tree"),
+        },
+        #[cfg(not(feature = "png"))]
+        Format::Png => anyhow::bail!("diskhound was built without png support"),
+        Format::Qdirstat => crate::qdirstat::write(w, report),
+        Format::Github => crate::github::write(w, report),
+        Format::Junit => crate::junit::write(w, report),
+    }
+}
+
+pub fn write_json(w: &mut dyn Write, report: &Report) -> Result<()> {
+    let directories: Vec<JsonDirEntry> = report
+        .entries
+        .iter()
+        .zip(report.percentages())
+        .map(|((name, stats), percentage)| JsonDirEntry {
+            name: display_name(name),
+            name_base64: raw_base64(name.as_os_str()),
+            root_files: is_root_files(name),
+            size: stats.size,
+            size_human: format_size(stats.size, BINARY),
+            file_count: stats.file_count,
+            mean_file_size: stats.mean_size(),
+            percentage,
+            extensions: report.extensions.as_ref().map(|exts| {
+                exts.dominant(name, DOMINANT)
+                    .into_iter()
+                    .map(|(ext, size, percentage)| JsonExtension {
+                        extension: ext.to_string(),
+                        size,
+                        percentage,
+                    })
+                    .collect()
+            }),
+            size_histogram: report.histograms.as_ref().map(|histograms| {
+                let histogram = histograms.get(name).copied().unwrap_or_default();
+                histogram.counts.to_vec()
+            }),
+            reclaimable: report
+                .reclaimable
+                .as_ref()
+                .map(|r| r.get(name).copied().unwrap_or(0)),
+            unreadable: report
+                .unreadable
+                .as_ref()
+                .map(|u| u.by_key.get(name).copied().unwrap_or(0)),
+            hardlinks: report
+                .hardlinks
+                .as_ref()
+                .and_then(|links| links.get(name))
+                .map(|sharing| JsonSharing {
+                    shared: sharing.shared,
+                    shared_human: format_size(sharing.shared, BINARY),
+                    with: sharing
+                        .with
+                        .iter()
+                        .map(|(other, size)| JsonSharedWith {
+                            name: other.as_deref().map(display_name),
+                            size: *size,
+                        })
+                        .collect(),
+                }),
+            quota: report
+                .quotas
+                .as_ref()
+                .and_then(|quotas| quotas.get(name))
+                .map(|quota| JsonQuota {
+                    used: quota.used,
+                    soft_limit: quota.soft,
+                    hard_limit: quota.hard,
+                    percentage: quota.percentage(),
+                    near_limit: quota.near_limit(),
+                }),
+            sources: report.sources.as_ref().map(|sources| {
+                sources
+                    .dominant(name, usize::MAX)
+                    .into_iter()
+                    .map(|(source, size, percentage)| JsonSource {
+                        source: source.to_string(),
+                        size,
+                        percentage,
+                    })
+                    .collect()
+            }),
+            filesystems: report.spread.as_ref().map(|spread| {
+                spread
+                    .shares
+                    .dominant(name, usize::MAX)
+                    .into_iter()
+                    .map(|(mount_point, size, percentage)| JsonEntryFilesystem {
+                        mount_point: mount_point.to_string(),
+                        fstype: spread
+                            .at(mount_point)
+                            .map(|fs| fs.fstype.clone())
+                            .unwrap_or_default(),
+                        source: spread
+                            .at(mount_point)
+                            .map(|fs| fs.source.clone())
+                            .unwrap_or_default(),
+                        size,
+                        percentage,
+                    })
+                    .collect()
+            }),
+        })
+        .collect();
+
+    let output = JsonOutput {
+        schema_version: SCHEMA_VERSION,
+        summary: JsonSummary {
+            root: report.root.to_string_lossy().to_string(),
+            scanned_at: report
+                .scanned_at
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            changed_since: report
+                .changed_since
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            filesystem: report.filesystem.clone(),
+            total_size: report.total_size,
+            total_size_human: format_size(report.total_size, BINARY),
+            total_files: report.total_files,
+            total_dirs: report.total_dirs,
+            shown: directories.len(),
+            percent_of: report.percent_of,
+            group_by: report.group_by.to_string(),
+            incomplete: report.incomplete.clone(),
+            file_sizes: report.file_sizes.as_ref().map(|sizes| JsonFileSizes {
+                median: sizes.median,
+                p90: sizes.p90,
+                p99: sizes.p99,
+                largest: JsonFile {
+                    path: sizes.largest.0.to_string_lossy().to_string(),
+                    path_base64: raw_base64(sizes.largest.0.as_os_str()),
+                    size: sizes.largest.1,
+                    size_human: format_size(sizes.largest.1, BINARY),
+                },
+            }),
+        },
+        directories,
+        duplicates: report
+            .duplicates
+            .as_ref()
+            .map(|groups| json_duplicates(groups, report.top)),
+        duplicate_trees: report
+            .duplicate_trees
+            .as_ref()
+            .map(|groups| json_duplicates(groups, report.top)),
+        unreadable_files: report.unreadable.as_ref().map(|u| {
+            u.files
+                .iter()
+                .map(|(path, error)| JsonUnreadable {
+                    path: path.to_string_lossy().to_string(),
+                    path_base64: raw_base64(path.as_os_str()),
+                    error: error.clone(),
+                })
+                .collect()
+        }),
+        similar_files: report.similar.as_ref().map(|pairs| {
+            pairs
+                .iter()
+                .take(report.top)
+                .map(|pair| JsonSimilarPair {
+                    paths: pair
+                        .paths
+                        .each_ref()
+                        .map(|p| p.to_string_lossy().to_string()),
+                    paths_base64: pair
+                        .paths
+                        .iter()
+                        .any(|p| p.to_str().is_none())
+                        .then(|| pair.paths.each_ref().map(|p| encode_raw(p.as_os_str()))),
+                    sizes: pair.sizes,
+                    similarity: pair.similarity,
+                    shared: pair.shared,
+                })
+                .collect()
+        }),
+        bind_mounts: report
+            .bind_mounts
+            .iter()
+            .map(|m| JsonBindMount {
+                path: display_name(&m.path),
+                same_as: m.same_as.to_string_lossy().to_string(),
+            })
+            .collect(),
+        filesystems: report.spread.as_ref().map(|spread| {
+            spread
+                .filesystems
+                .iter()
+                .map(|fs| JsonFilesystemTotal {
+                    mount_point: fs.mount_point.to_string_lossy().to_string(),
+                    fstype: fs.fstype.clone(),
+                    source: fs.source.clone(),
+                    size: fs.size,
+                    size_human: format_size(fs.size, BINARY),
+                    file_count: fs.file_count,
+                })
+                .collect()
+        }),
+        subvolumes: report.subvolumes.as_ref().map(|subvolumes| {
+            subvolumes
+                .iter()
+                .map(|s| JsonSubvolume {
+                    path: display_name(&s.path),
+                    id: s.id,
+                    snapshot: s.snapshot,
+                    size: s.size,
+                    referenced: s.referenced,
+                    exclusive: s.exclusive,
+                })
+                .collect()
+        }),
+        datasets: report.datasets.as_ref().map(|datasets| {
+            datasets
+                .iter()
+                .map(|d| JsonDataset {
+                    name: d.name.clone(),
+                    path: dataset_path(&d.path),
+                    size: d.size,
+                    used: d.properties.map(|p| p.used),
+                    referenced: d.properties.map(|p| p.referenced),
+                    logical_referenced: d.properties.map(|p| p.logical_referenced),
+                    compress_ratio: d.properties.map(|p| p.compress_ratio()),
+                })
+                .collect()
+        }),
+        auto_excluded: report.auto_excluded.as_ref().map(|presets| {
+            presets
+                .iter()
+                .map(|(preset, directories)| JsonAutoExcluded {
+                    preset: preset.clone(),
+                    directories: *directories,
+                })
+                .collect()
+        }),
+        skipped_files: report.oversized.as_ref().map(|o| JsonSkippedFiles {
+            limit: o.limit,
+            count: o.files.len(),
+            total_size: o.files.iter().map(|(_, s)| s).sum(),
+            files: o
+                .files
+                .iter()
+                .take(report.top)
+                .map(|(path, size)| JsonFile {
+                    path: path.to_string_lossy().to_string(),
+                    path_base64: raw_base64(path.as_os_str()),
+                    size: *size,
+                    size_human: format_size(*size, BINARY),
+                })
+                .collect(),
+        }),
+        special_files: report.special_files.as_ref().map(|s| JsonSpecialFiles {
+            policy: s.policy,
+            count: s.count(),
+            kinds: s.counts.iter().map(|(k, n)| (k.to_string(), *n)).collect(),
+            files: s
+                .files
+                .iter()
+                .take(report.top)
+                .map(|(path, kind)| JsonSpecialFile {
+                    path: path.to_string_lossy().to_string(),
+                    path_base64: raw_base64(path.as_os_str()),
+                    kind,
+                })
+                .collect(),
+        }),
+        budgets: report.budgets.as_ref().map(|budgets| {
+            budgets
+                .iter()
+                .map(|m| JsonBudget {
+                    path: m.name(),
+                    limit: m.budget.limit,
+                    size: m.size,
+                    exceeded: m.exceeded(),
+                })
+                .collect()
+        }),
+    };
+
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
+
+/// One entry (or, with `--files`, one file) per line. Sizes are plain bytes
+/// and names keep `/` separators; tabs, newlines, and backslashes in names are
+/// backslash-escaped so every line splits cleanly on tabs.
+pub fn write_tsv(w: &mut dyn Write, report: &Report) -> Result<()> {
+    fn field(name: &Path) -> String {
+        display_name(name)
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    }
+
+    if let Some(files) = &report.files {
+        if report.header {
+            writeln!(w, "path\tsize")?;
+        }
+        for file in files {
+            writeln!(w, "{}\t{}", field(&file.path), file.size)?;
+        }
+        return Ok(());
+    }
+
+    if report.header {
+        writeln!(w, "name\tsize\tfile_count\tpercentage")?;
+    }
+    for ((name, stats), percentage) in report.entries.iter().zip(report.percentages()) {
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{:.2}",
+            field(name),
+            stats.size,
+            stats.file_count,
+            percentage
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_json_schema(w: &mut dyn Write) -> Result<()> {
+    let schema = schemars::schema_for!(JsonOutput);
+    writeln!(w, "{}", serde_json::to_string_pretty(&schema)?)?;
+    Ok(())
+}
+
+pub fn write_table(w: &mut dyn Write, report: &Report) -> Result<()> {
+    if report.entries.is_empty() {
+        let root = format!("{:?}", report.root);
+        writeln!(w, "{}", tr(Msg::NoEntries, &[("root", &root)]))?;
+    } else {
+        let entries = &report.entries;
+        let names: Vec<String> = entries.iter().map(|(n, _)| display_name(n)).collect();
+        let max_name_len = names.iter().map(|n| n.chars().count()).max().unwrap_or(10);
+        let max_size = entries.iter().map(|(_, s)| s.size).max().unwrap_or(1);
+        let bar_width = 20;
+
+        let percentages = report.percentages();
+        for ((name, (key, stats)), percentage) in names.iter().zip(entries).zip(percentages) {
+            let filled = if max_size > 0 {
+                ((stats.size as f64 / max_size as f64) * bar_width as f64).round() as usize
+            } else {
+                0
+            };
+            let empty = bar_width - filled;
+            let bar: String = "\u{2588}".repeat(filled) + &"\u{2591}".repeat(empty);
+
+            if let Some(icons) = report.icons {
+                write!(w, "  {}", icons.for_entry(key, report.group_by))?;
+            }
+            write!(
+                w,
+                "  {:<width$}  {}  {:>10}  {:>5}%  ",
+                name,
+                bar,
+                locale::size(stats.size),
+                locale::fixed(percentage, 1),
+                width = max_name_len,
+            )?;
+            if let Some(histograms) = &report.histograms {
+                let histogram = histograms.get(key).copied().unwrap_or_default();
+                write!(w, "{}  ", histogram.sparkline())?;
+            }
+            if report.mean_size {
+                let size = format!("{:>10}", locale::size(stats.mean_size()));
+                write!(w, "{}  ", tr(Msg::MeanSize, &[("size", &size)]))?;
+            }
+            write!(
+                w,
+                "{}",
+                tr(Msg::Files, &[("count", &locale::count(stats.file_count))])
+            )?;
+            if let Some(exts) = &report.extensions {
+                let parts: Vec<String> = exts
+                    .dominant(key, DOMINANT)
+                    .into_iter()
+                    .map(|(ext, _, percentage)| format!("{percentage:.0}% {ext}"))
+                    .collect();
+                if !parts.is_empty() {
+                    write!(w, "  {}", parts.join(", "))?;
+                }
+            }
+            if let Some(sources) = &report.sources {
+                let parts: Vec<String> = sources
+                    .dominant(key, DOMINANT)
+                    .into_iter()
+                    .map(|(source, _, percentage)| format!("{percentage:.0}% {source}"))
+                    .collect();
+                write!(w, "  {}", parts.join(", "))?;
+            }
+            if let Some(spread) = &report.spread {
+                let parts: Vec<String> = spread
+                    .shares
+                    .dominant(key, DOMINANT)
+                    .into_iter()
+                    .map(|(mount, _, percentage)| {
+                        match spread.at(mount).filter(|fs| !fs.fstype.is_empty()) {
+                            Some(fs) => format!("{percentage:.0}% {mount} ({})", fs.fstype),
+                            None => format!("{percentage:.0}% {mount}"),
+                        }
+                    })
+                    .collect();
+                if !parts.is_empty() {
+                    write!(w, "  {}", parts.join(", "))?;
+                }
+            }
+            if let Some(bytes) = report.reclaimable.as_ref().and_then(|r| r.get(key)) {
+                let size = locale::size(*bytes);
+                write!(w, "  {}", tr(Msg::Reclaimable, &[("size", &size)]))?;
+            }
+            if let Some(&count) = report.unreadable.as_ref().and_then(|u| u.by_key.get(key)) {
+                let count = locale::count(count);
+                write!(w, "  {}", tr(Msg::UnreadableCount, &[("count", &count)]))?;
+            }
+            if let Some(quota) = report.quotas.as_ref().and_then(|q| q.get(key)) {
+                if let (Some(limit), Some(percent)) = (quota.limit(), quota.percentage()) {
+                    let line = tr(
+                        Msg::Quota,
+                        &[
+                            ("used", &locale::size(quota.used)),
+                            ("limit", &locale::size(limit)),
+                            ("percent", &locale::fixed(percent, 0)),
+                        ],
+                    );
+                    write!(w, "  {line}")?;
+                    if quota.near_limit() {
+                        write!(w, ", {}", tr(Msg::NearQuota, &[]))?;
+                    }
+                }
+            }
+            if let Some(sharing) = report.hardlinks.as_ref().and_then(|l| l.get(key)) {
+                let partner = match &sharing.with[0].0 {
+                    Some(other) => display_name(other),
+                    None => tr(Msg::OutsideScan, &[]),
+                };
+                let size = locale::size(sharing.shared);
+                let shared = tr(Msg::SharedWith, &[("size", &size), ("partner", &partner)]);
+                write!(w, "  {shared}")?;
+                if sharing.with.len() > 1 {
+                    let count = (sharing.with.len() - 1).to_string();
+                    write!(w, " {}", tr(Msg::More, &[("count", &count)]))?;
+                }
+            }
+            writeln!(w)?;
+        }
+
+        writeln!(w)?;
+        let total = tr(
+            Msg::Total,
+            &[
+                ("size", &locale::size(report.total_size)),
+                ("files", &locale::count(report.total_files)),
+                ("dirs", &locale::count(report.total_dirs)),
+                ("shown", &entries.len().to_string()),
+            ],
+        );
+        writeln!(w, "{total}")?;
+        if let Some(sizes) = &report.file_sizes {
+            let line = tr(
+                Msg::FileSizes,
+                &[
+                    ("median", &locale::size(sizes.median)),
+                    ("p90", &locale::size(sizes.p90)),
+                    ("p99", &locale::size(sizes.p99)),
+                    ("largest", &locale::size(sizes.largest.1)),
+                    ("path", &sizes.largest.0.display().to_string()),
+                ],
+            );
+            writeln!(w, "{line}")?;
+        }
+    }
+
+    if let Some(reason) = &report.incomplete {
+        writeln!(w, "{}", tr(Msg::Incomplete, &[("reason", reason)]))?;
+    }
+    if let Some(since) = report.changed_since {
+        let now = report.scanned_at.unwrap_or_else(SystemTime::now);
+        let days = now.duration_since(since).unwrap_or_default().as_secs_f64() / 86_400.0;
+        let days = locale::fixed(days, 1);
+        writeln!(w, "{}", tr(Msg::ChangedSince, &[("days", &days)]))?;
+    }
+    if !report.bind_mounts.is_empty() {
+        writeln!(w)?;
+        writeln!(w, "{}", tr(Msg::SkippedMounts, &[]))?;
+        for mount in &report.bind_mounts {
+            let path = display_name(&mount.path);
+            let original = mount.same_as.display().to_string();
+            let line = tr(Msg::SameDataAs, &[("path", &path), ("original", &original)]);
+            writeln!(w, "  {line}")?;
+        }
+    }
+    if let Some(spread) = &report.spread {
+        writeln!(w)?;
+        writeln!(w, "{}", tr(Msg::Filesystems, &[]))?;
+        for fs in &spread.filesystems {
+            let size = format!("{:>10}", locale::size(fs.size));
+            let mount = fs.mount_point.display().to_string();
+            let fstype = match (fs.fstype.as_str(), fs.source.as_str()) {
+                ("", _) => "?".to_string(),
+                (fstype, "") => fstype.to_string(),
+                (fstype, source) => format!("{fstype}, {source}"),
+            };
+            let files = locale::count(fs.file_count);
+            let line = tr(
+                Msg::FilesystemTotal,
+                &[
+                    ("size", &size),
+                    ("mount", &mount),
+                    ("type", &fstype),
+                    ("files", &files),
+                ],
+            );
+            writeln!(w, "  {line}")?;
+        }
+    }
+    if let Some(subvolumes) = &report.subvolumes {
+        writeln!(w)?;
+        writeln!(w, "{}", tr(Msg::Subvolumes, &[]))?;
+        for subvolume in subvolumes {
+            let size = format!("{:>10}", locale::size(subvolume.size));
+            let path = display_name(&subvolume.path);
+            let kind = tr(
+                match subvolume.snapshot {
+                    Some(true) => Msg::Snapshot,
+                    _ => Msg::Subvolume,
+                },
+                &[],
+            );
+            let mut line = tr(
+                Msg::SubvolumeTotal,
+                &[("size", &size), ("path", &path), ("kind", &kind)],
+            );
+            if let (Some(referenced), Some(exclusive)) = (subvolume.referenced, subvolume.exclusive)
+            {
+                let qgroup = tr(
+                    Msg::SubvolumeQgroup,
+                    &[
+                        ("exclusive", &locale::size(exclusive)),
+                        ("referenced", &locale::size(referenced)),
+                    ],
+                );
+                line = format!("{line}, {qgroup}");
+            }
+            writeln!(w, "  {line}")?;
+        }
+    }
+    if let Some(datasets) = &report.datasets {
+        writeln!(w)?;
+        writeln!(w, "{}", tr(Msg::Datasets, &[]))?;
+        for dataset in datasets {
+            let size = format!("{:>10}", locale::size(dataset.size));
+            let path = dataset_path(&dataset.path);
+            let mut line = tr(
+                Msg::DatasetTotal,
+                &[("size", &size), ("path", &path), ("name", &dataset.name)],
+            );
+            if let Some(p) = dataset.properties {
+                let zfs = tr(
+                    Msg::DatasetZfs,
+                    &[
+                        ("referenced", &locale::size(p.referenced)),
+                        ("logical", &locale::size(p.logical_referenced)),
+                        ("ratio", &format!("{:.2}", p.compress_ratio())),
+                        ("used", &locale::size(p.used)),
+                    ],
+                );
+                line = format!("{line}: {zfs}");
+            }
+            writeln!(w, "  {line}")?;
+        }
+    }
+    if let Some(presets) = &report.auto_excluded {
+        let applied: Vec<String> = presets
+            .iter()
+            .map(|(preset, count)| {
+                let count = locale::count(*count);
+                tr(
+                    Msg::AutoExcludedPreset,
+                    &[("preset", preset), ("count", &count)],
+                )
+            })
+            .collect();
+        let line = if applied.is_empty() {
+            tr(Msg::NothingAutoExcluded, &[])
+        } else {
+            tr(Msg::AutoExcluded, &[("presets", &applied.join(", "))])
+        };
+        writeln!(w, "{line}")?;
+    }
+    if let Some(budgets) = &report.budgets {
+        writeln!(w)?;
+        writeln!(w, "{}", tr(Msg::Budgets, &[]))?;
+        for measured in budgets {
+            let name = measured.name();
+            let size = locale::size(measured.size);
+            let limit = locale::size(measured.budget.limit);
+            let msg = if measured.exceeded() {
+                Msg::BudgetExceeded
+            } else {
+                Msg::BudgetWithin
+            };
+            let line = tr(msg, &[("path", &name), ("size", &size), ("limit", &limit)]);
+            writeln!(w, "  {line}")?;
+        }
+    }
+    if let Some(oversized) = &report.oversized {
+        write_oversized(w, oversized, report.top)?;
+    }
+    if let Some(special) = &report.special_files {
+        write_special(w, special, report.top)?;
+    }
+    if let Some(unreadable) = &report.unreadable {
+        write_unreadable(w, unreadable, report.top)?;
+    }
+    if let Some(groups) = &report.duplicates {
+        write_duplicates(w, groups, report.top, Msg::NoDuplicates, Msg::Duplicates)?;
+    }
+    if let Some(trees) = &report.duplicate_trees {
+        write_duplicates(
+            w,
+            trees,
+            report.top,
+            Msg::NoDuplicateTrees,
+            Msg::DuplicateTrees,
+        )?;
+    }
+    if let Some(pairs) = &report.similar {
+        write_similar(w, pairs, report.top)?;
+    }
+    Ok(())
+}
+
+/// GraphViz digraph of the tree. Font size and fill color grow with each node's
+/// share of the total, so the heavy branches stand out when rendered.
+pub fn write_dot(w: &mut dyn Write, report: &Report) -> Result<()> {
+    fn node(w: &mut dyn Write, n: &Node, id: &mut usize, total: u64) -> Result<usize> {
+        let own = *id;
+        *id += 1;
+        let share = if total > 0 {
+            n.size as f64 / total as f64
+        } else {
+            0.0
+        };
+        // Backslashes first, so the ones escaping quotes stay escapes.
+        let name = n
+            .name
+            .to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let label = format!("{name}\\n{}", format_size(n.size, BINARY));
+        writeln!(
+            w,
+            "  n{own} [label=\"{label}\", fontsize={:.1}, fillcolor=\"0.000 {:.3} 1.000\"];",
+            10.0 + 20.0 * share.sqrt(),
+            share.sqrt(),
+        )?;
+        for child in &n.children {
+            let child_id = node(w, child, id, total)?;
+            writeln!(w, "  n{own} -> n{child_id};")?;
+        }
+        Ok(own)
+    }
+
+    let Some(tree) = &report.tree else {
+        anyhow::bail!("dot output requires a directory tree");
+    };
+    writeln!(w, "digraph diskhound {{")?;
+    writeln!(w, "  rankdir=LR;")?;
+    writeln!(
+        w,
+        "  node [shape=box, style=filled, fontname=\"Helvetica\"];"
+    )?;
+    node(w, tree, &mut 0, tree.size)?;
+    writeln!(w, "}}")?;
+    Ok(())
+}
+
+/// Collapsed-stack lines (`root;child;grandchild bytes`) for flamegraph tools.
+/// Each line carries the bytes not accounted for by the node's listed children,
+/// so stack widths add up to the directory sizes.
+pub fn write_folded(w: &mut dyn Write, report: &Report) -> Result<()> {
+    fn node(w: &mut dyn Write, n: &Node, stack: &mut String) -> Result<()> {
+        let len = stack.len();
+        if !stack.is_empty() {
+            stack.push(';');
+        }
+        stack.push_str(&n.name.to_string_lossy().replace(';', "_"));
+        let children: u64 = n.children.iter().map(|c| c.size).sum();
+        let own = n.size.saturating_sub(children);
+        if own > 0 {
+            writeln!(w, "{stack} {own}")?;
+        }
+        for child in &n.children {
+            node(w, child, stack)?;
+        }
+        stack.truncate(len);
+        Ok(())
+    }
+
+    let Some(tree) = &report.tree else {
+        anyhow::bail!("folded output requires a directory tree");
+    };
+    node(w, tree, &mut String::new())
+}
+
+fn write_oversized(w: &mut dyn Write, oversized: &Oversized, top: usize) -> Result<()> {
+    if oversized.files.is_empty() {
+        return Ok(());
+    }
+    let total: u64 = oversized.files.iter().map(|(_, s)| s).sum();
+    writeln!(w)?;
+    let heading = tr(
+        Msg::SkippedFiles,
+        &[
+            ("count", &locale::count(oversized.files.len() as u64)),
+            ("limit", &locale::size(oversized.limit)),
+            ("size", &locale::size(total)),
+            ("shown", &oversized.files.len().min(top).to_string()),
+        ],
+    );
+    writeln!(w, "{heading}")?;
+    for (path, size) in oversized.files.iter().take(top) {
+        writeln!(w, "  {:>10}  {}", locale::size(*size), path.display())?;
+    }
+    Ok(())
+}
+
+fn write_special(w: &mut dyn Write, special: &SpecialFiles, top: usize) -> Result<()> {
+    let kinds: Vec<String> = special
+        .counts
+        .iter()
+        .map(|(kind, n)| format!("{kind}: {}", locale::count(*n)))
+        .collect();
+    let msg = match special.policy {
+        SpecialPolicy::Count => Msg::SpecialCounted,
+        SpecialPolicy::Skip => Msg::SpecialSkipped,
+        SpecialPolicy::List => Msg::SpecialListed,
+    };
+    writeln!(w)?;
+    let heading = tr(
+        msg,
+        &[
+            ("count", &locale::count(special.count())),
+            ("kinds", &kinds.join(", ")),
+            ("shown", &special.files.len().min(top).to_string()),
+        ],
+    );
+    writeln!(w, "{heading}")?;
+    for (path, kind) in special.files.iter().take(top) {
+        writeln!(w, "  {kind:<16}  {}", path.display())?;
+    }
+    Ok(())
+}
+
+fn write_unreadable(w: &mut dyn Write, unreadable: &Unreadable, top: usize) -> Result<()> {
+    writeln!(w)?;
+    if unreadable.files.is_empty() {
+        writeln!(w, "{}", tr(Msg::AllReadable, &[]))?;
+        return Ok(());
+    }
+    let heading = tr(
+        Msg::Unreadable,
+        &[
+            ("count", &locale::count(unreadable.files.len() as u64)),
+            ("shown", &unreadable.files.len().min(top).to_string()),
+        ],
+    );
+    writeln!(w, "{heading}")?;
+    for (path, error) in unreadable.files.iter().take(top) {
+        writeln!(w, "  {}: {error}", path.display())?;
+    }
+    Ok(())
+}
+
+/// Duplicate files or trees, under the `none` or `heading` message.
+fn write_duplicates(
+    w: &mut dyn Write,
+    groups: &[DuplicateGroup],
+    top: usize,
+    none: Msg,
+    heading: Msg,
+) -> Result<()> {
+    writeln!(w)?;
+    if groups.is_empty() {
+        writeln!(w, "{}", tr(none, &[]))?;
+        return Ok(());
+    }
+    let reclaimable: u64 = groups.iter().map(|g| g.reclaimable()).sum();
+    let heading = tr(
+        heading,
+        &[
+            ("groups", &locale::count(groups.len() as u64)),
+            ("size", &locale::size(reclaimable)),
+            ("shown", &groups.len().min(top).to_string()),
+        ],
+    );
+    writeln!(w, "{heading}")?;
+    for group in groups.iter().take(top) {
+        let line = tr(
+            Msg::DuplicateGroup,
+            &[
+                ("copies", &group.paths.len().to_string()),
+                ("size", &locale::size(group.size)),
+                ("reclaimable", &locale::size(group.reclaimable())),
+            ],
+        );
+        writeln!(w, "  {line}")?;
+        for path in &group.paths {
+            writeln!(w, "    {}", path.display())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_similar(w: &mut dyn Write, pairs: &[SimilarPair], top: usize) -> Result<()> {
+    writeln!(w)?;
+    if pairs.is_empty() {
+        writeln!(w, "{}", tr(Msg::NoSimilar, &[]))?;
+        return Ok(());
+    }
+    let shared: u64 = pairs.iter().map(|p| p.shared).sum();
+    let heading = tr(
+        Msg::Similar,
+        &[
+            ("pairs", &locale::count(pairs.len() as u64)),
+            ("size", &locale::size(shared)),
+            ("shown", &pairs.len().min(top).to_string()),
+        ],
+    );
+    writeln!(w, "{heading}")?;
+    for pair in pairs.iter().take(top) {
+        let line = tr(
+            Msg::SimilarPair,
+            &[
+                ("similarity", &locale::fixed(pair.similarity, 0)),
+                ("shared", &locale::size(pair.shared)),
+            ],
+        );
+        writeln!(w, "  {line}")?;
+        for (path, size) in pair.paths.iter().zip(pair.sizes) {
+            writeln!(w, "    {:>10}  {}", locale::size(size), path.display())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so an
+/// interrupted run never leaves a truncated report behind.
+pub fn write_atomic(path: &Path, render: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<()> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("invalid output path: {}", path.display()))?;
+    // Unique per call too, for threads writing the same path at once.
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let tmp = dir.join(format!(
+        ".{}.tmp{}-{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let file =
+            File::create(&tmp).with_context(|| format!("failed to create {}", tmp.display()))?;
+        let mut file = std::io::BufWriter::new(file);
+        render(&mut file)?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// Renders to stdout, piping through `$PAGER` (default `less`) when stdout is a
+/// terminal and the output would not fit on one screen.
+pub fn write_stdout(
+    enabled: bool,
+    render: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    let stdout = std::io::stdout();
+    if !enabled || !stdout.is_terminal() {
+        return render(&mut stdout.lock());
+    }
+
+    let mut buf = Vec::new();
+    render(&mut buf)?;
+
+    let height = terminal_size::terminal_size()
+        .map(|(_, terminal_size::Height(h))| h as usize)
+        .unwrap_or(usize::MAX);
+    let lines = buf.iter().filter(|&&b| b == b'\n').count();
+    if lines < height {
+        debug!("output fits the terminal ({lines} of {height} lines), not paging");
+    }
+    if lines < height || !spawn_pager(&buf) {
+        stdout.lock().write_all(&buf)?;
+    }
+    Ok(())
+}
+
+/// Returns false if no pager could be started, so the caller can print directly.
+fn spawn_pager(buf: &[u8]) -> bool {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+    let mut parts = pager.split_whitespace();
+    let Some(program) = parts.next() else {
+        return false;
+    };
+    if program == "cat" {
+        return false;
+    }
+
+    let mut command = Command::new(program);
+    command.args(parts).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        // Quit if one screen, keep colors, don't clear the screen on exit.
+        command.env("LESS", "FRX");
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            debug!("could not start pager {program:?}: {err}");
+            return false;
+        }
+    };
+    debug!("paging through {pager:?}");
+    if let Some(mut stdin) = child.stdin.take() {
+        // A user quitting the pager early closes the pipe; that's not an error.
+        let _ = stdin.write_all(buf);
+    }
+    let _ = child.wait();
+    true
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+/// Bundled exclusion sets for common kinds of scans.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Preset {
+    /// When scanning a filesystem root, skip pseudo filesystems and locked
+    /// system files
+    SystemScan,
+    /// JavaScript dependencies and build caches
+    Node,
+    /// Cargo build output
+    Rust,
+    /// Virtual environments and tool caches
+    Python,
+    /// Spotlight, Time Machine, and Trash metadata on macOS volumes
+    MacosSystem,
+}
+
+pub const BUILT_IN: &[(&str, Preset)] = &[
+    ("system-scan", Preset::SystemScan),
+    ("node", Preset::Node),
+    ("rust", Preset::Rust),
+    ("python", Preset::Python),
+    ("macos-system", Preset::MacosSystem),
+];
+
+/// Version control metadata directories, skipped with `--no-vcs`.
+pub const VCS_DIRECTORIES: &[&str] = &[".git", ".hg", ".svn", ".jj"];
+
+/// Files that mark a project root, with the preset whose directories are
+/// skipped beside them under `--auto-exclude`.
+const MARKERS: &[(&str, Preset)] = &[
+    ("Cargo.toml", Preset::Rust),
+    ("package.json", Preset::Node),
+    ("pyproject.toml", Preset::Python),
+    ("setup.py", Preset::Python),
+    ("requirements.txt", Preset::Python),
+];
+
+impl Preset {
+    /// The preset a project marker file calls for.
+    pub fn for_marker(file_name: &str) -> Option<Preset> {
+        MARKERS
+            .iter()
+            .find(|(marker, _)| *marker == file_name)
+            .map(|(_, preset)| *preset)
+    }
+
+    pub fn name(self) -> &'static str {
+        BUILT_IN
+            .iter()
+            .find(|(_, preset)| *preset == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        BUILT_IN
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, preset)| *preset)
+    }
+
+    /// Directory names skipped wherever they appear, like `--exclude`.
+    pub fn excludes(self) -> &'static [&'static str] {
+        match self {
+            Preset::SystemScan => &[],
+            Preset::Node => &[
+                "node_modules",
+                "bower_components",
+                ".npm",
+                ".pnpm-store",
+                ".yarn",
+                ".next",
+                ".nuxt",
+                ".parcel-cache",
+                ".turbo",
+            ],
+            Preset::Rust => &["target"],
+            Preset::Python => &[
+                "__pycache__",
+                ".venv",
+                "venv",
+                ".tox",
+                ".nox",
+                ".eggs",
+                ".mypy_cache",
+                ".pytest_cache",
+                ".ruff_cache",
+            ],
+            Preset::MacosSystem => &[
+                ".Spotlight-V100",
+                ".fseventsd",
+                ".Trashes",
+                ".DocumentRevisions-V100",
+                ".TemporaryItems",
+                ".MobileBackups",
+            ],
+        }
+    }
+
+    /// Paths skipped below a filesystem root, relative to it.
+    fn root_paths(self) -> &'static [&'static str] {
+        match self {
+            Preset::SystemScan if cfg!(windows) => &[
+                "pagefile.sys",
+                "hiberfil.sys",
+                "swapfile.sys",
+                "DumpStack.log.tmp",
+                "System Volume Information",
+            ],
+            // The firmlinked data volume under /System/Volumes would otherwise
+            // be counted twice.
+            Preset::SystemScan if cfg!(target_os = "macos") => &["dev", "System/Volumes"],
+            Preset::SystemScan => &["proc", "sys", "dev", "run"],
+            _ => &[],
+        }
+    }
+
+    /// Whether `relative`, a path below a filesystem root, is skipped.
+    pub fn skips(self, relative: &Path) -> bool {
+        self.root_paths().iter().any(|skip| {
+            // Windows paths are case-insensitive.
+            if cfg!(windows) {
+                skip.eq_ignore_ascii_case(&relative.to_string_lossy())
+            } else {
+                relative == Path::new(skip)
+            }
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{bail, Result};
+use tracing::debug;
+
+/// I/O scheduling class for `--ionice`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum IoClass {
+    /// Only get disk time when nothing else wants it
+    Idle,
+    /// Normal scheduling at the lowest priority level
+    BestEffort,
+}
+
+/// Lowers the CPU and I/O priority of the process. Must run before any worker
+/// threads start, since they inherit the setting from the main thread.
+#[cfg(unix)]
+pub fn lower(nice: Option<i32>, ionice: Option<IoClass>) -> Result<()> {
+    if let Some(nice) = nice {
+        // SAFETY: plain syscall on the current process, no pointers involved.
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+            bail!(
+                "failed to set nice value {nice}: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        debug!("nice value set to {nice}");
+    }
+    if let Some(class) = ionice {
+        set_io_class(class)?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_io_class(class: IoClass) -> Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    let prio = match class {
+        IoClass::Idle => 3 << IOPRIO_CLASS_SHIFT,
+        IoClass::BestEffort => (2 << IOPRIO_CLASS_SHIFT) | 7,
+    };
+    // SAFETY: ioprio_set takes only integers; 0 means the calling process.
+    if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, prio) } != 0 {
+        bail!(
+            "failed to set I/O priority: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    debug!("I/O priority lowered");
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn set_io_class(_class: IoClass) -> Result<()> {
+    tracing::warn!("--ionice is only supported on Linux; ignoring it");
+    Ok(())
+}
+
+/// Windows has no separate nice and I/O classes: either option switches the
+/// process to background mode, which lowers both.
+#[cfg(windows)]
+pub fn lower(nice: Option<i32>, ionice: Option<IoClass>) -> Result<()> {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, SetPriorityClass, PROCESS_MODE_BACKGROUND_BEGIN,
+    };
+    if nice.is_none() && ionice.is_none() {
+        return Ok(());
+    }
+    if nice.is_some_and(|n| n <= 0) {
+        tracing::warn!("Windows background mode only lowers priority; ignoring the --nice value");
+    }
+    // SAFETY: GetCurrentProcess returns a pseudo-handle that needs no cleanup.
+    if unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) } == 0 {
+        bail!(
+            "failed to enter background mode: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    debug!("process switched to background mode");
+    Ok(())
+}
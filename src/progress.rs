@@ -0,0 +1,226 @@
+use crate::i18n::{tr, Msg};
+use crate::output;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Held across the history's read-modify-write, so scans finishing together
+/// (`compare`, `merge`) don't drop each other's counts.
+static HISTORY: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Default)]
+struct ScanHistory {
+    roots: HashMap<String, RootStats>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct RootStats {
+    entries: u64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProgressMode {
+    Off,
+    Human,
+    Json,
+}
+
+/// Live scan progress on stderr. Shows a spinner on the first scan of a root and
+/// a percentage with ETA once a previous entry count has been recorded for it.
+/// In JSON mode, emits one event object per line instead.
+pub struct Progress {
+    mode: ProgressMode,
+    root_key: String,
+    expected: Option<u64>,
+    entries: u64,
+    bytes: u64,
+    started: Instant,
+    last_draw: Instant,
+    frame: usize,
+}
+
+impl Progress {
+    pub fn new(root: &Path, mode: ProgressMode) -> Self {
+        let root_key = root
+            .canonicalize()
+            .unwrap_or_else(|_| root.to_path_buf())
+            .to_string_lossy()
+            .to_string();
+        let mode = match mode {
+            ProgressMode::Human if !std::io::stderr().is_terminal() => ProgressMode::Off,
+            mode => mode,
+        };
+        let expected = if mode != ProgressMode::Off {
+            load_history()
+                .roots
+                .get(&root_key)
+                .map(|s| s.entries)
+                .filter(|&n| n > 0)
+        } else {
+            None
+        };
+        let now = Instant::now();
+        Progress {
+            mode,
+            root_key,
+            expected,
+            entries: 0,
+            bytes: 0,
+            started: now,
+            last_draw: now,
+            frame: 0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.entries += 1;
+        if self.mode == ProgressMode::Off || self.last_draw.elapsed() < REDRAW_INTERVAL {
+            return;
+        }
+        self.last_draw = Instant::now();
+        match self.mode {
+            ProgressMode::Json => self.emit("progress"),
+            _ => self.draw(),
+        }
+    }
+
+    pub fn add_bytes(&mut self, bytes: u64) {
+        self.bytes += bytes;
+    }
+
+    fn emit(&self, event: &str) {
+        let mut line = serde_json::json!({
+            "event": event,
+            "entries": self.entries,
+            "bytes": self.bytes,
+            "elapsed_ms": self.started.elapsed().as_millis() as u64,
+        });
+        if let Some(expected) = self.expected {
+            line["expected_entries"] = expected.into();
+        }
+        eprintln!("{line}");
+    }
+
+    fn draw(&mut self) {
+        let line = match self.expected {
+            Some(expected) => {
+                let fraction = (self.entries as f64 / expected as f64).min(0.99);
+                let elapsed = self.started.elapsed().as_secs_f64();
+                let eta = if fraction > 0.0 {
+                    format_eta(elapsed / fraction - elapsed)
+                } else {
+                    "?".to_string()
+                };
+                tr(
+                    Msg::ProgressEta,
+                    &[
+                        ("percent", &format!("{:.0}", fraction * 100.0)),
+                        ("entries", &format_count(expected)),
+                        ("eta", &eta),
+                    ],
+                )
+            }
+            None => {
+                self.frame = (self.frame + 1) % SPINNER.len();
+                tr(
+                    Msg::Progress,
+                    &[
+                        ("spinner", &SPINNER[self.frame].to_string()),
+                        ("entries", &format_count(self.entries)),
+                    ],
+                )
+            }
+        };
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{line}");
+        let _ = stderr.flush();
+    }
+
+    /// Clears the progress line and, if the walk ran to completion, records this
+    /// run's entry count for next time.
+    pub fn finish(self, complete: bool) {
+        match self.mode {
+            ProgressMode::Off => return,
+            ProgressMode::Json => self.emit("done"),
+            ProgressMode::Human => {
+                let mut stderr = std::io::stderr().lock();
+                let _ = write!(stderr, "\r\x1b[2K");
+                let _ = stderr.flush();
+            }
+        }
+
+        if !complete {
+            return;
+        }
+        let _lock = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+        let mut history = load_history();
+        history.roots.insert(
+            self.root_key,
+            RootStats {
+                entries: self.entries,
+            },
+        );
+        let _ = save_history(&history);
+    }
+}
+
+fn format_count(n: u64) -> String {
+    match n {
+        0..=9_999 => n.to_string(),
+        10_000..=999_999 => format!("{:.1}K", n as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1}M", n as f64 / 1e6),
+        _ => format!("{:.1}B", n as f64 / 1e9),
+    }
+}
+
+fn format_eta(secs: f64) -> String {
+    let secs = secs.max(0.0).round() as u64;
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Caches"))
+    } else {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+    };
+    base.map(|b| b.join("diskhound"))
+}
+
+fn history_path() -> Option<PathBuf> {
+    cache_dir().map(|d| d.join("scan-stats.json"))
+}
+
+fn load_history() -> ScanHistory {
+    history_path()
+        .and_then(|p| std::fs::read(p).ok())
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_history(history: &ScanHistory) -> anyhow::Result<()> {
+    let Some(path) = history_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Atomic, since a torn file reads back as no history for any root.
+    output::write_atomic(&path, |w| Ok(w.write_all(&serde_json::to_vec(history)?)?))
+}
@@ -0,0 +1,85 @@
+use crate::output::{ListedFile, Report};
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// QDirStat cache file, for File > Read Cache File in QDirStat's GUI.
+/// Directories are the parents of the listed files, so empty ones are left
+/// out; their own size and time are read back from disk.
+pub fn write(w: &mut dyn Write, report: &Report) -> Result<()> {
+    let Some(files) = &report.files else {
+        bail!("qdirstat output requires a file listing");
+    };
+    // The reader resolves every directory line as an absolute path.
+    let root = std::fs::canonicalize(&report.root).unwrap_or_else(|_| report.root.clone());
+
+    // Sorted paths put every directory after its parent, which the reader
+    // needs to attach it.
+    let mut by_dir: BTreeMap<PathBuf, Vec<&ListedFile>> = BTreeMap::new();
+    by_dir.insert(PathBuf::new(), Vec::new());
+    for file in files {
+        let parent = file.path.parent().unwrap_or(Path::new(""));
+        for ancestor in parent.ancestors() {
+            by_dir.entry(ancestor.to_path_buf()).or_default();
+        }
+        by_dir.get_mut(parent).unwrap().push(file);
+    }
+
+    writeln!(w, "[qdirstat 2.0 cache file]")?;
+    writeln!(w, "# Generated by diskhound")?;
+    writeln!(w, "# Type\tpath\tsize\tmtime")?;
+    for (dir, files) in &by_dir {
+        // Joining an empty path would add a trailing separator.
+        let path = if dir.as_os_str().is_empty() {
+            root.clone()
+        } else {
+            root.join(dir)
+        };
+        let metadata = std::fs::symlink_metadata(&path).ok();
+        writeln!(
+            w,
+            "D\t{}\t{}\t0x{:x}",
+            encode(path.as_os_str()),
+            metadata.as_ref().map_or(0, |m| m.len()),
+            unix_time(metadata.and_then(|m| m.modified().ok())),
+        )?;
+        for file in files {
+            let name = file.path.file_name().unwrap_or_default();
+            writeln!(
+                w,
+                "F\t{}\t{}\t0x{:x}",
+                encode(name),
+                file.size,
+                unix_time(file.modified),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Fields are whitespace-separated, so names are percent-encoded like
+/// QDirStat's own writer does, keeping `/` as is.
+fn encode(s: &OsStr) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(s).to_vec();
+    #[cfg(not(unix))]
+    let bytes = s.to_string_lossy().into_owned().into_bytes();
+    let mut out = String::with_capacity(bytes.len());
+    for byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// Times before 1970 or unknown are written as 0.
+fn unix_time(time: Option<SystemTime>) -> u64 {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Share of the limit at which an owner is flagged as close to their quota.
+pub const NEAR_LIMIT: f64 = 90.0;
+
+/// A user's disk quota on the scanned filesystem. `used` covers the whole
+/// filesystem, not just the scanned tree.
+#[derive(Clone, Copy)]
+pub struct Quota {
+    pub used: u64,
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+impl Quota {
+    /// The soft limit, where the filesystem starts warning, or else the hard
+    /// one.
+    pub fn limit(&self) -> Option<u64> {
+        self.soft.or(self.hard)
+    }
+
+    pub fn percentage(&self) -> Option<f64> {
+        let limit = self.limit()?;
+        Some(self.used as f64 / limit as f64 * 100.0)
+    }
+
+    pub fn near_limit(&self) -> bool {
+        self.percentage().is_some_and(|p| p >= NEAR_LIMIT)
+    }
+}
+
+/// User quotas on the filesystem mounted at `mount_point` for the entries of
+/// a `--group-by owner` view, keyed like them. Owners without limits are left
+/// out, as is everyone when quotas aren't enabled or the caller may not read
+/// them (unprivileged users only see their own).
+#[cfg(target_os = "linux")]
+pub fn user_quotas<'a>(
+    mount_point: &Path,
+    owners: impl Iterator<Item = &'a Path>,
+) -> HashMap<PathBuf, Quota> {
+    let Some(device) = crate::mounts::list(&crate::mounts::mountinfo())
+        .into_iter()
+        .find(|m| m.point == mount_point)
+        .and_then(|m| std::ffi::CString::new(m.source).ok())
+    else {
+        return HashMap::new();
+    };
+    let uids: HashMap<String, u32> = crate::group::user_names()
+        .into_iter()
+        .map(|(uid, name)| (name, uid))
+        .collect();
+    owners
+        .filter_map(|owner| {
+            let name = owner.to_string_lossy();
+            let uid = uids.get(name.as_ref()).copied().or(name.parse().ok())?;
+            Some((owner.to_path_buf(), get(&device, uid)?))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn user_quotas<'a>(
+    _mount_point: &Path,
+    _owners: impl Iterator<Item = &'a Path>,
+) -> HashMap<PathBuf, Quota> {
+    HashMap::new()
+}
+
+#[cfg(target_os = "linux")]
+fn get(device: &std::ffi::CStr, uid: u32) -> Option<Quota> {
+    // From <sys/quota.h>: QCMD(Q_GETQUOTA, USRQUOTA). Limits are in 1 KiB
+    // blocks, usage in bytes.
+    const Q_GETQUOTA: u32 = 0x800007;
+    const USRQUOTA: u32 = 0;
+    const BLOCK: u64 = 1024;
+    let cmd = ((Q_GETQUOTA << 8) | USRQUOTA) as libc::c_int;
+    let mut dqblk: libc::dqblk = unsafe { std::mem::zeroed() };
+    let data = (&mut dqblk as *mut libc::dqblk).cast::<libc::c_char>();
+    if unsafe { libc::quotactl(cmd, device.as_ptr(), uid as libc::c_int, data) } != 0 {
+        tracing::debug!(
+            "no quota for uid {uid}: {}",
+            std::io::Error::last_os_error()
+        );
+        return None;
+    }
+    let limit = |blocks: u64| (blocks > 0).then_some(blocks * BLOCK);
+    let quota = Quota {
+        used: dqblk.dqb_curspace,
+        soft: limit(dqblk.dqb_bsoftlimit),
+        hard: limit(dqblk.dqb_bhardlimit),
+    };
+    quota.limit().map(|_| quota)
+}
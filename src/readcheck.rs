@@ -0,0 +1,49 @@
+use crate::throttle::RateLimiter;
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// Bytes read from the start of every file: enough to touch the first data
+/// block on any common filesystem.
+const BLOCK: usize = 4096;
+
+/// Files whose first block couldn't be read (`--check-read`).
+pub struct Unreadable {
+    /// Failed files per report entry.
+    pub by_key: HashMap<PathBuf, u64>,
+    /// In path order.
+    pub files: Vec<(PathBuf, String)>,
+}
+
+/// Opens each file and reads its first block, collecting the I/O errors.
+/// `files` pairs each file's report key with its path.
+pub fn check(
+    files: Vec<(PathBuf, PathBuf)>,
+    threads: usize,
+    limiter: &RateLimiter,
+) -> Result<Vec<(PathBuf, PathBuf, String)>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    let mut failed: Vec<(PathBuf, PathBuf, String)> = pool.install(|| {
+        files
+            .into_par_iter()
+            .filter_map(|(key, path)| {
+                limiter.acquire(BLOCK as u64);
+                let result = File::open(&path).and_then(|mut file| {
+                    let mut buf = [0u8; BLOCK];
+                    file.read(&mut buf)
+                });
+                let err = result.err()?;
+                debug!("read check failed for {}: {err}", path.display());
+                Some((key, path, err.to_string()))
+            })
+            .collect()
+    });
+    failed.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(failed)
+}
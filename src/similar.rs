@@ -0,0 +1,183 @@
+use crate::throttle::RateLimiter;
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Files smaller than this are left to `--dupes`; delta storage of a few
+/// kilobytes isn't worth the bookkeeping.
+pub const MIN_SIZE: u64 = 1024 * 1024;
+
+/// Chunk boundaries fall where the rolling hash has these bits clear, giving
+/// 8 KiB chunks on average, bounded to 2-64 KiB.
+const BOUNDARY_MASK: u64 = (1 << 13) - 1;
+const MIN_CHUNK: usize = 2 * 1024;
+const MAX_CHUNK: usize = 64 * 1024;
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Chunk hashes kept per file. Similarity estimates are within a few percent
+/// at this sketch size.
+const SKETCH_SIZE: usize = 128;
+
+/// Chunks shared by more files than this (runs of zeros, common headers) say
+/// nothing about how alike two files are, and would pair everything with
+/// everything.
+const MAX_POSTINGS: usize = 64;
+
+/// Gear table for the rolling hash, from a fixed splitmix64 sequence so
+/// chunk boundaries are the same on every run.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Two files that share most of their content.
+pub struct SimilarPair {
+    pub paths: [PathBuf; 2],
+    pub sizes: [u64; 2],
+    /// Estimated share of chunks the files have in common, 0-100.
+    pub similarity: f64,
+    /// Estimated bytes the second file would no longer need if stored as a
+    /// delta against the first.
+    pub shared: u64,
+}
+
+/// Finds pairs of files that are at least `threshold` percent alike, by
+/// splitting each into content-defined chunks (so an insertion only changes
+/// the chunks around it) and comparing bottom-k sketches of the chunk
+/// hashes. Only files that share a sketched chunk are compared.
+pub fn find_similar(
+    files: Vec<(PathBuf, u64)>,
+    threshold: f64,
+    threads: usize,
+    limiter: &RateLimiter,
+) -> Result<Vec<SimilarPair>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    let sketches: Vec<(PathBuf, u64, Vec<u64>)> = pool.install(|| {
+        files
+            .into_par_iter()
+            .filter(|(_, size)| *size >= MIN_SIZE)
+            .filter_map(|(path, size)| {
+                let sketch = sketch(&path, limiter)
+                    .map_err(|err| info!("could not chunk {}: {err}", path.display()))
+                    .ok()?;
+                Some((path, size, sketch))
+            })
+            .collect()
+    });
+    debug!("sketched {} files for similarity", sketches.len());
+
+    let mut postings: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, (_, _, sketch)) in sketches.iter().enumerate() {
+        for &hash in sketch {
+            postings.entry(hash).or_default().push(i);
+        }
+    }
+    let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+    for files in postings.values() {
+        if files.len() > MAX_POSTINGS {
+            continue;
+        }
+        for (n, &a) in files.iter().enumerate() {
+            for &b in &files[n + 1..] {
+                candidates.insert((a, b));
+            }
+        }
+    }
+    debug!("{} candidate pairs", candidates.len());
+
+    let mut pairs: Vec<SimilarPair> = candidates
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let (path_a, size_a, sketch_a) = &sketches[a];
+            let (path_b, size_b, sketch_b) = &sketches[b];
+            let jaccard = jaccard(sketch_a, sketch_b);
+            let similarity = jaccard * 100.0;
+            if similarity < threshold {
+                return None;
+            }
+            // |A ∩ B| = J / (1 + J) * (|A| + |B|), in bytes rather than chunks.
+            let shared = (jaccard / (1.0 + jaccard) * (size_a + size_b) as f64) as u64;
+            let mut pair = [(path_a, *size_a), (path_b, *size_b)];
+            pair.sort();
+            Some(SimilarPair {
+                paths: [pair[0].0.clone(), pair[1].0.clone()],
+                sizes: [pair[0].1, pair[1].1],
+                similarity,
+                shared: shared.min(*size_a.min(size_b)),
+            })
+        })
+        .collect();
+    pairs.sort_by(|a, b| b.shared.cmp(&a.shared).then_with(|| a.paths.cmp(&b.paths)));
+    Ok(pairs)
+}
+
+/// The `SKETCH_SIZE` smallest chunk hashes of a file, ascending.
+fn sketch(path: &Path, limiter: &RateLimiter) -> Result<Vec<u64>> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; READ_CHUNK];
+    let mut smallest: BTreeSet<u64> = BTreeSet::new();
+    let mut chunk: Vec<u8> = Vec::with_capacity(MAX_CHUNK);
+    let mut rolling: u64 = 0;
+    let mut keep = |chunk: &[u8]| {
+        let hash = blake3::hash(chunk);
+        let hash = u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap());
+        smallest.insert(hash);
+        if smallest.len() > SKETCH_SIZE {
+            smallest.pop_last();
+        }
+    };
+    loop {
+        limiter.acquire(READ_CHUNK as u64);
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for &byte in &buf[..n] {
+            chunk.push(byte);
+            rolling = (rolling << 1).wrapping_add(GEAR[byte as usize]);
+            let boundary = chunk.len() >= MIN_CHUNK && rolling & BOUNDARY_MASK == 0;
+            if boundary || chunk.len() >= MAX_CHUNK {
+                keep(&chunk);
+                chunk.clear();
+                rolling = 0;
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        keep(&chunk);
+    }
+    Ok(smallest.into_iter().collect())
+}
+
+/// Bottom-k estimate of the Jaccard similarity of two sketches: the share of
+/// the union's smallest hashes that appear in both.
+fn jaccard(a: &[u64], b: &[u64]) -> f64 {
+    let a_set: HashSet<u64> = a.iter().copied().collect();
+    let b_set: HashSet<u64> = b.iter().copied().collect();
+    let union: BTreeSet<u64> = a.iter().chain(b).copied().collect();
+    let sample: Vec<u64> = union.into_iter().take(SKETCH_SIZE).collect();
+    if sample.is_empty() {
+        return 0.0;
+    }
+    let both = sample
+        .iter()
+        .filter(|h| a_set.contains(h) && b_set.contains(h))
+        .count();
+    both as f64 / sample.len() as f64
+}
@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+
+/// Relative accuracy of the estimates: a reported quantile is within 1% of
+/// the true file size.
+const ACCURACY: f64 = 0.01;
+
+/// Streaming quantile sketch over file sizes (the DDSketch scheme). Sizes are
+/// counted in logarithmic buckets, so memory grows with the range of sizes
+/// seen (a few thousand buckets at most), not with the number of files.
+#[derive(Default)]
+pub struct Quantiles {
+    zeros: u64,
+    buckets: BTreeMap<i32, u64>,
+    count: u64,
+}
+
+fn gamma() -> f64 {
+    (1.0 + ACCURACY) / (1.0 - ACCURACY)
+}
+
+impl Quantiles {
+    pub fn add(&mut self, size: u64) {
+        self.count += 1;
+        if size == 0 {
+            self.zeros += 1;
+            return;
+        }
+        let index = (size as f64).ln() / gamma().ln();
+        *self.buckets.entry(index.ceil() as i32).or_insert(0) += 1;
+    }
+
+    /// Estimated size at quantile `q` (0.5 for the median), or `None` when
+    /// nothing was added.
+    pub fn quantile(&self, q: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q * (self.count - 1) as f64).round() as u64;
+        if rank < self.zeros {
+            return Some(0);
+        }
+        let mut seen = self.zeros;
+        for (&index, &count) in &self.buckets {
+            seen += count;
+            if seen > rank {
+                // Midpoint of the bucket (gamma^(i-1), gamma^i] in relative terms.
+                let estimate = 2.0 * gamma().powi(index) / (gamma() + 1.0);
+                return Some(estimate.round() as u64);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,142 @@
+use crate::filesystem::Usage;
+use crate::group::{self, GroupBy};
+use crate::output::{decode_raw, DirStats, PercentOf, Report, SortBy, SCHEMA_VERSION};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A report saved with `--format json`, read back. Only the fields needed to
+/// rebuild the entries are parsed; everything else is ignored.
+#[derive(Deserialize)]
+pub struct Snapshot {
+    pub schema_version: u32,
+    pub directories: Vec<SnapshotEntry>,
+    pub summary: SnapshotSummary,
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotEntry {
+    pub name: String,
+    #[serde(default)]
+    pub name_base64: Option<String>,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotSummary {
+    /// Absent in reports written before the root was recorded.
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Seconds since the Unix epoch; absent in older reports.
+    #[serde(default)]
+    pub scanned_at: Option<u64>,
+    #[serde(default)]
+    pub filesystem: Option<Usage>,
+    pub total_size: u64,
+    pub total_files: u64,
+    pub total_dirs: u64,
+    #[serde(default)]
+    pub group_by: Option<String>,
+    #[serde(default)]
+    pub incomplete: Option<String>,
+}
+
+impl Snapshot {
+    /// The `--group-by` mode the report was written with.
+    pub fn group_by(&self) -> Result<GroupBy> {
+        match &self.summary.group_by {
+            Some(s) => group::parse(s).map_err(anyhow::Error::msg),
+            None => Ok(GroupBy::Dir),
+        }
+    }
+
+    /// Rebuilds the report for rendering with different display options.
+    /// Only the entries the report kept can be shown, so `top` can narrow
+    /// the view but not widen it.
+    pub fn into_report(
+        self,
+        path: &Path,
+        percent_of: PercentOf,
+        min_size: u64,
+        sort: SortBy,
+        top: usize,
+    ) -> Result<Report> {
+        if percent_of == PercentOf::Parent {
+            bail!("--percent-of parent needs a fresh scan");
+        }
+        let group_by = self.group_by()?;
+        let mut entries = Vec::with_capacity(self.directories.len());
+        for entry in &self.directories {
+            if entry.size >= min_size {
+                let stats = DirStats {
+                    size: entry.size,
+                    file_count: entry.file_count,
+                };
+                entries.push((entry.key()?, stats));
+            }
+        }
+        sort.sort(&mut entries);
+        entries.truncate(top);
+
+        let root = self
+            .summary
+            .root
+            .map_or_else(|| path.to_path_buf(), PathBuf::from);
+        Ok(Report {
+            scanned_at: self
+                .summary
+                .scanned_at
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            filesystem: self.summary.filesystem,
+            total_size: self.summary.total_size,
+            total_files: self.summary.total_files,
+            total_dirs: self.summary.total_dirs,
+            incomplete: self.summary.incomplete,
+            ..Report::new(root, entries, percent_of, group_by, top)
+        })
+    }
+}
+
+impl SnapshotEntry {
+    /// The grouping key this entry was written from, with its raw bytes when
+    /// the name had to be converted lossily.
+    pub fn key(&self) -> Result<PathBuf> {
+        match &self.name_base64 {
+            Some(raw) => Ok(PathBuf::from(decode_raw(raw)?)),
+            None => Ok(self.name.split('/').collect()),
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<Snapshot> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse(&text, &path.display().to_string())
+}
+
+/// A report from `text`, named `what` in errors.
+pub fn parse(text: &str, what: &str) -> Result<Snapshot> {
+    let snapshot: Snapshot = serde_json::from_str(text)
+        .with_context(|| format!("{what} is not a diskhound JSON report"))?;
+    if snapshot.schema_version > SCHEMA_VERSION {
+        bail!(
+            "{what} uses schema version {}; this diskhound reads up to {}",
+            snapshot.schema_version,
+            SCHEMA_VERSION
+        );
+    }
+    Ok(snapshot)
+}
+
+/// When `snapshot`, loaded from `path`, was scanned: the time it records, or
+/// the file's modification time for reports written before it did.
+pub fn taken(snapshot: &Snapshot, path: &Path) -> Result<SystemTime> {
+    Ok(match snapshot.summary.scanned_at {
+        Some(secs) => UNIX_EPOCH + Duration::from_secs(secs),
+        None => std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .with_context(|| format!("failed to read {}", path.display()))?,
+    })
+}
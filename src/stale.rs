@@ -0,0 +1,139 @@
+use crate::group::{mtime_bucket, Period};
+use crate::locale;
+use crate::output::{display_name, raw_base64, Report, SCHEMA_VERSION};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY: f64 = 86_400.0;
+
+/// An entry with the age of its newest file.
+pub struct StaleEntry {
+    pub name: PathBuf,
+    pub size: u64,
+    pub file_count: u64,
+    pub modified: Option<SystemTime>,
+    pub age_days: f64,
+    pub score: f64,
+}
+
+/// Adds every key's newest modification time to all of its ancestors,
+/// matching `tree::cumulative`.
+pub fn cumulative(by_key: HashMap<PathBuf, SystemTime>) -> HashMap<PathBuf, SystemTime> {
+    let mut out: HashMap<PathBuf, SystemTime> = HashMap::new();
+    for (key, &modified) in &by_key {
+        for ancestor in key.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+            let newest = out.entry(ancestor.to_path_buf()).or_insert(modified);
+            *newest = (*newest).max(modified);
+        }
+    }
+    out
+}
+
+/// Ranks the entries of an untruncated report by
+/// `size^size_weight × age^age_weight`, where age is the days since the
+/// entry's newest file changed. Entries modified in the future, or whose
+/// times couldn't be read, score zero.
+pub fn rank(report: &Report, size_weight: f64, age_weight: f64, top: usize) -> Vec<StaleEntry> {
+    let now = SystemTime::now();
+    let empty = HashMap::new();
+    let newest = report.modified.as_ref().unwrap_or(&empty);
+    let mut entries: Vec<StaleEntry> = report
+        .entries
+        .iter()
+        .map(|(name, stats)| {
+            let modified = newest.get(name).copied();
+            let age_days = modified
+                .and_then(|m| now.duration_since(m).ok())
+                .map_or(0.0, |age| age.as_secs_f64() / DAY);
+            StaleEntry {
+                name: name.clone(),
+                size: stats.size,
+                file_count: stats.file_count,
+                modified,
+                age_days,
+                score: (stats.size as f64).powf(size_weight) * age_days.powf(age_weight),
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.score.total_cmp(&a.score).then(b.size.cmp(&a.size)));
+    entries.truncate(top);
+    entries
+}
+
+pub fn write_table(w: &mut dyn Write, entries: &[StaleEntry]) -> Result<()> {
+    if entries.is_empty() {
+        writeln!(w, "No entries found")?;
+        return Ok(());
+    }
+    let names: Vec<String> = entries.iter().map(|e| display_name(&e.name)).collect();
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    writeln!(
+        w,
+        "  {:<width$}  {:>10}  {:>7}  {:>10}",
+        "", "size", "newest", "age"
+    )?;
+    for (name, entry) in names.iter().zip(entries) {
+        writeln!(
+            w,
+            "  {:<width$}  {:>10}  {:>7}  {:>5} days",
+            name,
+            locale::size(entry.size),
+            mtime_bucket(entry.modified, Period::Month),
+            locale::count(entry.age_days as u64),
+        )?;
+    }
+    writeln!(w)?;
+    writeln!(
+        w,
+        "Ranked by size x age of the newest file (showing top {})",
+        entries.len()
+    )?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonStale {
+    schema_version: u32,
+    entries: Vec<JsonStaleEntry>,
+}
+
+#[derive(Serialize)]
+struct JsonStaleEntry {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_base64: Option<String>,
+    size: u64,
+    file_count: u64,
+    /// Modification time of the newest file in seconds since the Unix
+    /// epoch; `null` when it couldn't be read.
+    newest_modified: Option<i64>,
+    age_days: f64,
+    score: f64,
+}
+
+pub fn write_json(w: &mut dyn Write, entries: &[StaleEntry]) -> Result<()> {
+    let output = JsonStale {
+        schema_version: SCHEMA_VERSION,
+        entries: entries
+            .iter()
+            .map(|e| JsonStaleEntry {
+                name: display_name(&e.name),
+                name_base64: raw_base64(e.name.as_os_str()),
+                size: e.size,
+                file_count: e.file_count,
+                newest_modified: e.modified.map(|m| match m.duration_since(UNIX_EPOCH) {
+                    Ok(d) => d.as_secs() as i64,
+                    Err(e) => -(e.duration().as_secs() as i64),
+                }),
+                age_days: e.age_days,
+                score: e.score,
+            })
+            .collect(),
+    };
+    writeln!(w, "{}", serde_json::to_string_pretty(&output)?)?;
+    Ok(())
+}
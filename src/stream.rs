@@ -0,0 +1,273 @@
+//! [`crate::scan_with`] as an async stream, for services that
+//! consume a walk from an executor (tokio or any other) without blocking
+//! one of its threads. The walk runs on a thread of its own and hands
+//! results over through a bounded queue, waking the task that polls.
+//!
+//! With the `futures` feature, [`ScanStream`] implements
+//! `futures_core::Stream`, for `StreamExt` and `tokio_stream`; without it,
+//! [`ScanStream::next`] and [`ScanStream::poll_next`] do the same job.
+
+use crate::{scan_with, Event, ScanOptions};
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Updates the walk may get ahead of the consumer by before it waits.
+const CAPACITY: usize = 1024;
+
+/// Files between two [`Update::Progress`].
+const PROGRESS_INTERVAL: u64 = 1000;
+
+/// What a [`ScanStream`] yields.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Update {
+    /// Files and bytes counted so far, every thousand files.
+    Progress { files: u64, bytes: u64 },
+    /// A directory's totals, once everything below it has been seen:
+    /// deepest first, the root (depth 0) last.
+    Dir {
+        path: PathBuf,
+        depth: usize,
+        size: u64,
+        file_count: u64,
+    },
+}
+
+#[derive(Default)]
+struct Queue {
+    updates: VecDeque<Result<Update>>,
+    finished: bool,
+    /// Set when the stream is dropped; the walk stops handing over updates.
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Signalled when the consumer makes room or goes away.
+    room: Condvar,
+}
+
+impl Shared {
+    /// Queues `update` for the consumer, waiting while the queue is full.
+    /// False once the consumer is gone.
+    fn push(&self, update: Result<Update>) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        while queue.updates.len() >= CAPACITY && !queue.closed {
+            queue = self.room.wait(queue).unwrap();
+        }
+        if queue.closed {
+            return false;
+        }
+        queue.updates.push_back(update);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+
+    fn finish(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.finished = true;
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A walk of `root` in progress; see [`ScanStream::new`].
+pub struct ScanStream {
+    shared: Arc<Shared>,
+}
+
+impl ScanStream {
+    /// Starts walking `root` on a new thread. The stream yields progress
+    /// and every directory's totals, then ends; a root that can't be read
+    /// yields one error instead. Dropping the stream stops the updates,
+    /// but the walk itself (which can't be interrupted) finishes in the
+    /// background.
+    pub fn new(root: PathBuf, options: ScanOptions) -> Self {
+        let shared = Arc::new(Shared::default());
+        let walk = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            let (mut files, mut bytes, mut open) = (0u64, 0u64, true);
+            let result = scan_with(&root, &options, |event| {
+                if !open {
+                    return;
+                }
+                let update = match event {
+                    Event::File { size, .. } => {
+                        files += 1;
+                        bytes += size;
+                        if files % PROGRESS_INTERVAL != 0 {
+                            return;
+                        }
+                        Update::Progress { files, bytes }
+                    }
+                    Event::DirComplete {
+                        path,
+                        depth,
+                        size,
+                        file_count,
+                    } => Update::Dir {
+                        path: path.to_path_buf(),
+                        depth,
+                        size,
+                        file_count,
+                    },
+                    Event::Entered { .. } => return,
+                };
+                open = walk.push(Ok(update));
+            });
+            if let Err(err) = result {
+                walk.push(Err(err));
+            }
+            walk.finish();
+        });
+        ScanStream { shared }
+    }
+
+    /// The next update, or `None` once the walk is done.
+    pub fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Update>>> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if let Some(update) = queue.updates.pop_front() {
+            self.shared.room.notify_one();
+            return Poll::Ready(Some(update));
+        }
+        if queue.finished {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// `poll_next` as a future: `while let Some(update) = stream.next().await`.
+    pub async fn next(&mut self) -> Option<Result<Update>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+#[cfg(feature = "futures")]
+impl futures_core::Stream for ScanStream {
+    type Item = Result<Update>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        ScanStream::poll_next(self, cx)
+    }
+}
+
+impl Drop for ScanStream {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.room.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    /// Runs `future` on this thread, parking until it's woken.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct Unpark(Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn streams_progress_and_directory_totals() {
+        let root = std::env::temp_dir().join(format!("diskhound-stream-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        for i in 0..1500 {
+            std::fs::write(root.join("a").join(i.to_string()), b"x").unwrap();
+        }
+        std::fs::write(root.join("top"), b"four").unwrap();
+
+        let updates = block_on(async {
+            let mut stream = ScanStream::new(root.clone(), ScanOptions::default());
+            let mut updates = Vec::new();
+            while let Some(update) = stream.next().await {
+                updates.push(update.unwrap());
+            }
+            updates
+        });
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(updates
+            .iter()
+            .any(|u| matches!(u, Update::Progress { files: 1000, .. })));
+        let dirs: Vec<_> = updates
+            .iter()
+            .filter_map(|u| match u {
+                Update::Dir {
+                    path,
+                    depth,
+                    size,
+                    file_count,
+                } => Some((
+                    path.strip_prefix(&root).unwrap(),
+                    *depth,
+                    *size,
+                    *file_count,
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            dirs,
+            [
+                (std::path::Path::new("a"), 1, 1500, 1500),
+                (std::path::Path::new(""), 0, 1504, 1501),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_root_is_one_error() {
+        let root = std::env::temp_dir().join("diskhound-stream-missing");
+        let mut stream = ScanStream::new(root, ScanOptions::default());
+        let first = block_on(stream.next());
+        assert!(matches!(first, Some(Err(_))));
+        assert!(block_on(stream.next()).is_none());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn is_a_futures_stream() {
+        fn next<S: futures_core::Stream + Unpin>(
+            stream: &mut S,
+        ) -> impl Future<Output = Option<S::Item>> + '_ {
+            std::future::poll_fn(move |cx| Pin::new(&mut *stream).poll_next(cx))
+        }
+        let root = std::env::temp_dir().join(format!("diskhound-futures-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        let mut stream = ScanStream::new(root.clone(), ScanOptions::default());
+        let mut depths = Vec::new();
+        while let Some(update) = block_on(next(&mut stream)) {
+            if let Update::Dir { depth, .. } = update.unwrap() {
+                depths.push(depth);
+            }
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(depths, [1, 0]);
+    }
+}
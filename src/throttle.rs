@@ -0,0 +1,34 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Paces work across threads to a shared budget of units (bytes read,
+/// filesystem operations) per second.
+pub struct RateLimiter {
+    per_sec: Option<u64>,
+    state: Mutex<(Instant, u64)>,
+}
+
+impl RateLimiter {
+    pub fn new(per_sec: Option<u64>) -> Self {
+        RateLimiter {
+            per_sec: per_sec.filter(|&r| r > 0),
+            state: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Blocks until `units` more fit in the budget.
+    pub fn acquire(&self, units: u64) {
+        let Some(rate) = self.per_sec else {
+            return;
+        };
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            state.1 += units;
+            let due = state.0 + Duration::from_secs_f64(state.1 as f64 / rate as f64);
+            due.saturating_duration_since(Instant::now())
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
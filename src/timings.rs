@@ -0,0 +1,72 @@
+use anyhow::Result;
+use humansize::{format_size, BINARY};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Where a scan spent its time, for `--timings`.
+pub struct Timings {
+    pub started: Instant,
+    /// Reading the config file and mount table before the walk.
+    pub setup: Duration,
+    /// The directory walk, including `stat`.
+    pub walk: Duration,
+    /// Time spent in `stat` calls during the walk.
+    pub stat: Duration,
+    /// Hashing, sorting, and rolling up results after the walk.
+    pub aggregate: Duration,
+    /// Files, directories, and other entries the walk returned.
+    pub entries: u64,
+}
+
+/// Writes the phases, throughput, and peak memory once the report has been
+/// rendered (which took `render`).
+pub fn write(w: &mut dyn Write, timings: &Timings, render: Duration) -> Result<()> {
+    let total = timings.started.elapsed();
+    let per_second = match timings.walk.as_secs_f64() {
+        0.0 => 0.0,
+        secs => timings.entries as f64 / secs,
+    };
+    writeln!(w, "Timings:")?;
+    writeln!(w, "  setup      {:>10.2?}", timings.setup)?;
+    writeln!(
+        w,
+        "  walk       {:>10.2?}  (reading directories {:.2?}, stat {:.2?})",
+        timings.walk,
+        timings.walk.saturating_sub(timings.stat),
+        timings.stat
+    )?;
+    writeln!(w, "  aggregate  {:>10.2?}", timings.aggregate)?;
+    writeln!(w, "  render     {render:>10.2?}")?;
+    writeln!(w, "  total      {total:>10.2?}")?;
+    writeln!(
+        w,
+        "  {} entries, {per_second:.0} entries/s",
+        timings.entries
+    )?;
+    if let Some(peak) = peak_memory() {
+        writeln!(w, "  peak memory {}", format_size(peak, BINARY))?;
+    }
+    Ok(())
+}
+
+/// The process's peak resident set size in bytes.
+#[cfg(unix)]
+fn peak_memory() -> Option<u64> {
+    // SAFETY: getrusage only writes to the struct passed in.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let maxrss = u64::try_from(usage.ru_maxrss).ok()?;
+    // Linux and the BSDs report KiB; macOS reports bytes.
+    if cfg!(target_os = "macos") {
+        Some(maxrss)
+    } else {
+        Some(maxrss * 1024)
+    }
+}
+
+#[cfg(not(unix))]
+fn peak_memory() -> Option<u64> {
+    None
+}
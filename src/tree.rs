@@ -0,0 +1,116 @@
+use crate::output::{self, DirStats, PercentOf, SortBy};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+/// A directory with its cumulative size, for tree-shaped output formats.
+pub struct Node {
+    pub name: OsString,
+    pub size: u64,
+    pub children: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Builder {
+    size: u64,
+    children: HashMap<OsString, Builder>,
+}
+
+impl Builder {
+    /// Sorts children by size, keeps the largest `top` at every level, and drops
+    /// nodes smaller than `min_size`.
+    fn finish(self, name: OsString, top: usize, min_size: u64) -> Node {
+        let mut children: Vec<_> = self
+            .children
+            .into_iter()
+            .filter(|(_, c)| c.size >= min_size)
+            .collect();
+        children.sort_by_key(|(_, c)| std::cmp::Reverse(c.size));
+        children.truncate(top);
+        Node {
+            name,
+            size: self.size,
+            children: children
+                .into_iter()
+                .map(|(name, c)| c.finish(name, top, min_size))
+                .collect(),
+        }
+    }
+}
+
+/// Builds the tree from the grouping keys. Each key's totals are added to
+/// every ancestor, so inner nodes are cumulative. The root's totals cover the
+/// whole scan, including files directly under it.
+pub fn build(
+    root_name: &OsStr,
+    dir_sizes: &HashMap<PathBuf, DirStats>,
+    total_size: u64,
+    top: usize,
+    min_size: u64,
+) -> Node {
+    let mut root = Builder {
+        size: total_size,
+        children: HashMap::new(),
+    };
+    for (key, stats) in dir_sizes {
+        let mut node = &mut root;
+        for part in key {
+            node = node.children.entry(part.to_os_string()).or_default();
+            node.size += stats.size;
+        }
+    }
+    root.finish(root_name.to_os_string(), top, min_size)
+}
+
+/// Rolls per-directory totals up so every directory also counts everything
+/// below it.
+pub fn cumulative(dir_sizes: &HashMap<PathBuf, DirStats>) -> HashMap<PathBuf, DirStats> {
+    let mut out: HashMap<PathBuf, DirStats> = HashMap::new();
+    for (key, stats) in dir_sizes {
+        for ancestor in key.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+            let entry = out.entry(ancestor.to_path_buf()).or_insert(DirStats {
+                size: 0,
+                file_count: 0,
+            });
+            entry.size += stats.size;
+            entry.file_count += stats.file_count;
+        }
+    }
+    out
+}
+
+/// Turns per-key totals into report entries. Without a depth limit (`depth`
+/// 0) every key is rolled up to include everything below it. Adds the
+/// `<files in .>` pseudo-entry, drops entries under `min_size`, and keeps the
+/// `top` largest by `sort`. Also returns the cumulative size of every directory as the
+/// `--percent-of parent` baseline, empty otherwise.
+pub fn rank(
+    mut dir_sizes: HashMap<PathBuf, DirStats>,
+    root_files: DirStats,
+    depth: usize,
+    percent_of: PercentOf,
+    min_size: u64,
+    sort: SortBy,
+    top: usize,
+) -> (Vec<(PathBuf, DirStats)>, HashMap<PathBuf, u64>) {
+    let rolled_up = (depth == 0 || percent_of == PercentOf::Parent).then(|| cumulative(&dir_sizes));
+    let parent_sizes: HashMap<PathBuf, u64> = match &rolled_up {
+        Some(rolled) if percent_of == PercentOf::Parent => {
+            rolled.iter().map(|(k, s)| (k.clone(), s.size)).collect()
+        }
+        _ => HashMap::new(),
+    };
+    if let (0, Some(rolled)) = (depth, rolled_up) {
+        dir_sizes = rolled;
+    }
+
+    if root_files.file_count > 0 || root_files.size > 0 {
+        dir_sizes.insert(PathBuf::from(output::ROOT_FILES), root_files);
+    }
+    dir_sizes.retain(|_, stats| stats.size >= min_size);
+
+    let mut sorted: Vec<_> = dir_sizes.into_iter().collect();
+    sort.sort(&mut sorted);
+    sorted.truncate(top);
+    (sorted, parent_sizes)
+}
@@ -0,0 +1,201 @@
+use crate::tree::Node;
+
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+/// Squarified treemap layout (Bruls, Huizing, van Wijk): splits `rect` into one
+/// rectangle per value, proportional in area, keeping aspect ratios near 1.
+/// Rectangles are returned in the order of `values`.
+pub fn squarify(values: &[u64], rect: Rect) -> Vec<Rect> {
+    let mut out = vec![
+        Rect {
+            x: rect.x,
+            y: rect.y,
+            w: 0.0,
+            h: 0.0,
+        };
+        values.len()
+    ];
+    let total: u64 = values.iter().sum();
+    if total == 0 || rect.w <= 0.0 || rect.h <= 0.0 {
+        return out;
+    }
+
+    let mut order: Vec<usize> = (0..values.len()).filter(|&i| values[i] > 0).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(values[i]));
+    let scale = rect.w * rect.h / total as f64;
+    let areas: Vec<f64> = order.iter().map(|&i| values[i] as f64 * scale).collect();
+
+    let mut free = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = free.w.min(free.h);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst(&areas[start..=end], side) <= worst(&areas[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let row_area: f64 = row.iter().sum();
+        if free.w >= free.h {
+            // Column along the left edge.
+            let col_w = row_area / free.h;
+            let mut y = free.y;
+            for (k, area) in row.iter().enumerate() {
+                let h = area / col_w;
+                out[order[start + k]] = Rect {
+                    x: free.x,
+                    y,
+                    w: col_w,
+                    h,
+                };
+                y += h;
+            }
+            free.x += col_w;
+            free.w -= col_w;
+        } else {
+            // Row along the top edge.
+            let row_h = row_area / free.w;
+            let mut x = free.x;
+            for (k, area) in row.iter().enumerate() {
+                let w = area / row_h;
+                out[order[start + k]] = Rect {
+                    x,
+                    y: free.y,
+                    w,
+                    h: row_h,
+                };
+                x += w;
+            }
+            free.y += row_h;
+            free.h -= row_h;
+        }
+        start = end;
+    }
+    out
+}
+
+/// Worst aspect ratio in a row of areas laid out along a side of length `side`.
+fn worst(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let s2 = sum * sum;
+    let w2 = side * side;
+    (w2 * max / s2).max(s2 / (w2 * min))
+}
+
+/// Lays out a node's children inside `rect`. The bytes not covered by the listed
+/// children (own files and pruned entries) keep their share of the area too,
+/// so the children aren't inflated to fill the parent.
+pub fn layout_children(node: &Node, rect: Rect) -> Vec<Rect> {
+    let mut values: Vec<u64> = node.children.iter().map(|c| c.size).collect();
+    let listed: u64 = values.iter().sum();
+    values.push(node.size.saturating_sub(listed));
+    let mut rects = squarify(&values, rect);
+    rects.pop();
+    rects
+}
+
+pub fn write_png(w: &mut dyn std::io::Write, tree: &Node) -> anyhow::Result<()> {
+    const WIDTH: u32 = 1600;
+    const HEIGHT: u32 = 1000;
+    const PADDING: f64 = 3.0;
+
+    struct Canvas {
+        pixels: Vec<u8>,
+    }
+
+    impl Canvas {
+        fn fill(&mut self, r: Rect, color: [u8; 3]) {
+            let x0 = r.x.round().max(0.0) as u32;
+            let y0 = r.y.round().max(0.0) as u32;
+            let x1 = ((r.x + r.w).round() as u32).min(WIDTH);
+            let y1 = ((r.y + r.h).round() as u32).min(HEIGHT);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let i = ((y * WIDTH + x) * 3) as usize;
+                    self.pixels[i..i + 3].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    fn draw(canvas: &mut Canvas, node: &Node, rect: Rect, hue: f64, depth: usize) {
+        if rect.w < 1.0 || rect.h < 1.0 {
+            return;
+        }
+        // Dark outline, then the body inset by one pixel.
+        canvas.fill(rect, hsv(hue, 0.6, 0.25));
+        let body = Rect {
+            x: rect.x + 1.0,
+            y: rect.y + 1.0,
+            w: rect.w - 2.0,
+            h: rect.h - 2.0,
+        };
+        let value = (0.55 + 0.1 * depth as f64).min(0.95);
+        canvas.fill(body, hsv(hue, 0.55, value));
+
+        let inner = Rect {
+            x: body.x + PADDING,
+            y: body.y + PADDING,
+            w: body.w - 2.0 * PADDING,
+            h: body.h - 2.0 * PADDING,
+        };
+        for (child, r) in node.children.iter().zip(layout_children(node, inner)) {
+            draw(canvas, child, r, hue, depth + 1);
+        }
+    }
+
+    let mut canvas = Canvas {
+        pixels: vec![0x20; (WIDTH * HEIGHT * 3) as usize],
+    };
+    let full = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: WIDTH as f64,
+        h: HEIGHT as f64,
+    };
+    // Each top-level branch gets its own hue, spread by the golden ratio.
+    for (i, (child, r)) in tree
+        .children
+        .iter()
+        .zip(layout_children(tree, full))
+        .enumerate()
+    {
+        let hue = (i as f64 * 0.618_033_988_75) % 1.0;
+        draw(&mut canvas, child, r, hue, 0);
+    }
+
+    let mut encoder = png::Encoder::new(w, WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&canvas.pixels)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn hsv(h: f64, s: f64, v: f64) -> [u8; 3] {
+    let i = (h * 6.0).floor();
+    let f = h * 6.0 - i;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - f * s);
+    let t = v * (1.0 - (1.0 - f) * s);
+    let (r, g, b) = match i as i64 % 6 {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    };
+    [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+}
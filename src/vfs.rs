@@ -0,0 +1,102 @@
+//! Filesystems for [`scan_fs_with`](crate::scan_fs_with): anything that can
+//! list a directory, such as a browser's File System Access API handles
+//! behind a wasm32 build, or [`MemoryFs`] rebuilt from an uploaded listing.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Dir,
+    File,
+    /// Symlinks, devices, and the like, which the walk skips.
+    Other,
+}
+
+/// One directory entry: its name, what it is, and for files their size.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub name: OsString,
+    pub kind: Kind,
+    pub size: u64,
+}
+
+pub trait FileSystem {
+    /// The entries directly in directory `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<Entry>>;
+}
+
+/// A tree held in memory, e.g. from a `--files` listing of paths and sizes.
+/// Directories are implied by the files below them.
+#[derive(Default)]
+pub struct MemoryFs {
+    dirs: BTreeMap<PathBuf, BTreeMap<OsString, (Kind, u64)>>,
+}
+
+impl MemoryFs {
+    /// Builds the tree from file paths (relative to the root, which is the
+    /// empty path) and sizes. A file listed twice keeps its last size.
+    pub fn from_files(files: impl IntoIterator<Item = (PathBuf, u64)>) -> MemoryFs {
+        let mut fs = MemoryFs::default();
+        fs.dirs.insert(PathBuf::new(), BTreeMap::new());
+        for (path, size) in files {
+            let path: PathBuf = path
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect();
+            let Some(name) = path.file_name().map(OsString::from) else {
+                continue;
+            };
+            let mut parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            fs.dirs
+                .entry(parent.clone())
+                .or_default()
+                .insert(name, (Kind::File, size));
+            while let Some(name) = parent.file_name().map(OsString::from) {
+                parent.pop();
+                fs.dirs
+                    .entry(parent.clone())
+                    .or_default()
+                    .insert(name, (Kind::Dir, 0));
+            }
+        }
+        // Every listed directory needs an entry of its own.
+        let implied: Vec<PathBuf> = fs
+            .dirs
+            .iter()
+            .flat_map(|(dir, entries)| {
+                entries
+                    .iter()
+                    .filter(|(_, (kind, _))| *kind == Kind::Dir)
+                    .map(move |(name, _)| dir.join(name))
+            })
+            .collect();
+        for dir in implied {
+            fs.dirs.entry(dir).or_default();
+        }
+        fs
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<Entry>> {
+        let path: PathBuf = path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        let entries = self
+            .dirs
+            .get(&path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such directory"))?;
+        Ok(entries
+            .iter()
+            .map(|(name, (kind, size))| Entry {
+                name: name.clone(),
+                kind: *kind,
+                size: *size,
+            })
+            .collect())
+    }
+}
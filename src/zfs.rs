@@ -0,0 +1,123 @@
+use crate::mounts;
+use std::path::{Path, PathBuf};
+
+/// A ZFS dataset the scan walked into, with what `zfs list` says about it so
+/// the walked size can be reconciled with ZFS's own accounting.
+pub struct Dataset {
+    /// The dataset name, e.g. `tank/home`.
+    pub name: String,
+    /// Its mount point relative to the scanned root; empty for the dataset
+    /// the root itself is on.
+    pub path: PathBuf,
+    /// Bytes the walk counted on it (apparent sizes).
+    pub size: u64,
+    /// From `zfs list`; `None` where it couldn't be run.
+    pub properties: Option<Properties>,
+}
+
+/// Sizes as `zfs list -p` reports them.
+#[derive(Clone, Copy)]
+pub struct Properties {
+    /// Space the dataset and its snapshots and children take up.
+    pub used: u64,
+    /// Space its own data takes up on disk, after compression; shared with
+    /// snapshots or not.
+    pub referenced: u64,
+    /// `referenced` before compression, which is what the walk counts.
+    pub logical_referenced: u64,
+}
+
+impl Properties {
+    /// How much compression saves, as `zfs get compressratio` shows it.
+    pub fn compress_ratio(&self) -> f64 {
+        if self.referenced == 0 {
+            1.0
+        } else {
+            self.logical_referenced as f64 / self.referenced as f64
+        }
+    }
+}
+
+/// The ZFS datasets mounted at or below `root` (canonical), and the one
+/// `root` is on, each with its `zfs list` sizes. Empty off ZFS, and outside
+/// Linux where the mount table isn't read.
+pub fn datasets(root: &Path) -> Vec<Dataset> {
+    let all = mounts::list(&mounts::mountinfo());
+    let enclosing = all
+        .iter()
+        .filter(|m| root.starts_with(&m.point))
+        .max_by_key(|m| m.point.components().count());
+    let mut found: Vec<Dataset> = enclosing
+        .into_iter()
+        .chain(
+            all.iter()
+                .filter(|m| m.point.starts_with(root) && m.point != root),
+        )
+        .filter(|m| m.fstype == "zfs")
+        .map(|m| Dataset {
+            name: m.source.clone(),
+            path: m
+                .point
+                .strip_prefix(root)
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+            size: 0,
+            properties: None,
+        })
+        .collect();
+    if found.is_empty() {
+        return found;
+    }
+    // Parents before the datasets mounted inside them.
+    found.sort_by_key(|d| d.path.components().count());
+    let names: Vec<&str> = found.iter().map(|d| d.name.as_str()).collect();
+    let properties = list(&names);
+    for dataset in &mut found {
+        dataset.properties = properties
+            .iter()
+            .find(|(name, _)| *name == dataset.name)
+            .map(|(_, p)| *p);
+    }
+    found
+}
+
+/// `zfs list` for `names`, which needs no privileges.
+fn list(names: &[&str]) -> Vec<(String, Properties)> {
+    let output = match std::process::Command::new("zfs")
+        .args([
+            "list",
+            "-Hp",
+            "-o",
+            "name,used,referenced,logicalreferenced",
+        ])
+        .args(names)
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => {
+            tracing::info!("couldn't run zfs: {err}");
+            return Vec::new();
+        }
+    };
+    if !output.status.success() {
+        tracing::info!(
+            "zfs list failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    // Rows are tab-separated, without a header.
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.to_string();
+            let mut next = || fields.next()?.parse::<u64>().ok();
+            let properties = Properties {
+                used: next()?,
+                referenced: next()?,
+                logical_referenced: next()?,
+            };
+            Some((name, properties))
+        })
+        .collect()
+}